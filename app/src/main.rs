@@ -1,19 +1,279 @@
-use std::{fs::File, io::Read, path::Path};
+mod repl;
+
+use std::{
+    io::{BufWriter, Read},
+    os::unix::process::ExitStatusExt,
+    path::{Path, PathBuf},
+    process,
+};
 
 use anyhow::{anyhow, Result};
-use clap::{Args, Parser};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 
 #[derive(Parser, Debug)]
-#[command(author, version, about)]
+#[command(
+    author,
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    // without a subcommand the arguments are the ones of `build`
+    #[command(flatten)]
+    build: BuildArgs,
+
+    /// Print the long description of an error code, e.g. E0002
+    #[arg(long, value_name = "CODE", exclusive = true)]
+    explain: Option<String>,
+}
+
+impl Cli {
+    /// How the errors of the command are printed.
+    fn message_format(&self) -> MessageFormat {
+        match &self.command {
+            Some(Command::Check { source_options, .. })
+            | Some(Command::Run { source_options, .. })
+            | Some(Command::Emit { source_options, .. })
+            | Some(Command::Build(BuildArgs { source_options, .. })) => {
+                source_options.message_format
+            }
+            Some(Command::Fmt { .. }) | Some(Command::Repl { .. }) => MessageFormat::Human,
+            None => self.build.source_options.message_format,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+enum MessageFormat {
+    Human,
+    /// One JSON object per line
+    Json,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Parse and analyse the program without generating code
+    Check {
+        #[command(flatten)]
+        source_options: SourceOptions,
+
+        /// The program file, `-` to read the program from stdin
+        file: String,
+    },
+    /// Compile the program into an executable
+    Build(BuildArgs),
+    /// Compile the program into a temporary executable and run it
+    Run {
+        /// Interpret the program instead of compiling it
+        #[arg(long)]
+        interpret: bool,
+
+        #[command(flatten)]
+        translation_options: TranslationOptions,
+
+        #[command(flatten)]
+        source_options: SourceOptions,
+
+        /// The program file, `-` to read the program from stdin
+        file: String,
+
+        /// Arguments passed to the program
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
+    /// Format the program files in place
+    Fmt {
+        /// Don't write the files, list the ones which are not formatted and fail if there are any
+        #[arg(long)]
+        check: bool,
+
+        /// The program files, `-` to format stdin to stdout
+        #[arg(required = true)]
+        files: Vec<String>,
+    },
+    /// Print the program at a stage of the compilation
+    Emit {
+        /// The stage to stop at
+        #[arg(long, value_enum)]
+        stage: Stage,
+
+        /// Place the output into FILE instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<String>,
+
+        #[command(flatten)]
+        translation_options: TranslationOptions,
+
+        #[command(flatten)]
+        source_options: SourceOptions,
+
+        /// The program file, `-` to read the program from stdin
+        file: String,
+    },
+    /// Execute lines interactively on a persistent stack
+    Repl {
+        /// Don't include the standard prelude
+        #[arg(long)]
+        no_prelude: bool,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Stage {
+    /// The syntax tree of the file as written
+    Ast,
+    /// The syntax tree as JSON
+    AstJson,
+    /// The program after includes, macros, the prelude and `const-eval`
+    Ir,
+    /// The program after includes, macros, the prelude and `const-eval` as JSON
+    IrJson,
+    /// The generated assembly
+    Asm,
+    /// The assembled object file, `output.o` unless `-o` is given
+    Obj,
+}
+
+#[derive(Args, Debug)]
+struct BuildArgs {
     #[command(flatten)]
     compilation_options: CompilationOptions,
 
+    /// Print the program at STAGE instead of building it, the same as `plc emit --stage`
+    #[arg(
+        long,
+        value_enum,
+        value_name = "STAGE",
+        hide_possible_values = true,
+        conflicts_with_all = ["compile_only", "assemble_only"]
+    )]
+    emit: Option<Stage>,
+
     /// Place the output file into FILE
     #[arg(short, long, value_name = "FILE")]
     output: Option<String>,
 
-    file: String,
+    #[command(flatten)]
+    translation_options: TranslationOptions,
+
+    #[command(flatten)]
+    source_options: SourceOptions,
+
+    #[arg(required = true)]
+    file: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct TranslationOptions {
+    /// Abort the program with an error if `&` can't read a number
+    #[arg(long)]
+    strict_scan: bool,
+
+    /// Insert runtime checks into the program, e.g. array bounds checking
+    #[arg(long)]
+    debug_checks: bool,
+
+    /// Generate DWARF debug info, so a debugger shows the lines of the program
+    #[arg(short = 'g')]
+    debug_info: bool,
+}
+
+impl From<TranslationOptions> for lib::TranslationOptions {
+    fn from(options: TranslationOptions) -> lib::TranslationOptions {
+        lib::TranslationOptions {
+            strict_scan: options.strict_scan,
+            debug_checks: options.debug_checks,
+            debug_info: options.debug_info,
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+struct SourceOptions {
+    /// Don't include the standard prelude
+    #[arg(long)]
+    no_prelude: bool,
+
+    /// Add DIR to the list of directories searched for included files
+    #[arg(short = 'I', value_name = "DIR")]
+    include_dirs: Vec<PathBuf>,
+
+    #[command(flatten)]
+    lint_options: LintOptions,
+
+    /// How to print the errors
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = MessageFormat::Human,
+        hide_possible_values = true,
+        value_name = "human|json"
+    )]
+    message_format: MessageFormat,
+}
+
+impl SourceOptions {
+    /// With line info the loaded program keeps the lines of its terms for the debug info.
+    fn into_frontend(self, current_dir: &Path, line_info: bool) -> Result<Frontend> {
+        let include_dirs = self
+            .include_dirs
+            .into_iter()
+            .map(|x| current_dir.join(x))
+            .collect();
+
+        Ok(Frontend {
+            loader: lib::Loader::new(include_dirs, !self.no_prelude).with_line_info(line_info),
+            lint_levels: self.lint_options.into_levels()?,
+            prelude: !self.no_prelude,
+            message_format: self.message_format,
+        })
+    }
+}
+
+/// A lint name, or `warnings` for all of them. A single lint takes precedence over
+/// `warnings`; with both given for a lint `-D` wins over `-W`, and `-W` over `-A`.
+#[derive(Args, Debug)]
+struct LintOptions {
+    /// Warn about LINT
+    #[arg(short = 'W', long = "warn", value_name = "LINT")]
+    warn: Vec<String>,
+
+    /// Allow LINT
+    #[arg(short = 'A', long = "allow", value_name = "LINT")]
+    allow: Vec<String>,
+
+    /// Report LINT as an error, e.g. `-D warnings`
+    #[arg(short = 'D', long = "deny", value_name = "LINT")]
+    deny: Vec<String>,
+}
+
+impl LintOptions {
+    fn into_levels(self) -> Result<lib::LintLevels> {
+        let mut levels = lib::LintLevels::default();
+        for (names, level) in [
+            (self.allow, lib::Level::Allow),
+            (self.warn, lib::Level::Warn),
+            (self.deny, lib::Level::Deny),
+        ] {
+            for name in names {
+                levels.set(&name, level)?;
+            }
+        }
+
+        Ok(levels)
+    }
+}
+
+/// Everything needed to load a program and report the lints found in it.
+struct Frontend {
+    loader: lib::Loader,
+    lint_levels: lib::LintLevels,
+    prelude: bool,
+    message_format: MessageFormat,
 }
 
 #[derive(Args, Debug)]
@@ -34,43 +294,329 @@ enum OpMode {
     All,
 }
 
-fn main() -> Result<()> {
+fn main() {
     let cli = Cli::parse();
+    let message_format = cli.message_format();
+
+    if let Err(e) = execute(cli) {
+        report(&lib::Diagnostic::from_error(&e), message_format);
+        process::exit(1);
+    }
+}
+
+fn report(diagnostic: &lib::Diagnostic, message_format: MessageFormat) {
+    match message_format {
+        MessageFormat::Human => eprintln!("{}", diagnostic),
+        MessageFormat::Json => match serde_json::to_string(diagnostic) {
+            Ok(json) => eprintln!("{}", json),
+            Err(_) => eprintln!("{}", diagnostic),
+        },
+    }
+}
+
+fn execute(cli: Cli) -> Result<()> {
+    if let Some(code) = cli.explain {
+        let explanation =
+            lib::explain(&code).ok_or_else(|| anyhow!("`{}` is not an error code", code))?;
+        print!("{}", explanation);
+        return Ok(());
+    }
+
+    let current_dir = std::env::current_dir()?;
+
+    match cli.command {
+        Some(Command::Check {
+            source_options,
+            file,
+        }) => {
+            let mut frontend = source_options.into_frontend(&current_dir, false)?;
+            let source = read_source(&current_dir, &file)?;
+            load_text(&mut frontend, &current_dir, &file, &source)?;
+
+            check_stack(&frontend, &file, &source)
+        }
+        Some(Command::Build(build)) => build_program(build, &current_dir),
+        Some(Command::Run {
+            interpret,
+            translation_options,
+            source_options,
+            file,
+            args,
+        }) => {
+            let translation_options: lib::TranslationOptions = translation_options.into();
+            let mut frontend =
+                source_options.into_frontend(&current_dir, translation_options.debug_info)?;
+            let ast = load(&mut frontend, &current_dir, &file)?;
 
-    let op_mode = if cli.compilation_options.assemble_only {
+            if interpret {
+                run_interpreted(&ast, &translation_options, frontend.message_format)
+            } else {
+                run(&ast, &translation_options, &args)
+            }
+        }
+        Some(Command::Fmt { check, files }) => format(&files, check, &current_dir),
+        Some(Command::Emit {
+            stage,
+            output,
+            translation_options,
+            source_options,
+            file,
+        }) => {
+            let translation_options: lib::TranslationOptions = translation_options.into();
+            let mut frontend =
+                source_options.into_frontend(&current_dir, translation_options.debug_info)?;
+            emit(
+                stage,
+                &mut frontend,
+                &translation_options,
+                &current_dir,
+                &file,
+                output.map(|x| current_dir.join(x)).as_deref(),
+            )
+        }
+        Some(Command::Repl { no_prelude }) => repl::repl(!no_prelude),
+        None => build_program(cli.build, &current_dir),
+    }
+}
+
+fn build_program(build: BuildArgs, current_dir: &Path) -> Result<()> {
+    let file = build.file.unwrap_or_default();
+    let translation_options: lib::TranslationOptions = build.translation_options.into();
+    let mut frontend = build
+        .source_options
+        .into_frontend(current_dir, translation_options.debug_info)?;
+
+    if let Some(stage) = build.emit {
+        return emit(
+            stage,
+            &mut frontend,
+            &translation_options,
+            current_dir,
+            &file,
+            build.output.map(|x| current_dir.join(x)).as_deref(),
+        );
+    }
+
+    let op_mode = if build.compilation_options.assemble_only {
         OpMode::AssembleOnly
-    } else if cli.compilation_options.compile_only {
+    } else if build.compilation_options.compile_only {
         OpMode::CompileOnly
     } else {
         OpMode::All
     };
 
-    let current_dir = std::env::current_dir()?;
-    let input_file_path = current_dir.join(cli.file);
-    let output_file_path = current_dir.join(match cli.output {
+    let output_file_path = current_dir.join(match build.output {
         Some(output_file) => output_file,
         None => "output".to_string(),
     });
 
+    let ast = load(&mut frontend, current_dir, &file)?;
+
     perform(
         op_mode,
-        input_file_path.as_path(),
+        &translation_options,
+        &ast,
         output_file_path.as_path(),
     )
 }
 
-fn perform(op_mode: OpMode, input_file_path: &Path, output_file_path: &Path) -> Result<()> {
+/// Reads the program source from the file, or from stdin if the file is `-`.
+fn read_source(current_dir: &Path, file: &str) -> Result<String> {
+    if file == "-" {
+        let mut source = String::new();
+        std::io::stdin().read_to_string(&mut source)?;
+        Ok(source)
+    } else {
+        let path = current_dir.join(file);
+        std::fs::read_to_string(&path).map_err(|e| anyhow!("can't read {}: {}", path.display(), e))
+    }
+}
+
+/// The name of the file in the diagnostics.
+fn file_name(file: &str) -> &str {
+    if file == "-" {
+        "<stdin>"
+    } else {
+        file
+    }
+}
+
+/// Formats the files in place, or stdin to stdout for `-`.
+/// With `check` only prints the names of the files which are not formatted.
+fn format(files: &[String], check: bool, current_dir: &Path) -> Result<()> {
+    let mut unformatted = 0;
+
+    for file in files {
+        let source = read_source(current_dir, file)?;
+        let formatted =
+            lib::format(&source).map_err(|e| e.to_diagnostic().in_file(file_name(file)))?;
+
+        if check {
+            if formatted != source {
+                println!("{}", file_name(file));
+                unformatted += 1;
+            }
+        } else if file == "-" {
+            print!("{}", formatted);
+        } else if formatted != source {
+            std::fs::write(current_dir.join(file), formatted)?;
+        }
+    }
+
+    if unformatted > 0 {
+        return Err(anyhow!(
+            "{} file{} not formatted",
+            unformatted,
+            if unformatted == 1 { " is" } else { "s are" }
+        ));
+    }
+
+    Ok(())
+}
+
+fn emit(
+    stage: Stage,
+    frontend: &mut Frontend,
+    translation_options: &lib::TranslationOptions,
+    current_dir: &Path,
+    file: &str,
+    output_file_path: Option<&Path>,
+) -> Result<()> {
+    let text = match stage {
+        Stage::Ast | Stage::AstJson => {
+            let source = read_source(current_dir, file)?;
+            let ast =
+                lib::parse(&source).map_err(|e| e.to_diagnostic().in_file(file_name(file)))?;
+            dump(&ast, matches!(stage, Stage::AstJson))?
+        }
+        Stage::Ir | Stage::IrJson => {
+            let ast = load(frontend, current_dir, file)?;
+            dump(&ast, matches!(stage, Stage::IrJson))?
+        }
+        Stage::Asm => {
+            let ast = load(frontend, current_dir, file)?;
+            lib::translate(&ast, translation_options)?.into_assembly()
+        }
+        Stage::Obj => {
+            let ast = load(frontend, current_dir, file)?;
+            let output_file_path = output_file_path
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|| current_dir.join("output.o"));
+
+            return perform(
+                OpMode::AssembleOnly,
+                translation_options,
+                &ast,
+                &output_file_path,
+            );
+        }
+    };
+
+    match output_file_path {
+        Some(path) => std::fs::write(path, text)?,
+        None => print!("{}", text),
+    }
+
+    Ok(())
+}
+
+fn dump(ast: &lib::Ast, json: bool) -> Result<String> {
+    if json {
+        Ok(serde_json::to_string_pretty(ast)? + "\n")
+    } else {
+        Ok(ast.to_tree())
+    }
+}
+
+/// Loads the program from the file, or from stdin if the file is `-`,
+/// and reports the lints found in the file. Included modules are not linted.
+fn load(frontend: &mut Frontend, current_dir: &Path, file: &str) -> Result<lib::Ast> {
+    let source = read_source(current_dir, file)?;
+    load_text(frontend, current_dir, file, &source)
+}
+
+/// Loads a program whose text is already read, the text is linted.
+fn load_text(
+    frontend: &mut Frontend,
+    current_dir: &Path,
+    file: &str,
+    source: &str,
+) -> Result<lib::Ast> {
+    let ast = if file == "-" {
+        frontend.loader.load_source(source, current_dir)?
+    } else {
+        frontend
+            .loader
+            .load_file(current_dir.join(file).as_path())?
+    };
+
+    let diagnostics = lib::lint(source, &frontend.lint_levels, frontend.prelude)
+        .map_err(|e| e.to_diagnostic().in_file(file_name(file)))?;
+    for diagnostic in &diagnostics {
+        report(
+            &diagnostic.clone().in_file(file_name(file)),
+            frontend.message_format,
+        );
+    }
+
+    let denied = diagnostics
+        .iter()
+        .filter(|x| x.severity == lib::Severity::Error)
+        .count();
+    if denied > 0 {
+        return Err(anyhow!(
+            "could not compile `{}` because of {} denied lint{}",
+            file_name(file),
+            denied,
+            if denied == 1 { "" } else { "s" }
+        ));
+    }
+
+    Ok(ast)
+}
+
+/// Follows the stack depth through the program and reports the term which underflows it.
+fn check_stack(frontend: &Frontend, file: &str, source: &str) -> Result<()> {
+    let nodes = lib::parse_nodes(source).map_err(|e| e.to_diagnostic().in_file(file_name(file)))?;
+    let prelude = if frontend.prelude {
+        lib::parse_nodes(lib::PRELUDE).map_err(|e| anyhow!("prelude: {}", e))?
+    } else {
+        Vec::new()
+    };
+
+    match lib::stack_underflow(&nodes, &prelude) {
+        Some(span) => Err(lib::CompilerError::StackUnderflow
+            .to_diagnostic()
+            .at(source, span)
+            .in_file(file_name(file))
+            .into()),
+        None => Ok(()),
+    }
+}
+
+fn perform(
+    op_mode: OpMode,
+    translation_options: &lib::TranslationOptions,
+    ast: &lib::Ast,
+    output_file_path: &Path,
+) -> Result<()> {
     match op_mode {
         OpMode::CompileOnly => {
-            compile(input_file_path, output_file_path)?;
+            compile(ast, translation_options, output_file_path)?;
         }
         OpMode::AssembleOnly => {
             lib::check_tmp_dir()?;
 
             let asm_tmp_path = lib::make_tmp_path();
 
-            let assemble_result = compile(input_file_path, asm_tmp_path.as_path())
-                .and_then(|_| assemble(asm_tmp_path.as_path(), output_file_path));
+            let assemble_result = compile(ast, translation_options, asm_tmp_path.as_path())
+                .and_then(|_| {
+                    assemble(
+                        asm_tmp_path.as_path(),
+                        output_file_path,
+                        translation_options.debug_info,
+                    )
+                });
 
             let _ = std::fs::remove_file(asm_tmp_path);
 
@@ -84,9 +630,15 @@ fn perform(op_mode: OpMode, input_file_path: &Path, output_file_path: &Path) ->
             let stdlib_tmp_path = lib::make_tmp_path(); // TODO: precompile
 
             let compilation_result = {
-                compile(input_file_path, asm_tmp_path.as_path())
+                compile(ast, translation_options, asm_tmp_path.as_path())
                     .and_then(|_| assemble_stdlib(stdlib_tmp_path.as_path()))
-                    .and_then(|_| assemble(asm_tmp_path.as_path(), object_tmp_path.as_path()))
+                    .and_then(|_| {
+                        assemble(
+                            asm_tmp_path.as_path(),
+                            object_tmp_path.as_path(),
+                            translation_options.debug_info,
+                        )
+                    })
                     .and_then(|_| {
                         link(
                             &[object_tmp_path.as_path(), stdlib_tmp_path.as_path()],
@@ -106,32 +658,87 @@ fn perform(op_mode: OpMode, input_file_path: &Path, output_file_path: &Path) ->
     Ok(())
 }
 
-fn compile(input_file_path: &Path, output_file_path: &Path) -> Result<()> {
-    // TODO: move asm read functionality to lib
-    let mut input = String::new();
-    File::open(input_file_path)?.read_to_string(&mut input)?;
-
-    let ast = lib::parse(input.as_str()).map_err(|e| anyhow!(e.to_string()))?;
-    let asm = lib::translate(&ast);
+fn compile(
+    ast: &lib::Ast,
+    translation_options: &lib::TranslationOptions,
+    output_file_path: &Path,
+) -> Result<()> {
+    let asm = lib::translate(ast, translation_options)?;
     lib::make_asm_file(asm, output_file_path)?;
 
     Ok(())
 }
 
+/// Builds a temporary executable, runs it with the inherited stdin and stdout
+/// and exits with its exit code.
+fn run(
+    ast: &lib::Ast,
+    translation_options: &lib::TranslationOptions,
+    args: &[String],
+) -> Result<()> {
+    lib::check_tmp_dir()?;
+
+    let executable_tmp_path = lib::make_tmp_path();
+
+    let run_result = perform(
+        OpMode::All,
+        translation_options,
+        ast,
+        executable_tmp_path.as_path(),
+    )
+    .and_then(|_| {
+        Ok(process::Command::new(&executable_tmp_path)
+            .args(args)
+            .status()?)
+    });
+
+    let _ = std::fs::remove_file(executable_tmp_path);
+
+    let status = run_result?;
+    // a program killed by a signal exits like it does in a shell
+    let code = status
+        .code()
+        .or(status.signal().map(|signal| 128 + signal))
+        .unwrap_or(1);
+
+    process::exit(code)
+}
+
+fn run_interpreted(
+    ast: &lib::Ast,
+    translation_options: &lib::TranslationOptions,
+    message_format: MessageFormat,
+) -> Result<()> {
+    let mut input = std::io::stdin().lock();
+    let mut output = BufWriter::new(std::io::stdout().lock());
+
+    // runtime errors are reported the same way as by a compiled program
+    if let Err(e) = lib::interpret(ast, translation_options, &mut input, &mut output) {
+        match message_format {
+            MessageFormat::Human => eprintln!("plc: {}", e),
+            MessageFormat::Json => report(&lib::Diagnostic::from_error(&e), message_format),
+        }
+        process::exit(1);
+    }
+
+    Ok(())
+}
+
 fn assemble_stdlib(output_file_path: &Path) -> Result<()> {
     let stdlib = lib::make_std_lib();
     let asm_tmp_path = lib::make_tmp_path();
 
-    let assemble_result = lib::make_asm_file(stdlib, asm_tmp_path.as_path())
-        .and_then(|_| lib::make_object_file(asm_tmp_path.as_path(), output_file_path).map(|_| {}));
+    let assemble_result = lib::make_asm_file(stdlib, asm_tmp_path.as_path()).and_then(|_| {
+        lib::make_object_file(asm_tmp_path.as_path(), output_file_path, false).map(|_| {})
+    });
 
     let _ = std::fs::remove_file(asm_tmp_path);
 
     assemble_result
 }
 
-fn assemble(input_file_path: &Path, output_file_path: &Path) -> Result<()> {
-    lib::make_object_file(input_file_path, output_file_path).map(|_| {})
+fn assemble(input_file_path: &Path, output_file_path: &Path, debug_info: bool) -> Result<()> {
+    lib::make_object_file(input_file_path, output_file_path, debug_info).map(|_| {})
 }
 
 fn link(input_file_paths: &[&Path], output_file_path: &Path) -> Result<()> {
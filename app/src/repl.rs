@@ -0,0 +1,146 @@
+use std::{
+    collections::HashMap,
+    io::{BufWriter, Write},
+    rc::Rc,
+};
+
+use anyhow::{anyhow, Result};
+use rustyline::{error::ReadlineError, DefaultEditor};
+
+const PROMPT: &str = "> ";
+const CONTINUATION_PROMPT: &str = ". ";
+
+const HELP: &str = "\
+:bindings  list the bound names
+:clear     drop everything from the stack
+:help      show this message
+Ctrl-D     exit";
+
+/// Reads lines and executes them on a stack which persists between lines.
+/// A line with an unclosed `[` is continued on the next one.
+pub fn repl(prelude: bool) -> Result<()> {
+    let mut editor = DefaultEditor::new()?;
+    let mut interpreter = lib::Interpreter::new();
+
+    let mut prelude_words = HashMap::new();
+    if prelude {
+        let ast = lib::parse(lib::PRELUDE).map_err(|e| anyhow!(e.to_string()))?;
+        interpreter.run(&ast.terms)?;
+        prelude_words = interpreter.bindings().clone();
+    }
+
+    let mut source = String::new();
+    loop {
+        let prompt = if source.is_empty() {
+            PROMPT
+        } else {
+            CONTINUATION_PROMPT
+        };
+        let line = match editor.readline(prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => {
+                source.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+
+        if source.is_empty() {
+            match line.trim() {
+                "" => continue,
+                ":bindings" => {
+                    editor.add_history_entry(line.as_str())?;
+                    print_bindings(&interpreter, &prelude_words);
+                    continue;
+                }
+                ":clear" => {
+                    editor.add_history_entry(line.as_str())?;
+                    interpreter.clear();
+                    continue;
+                }
+                ":help" => {
+                    println!("{}", HELP);
+                    continue;
+                }
+                _ => {}
+            }
+        }
+
+        source.push_str(&line);
+        source.push('\n');
+        if unclosed_lists(&source) {
+            continue;
+        }
+
+        editor.add_history_entry(source.trim_end())?;
+        execute(&mut interpreter, &source);
+        source.clear();
+    }
+}
+
+fn execute(interpreter: &mut lib::Interpreter, source: &str) {
+    let ast = match lib::parse(source) {
+        Ok(ast) => ast,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+
+    let mut input = std::io::stdin().lock();
+    let mut output = BufWriter::new(std::io::stdout().lock());
+    let mut io = lib::Io {
+        input: &mut input,
+        output: &mut output,
+    };
+
+    // the stack is kept as the failed line left it
+    if let Err(e) = interpreter.run_with_io(&ast.terms, &mut io) {
+        eprintln!("error: {}", e);
+    }
+
+    let stack = interpreter.stack();
+    let _ = write!(output, "<{}>", stack.len());
+    for value in stack {
+        let _ = write!(output, " {}", value);
+    }
+    let _ = writeln!(output);
+}
+
+/// Prints the names bound by the user, the prelude words are shown only if redefined.
+fn print_bindings(interpreter: &lib::Interpreter, prelude_words: &HashMap<String, lib::Value>) {
+    let mut bindings: Vec<_> = interpreter
+        .bindings()
+        .iter()
+        .filter(|(name, value)| match (value, prelude_words.get(*name)) {
+            (lib::Value::Quotation(x), Some(lib::Value::Quotation(y))) => !Rc::ptr_eq(x, y),
+            _ => true,
+        })
+        .collect();
+    bindings.sort_by(|a, b| a.0.cmp(b.0));
+
+    for (name, value) in bindings {
+        println!("{} = {}", name, value);
+    }
+}
+
+/// Whether there are more `[` than `]`, skipping comments and string literals.
+fn unclosed_lists(source: &str) -> bool {
+    let mut depth = 0;
+
+    for line in source.lines() {
+        let mut in_string = false;
+        for c in line.chars() {
+            match c {
+                '"' => in_string = !in_string,
+                '#' if !in_string => break,
+                '[' if !in_string => depth += 1,
+                ']' if !in_string => depth -= 1,
+                _ => {}
+            }
+        }
+    }
+
+    depth > 0
+}
@@ -0,0 +1,54 @@
+section .rodata 
+section .data 
+	dq 10 
+$lb1: dq 0, 1, 4, 9, 16, 25, 36, 49, 64, 81
+section .bss 
+$op_stack_base: resd 1
+$zero: resd 1
+$op_stack: resb 8192
+$bind_squares: resq 1
+	extern $std_print
+	extern $std_scan
+	extern $std_strict_scan
+	extern $std_try_scan
+	extern $str_exit
+	extern $std_alloc
+	extern $std_index_error
+	extern $std_apply
+	extern $std_call
+	extern $std_curry
+	extern $std_compose
+section .text 
+	global _start
+_start: 
+	mov ebx, $op_stack
+	add ebx, 8192
+	mov [$op_stack_base], ebx
+	sub ebx, 8
+	mov [ebx], dword 5
+	mov eax, [ebx]
+	sub ebx, 8
+	mov [ebx], eax
+	mov rax, [ebx]
+	add ebx, 8
+	mul dword[EBX]
+	mov [ebx], eax
+	call $std_print
+	sub ebx, 8
+	mov [ebx], dword $lb1
+	mov rax, [ebx]
+	add ebx, 8
+	mov [$bind_squares], rax
+	mov rax, [$bind_squares]
+	sub ebx, 8
+	mov [ebx], rax
+	sub ebx, 8
+	mov [ebx], dword 7
+	mov ecx, [ebx]
+	add ebx, 8
+	mov eax, [ebx]
+	mov rax, [EAX+ECX*8]
+	mov [ebx], rax
+	call $std_print
+	mov rdi, dword 0
+	call $str_exit
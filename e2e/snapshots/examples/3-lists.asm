@@ -0,0 +1,78 @@
+section .rodata 
+section .data 
+section .bss 
+$op_stack_base: resd 1
+$zero: resd 1
+$op_stack: resb 8192
+	extern $std_print
+	extern $std_scan
+	extern $std_strict_scan
+	extern $std_try_scan
+	extern $str_exit
+	extern $std_alloc
+	extern $std_index_error
+	extern $std_apply
+	extern $std_call
+	extern $std_curry
+	extern $std_compose
+section .text 
+	global _start
+_start: 
+	mov ebx, $op_stack
+	add ebx, 8192
+	mov [$op_stack_base], ebx
+	sub ebx, 8
+	mov [ebx], dword $lb1
+	call $std_apply
+	sub ebx, 8
+	mov [ebx], dword $lb2
+	call $std_apply
+	sub ebx, 8
+	mov [ebx], dword $lb3
+	call $std_apply
+	call $std_apply
+	mov rdi, dword 0
+	call $str_exit
+$lb1: 
+	sub ebx, 8
+	mov [ebx], dword 40
+	sub ebx, 8
+	mov [ebx], dword 2
+	mov eax, [ebx]
+	add ebx, 8
+	add [ebx], eax
+	call $std_print
+	ret 
+$lb2: 
+	sub ebx, 8
+	mov [ebx], dword 4
+	sub ebx, 8
+	mov [ebx], dword 3
+	mov eax, [ebx]
+	add ebx, 8
+	add [ebx], eax
+	ret 
+$lb5: 
+	sub ebx, 8
+	mov [ebx], dword 4
+	ret 
+$lb4: 
+	sub ebx, 8
+	mov [ebx], dword 5
+	sub ebx, 8
+	mov [ebx], dword $lb5
+	call $std_apply
+	mov eax, [ebx]
+	add ebx, 8
+	add [ebx], eax
+	ret 
+$lb6: 
+	call $std_print
+	ret 
+$lb3: 
+	sub ebx, 8
+	mov [ebx], dword $lb4
+	call $std_apply
+	sub ebx, 8
+	mov [ebx], dword $lb6
+	ret 
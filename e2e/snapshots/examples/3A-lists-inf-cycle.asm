@@ -0,0 +1,40 @@
+section .rodata 
+section .data 
+section .bss 
+$op_stack_base: resd 1
+$zero: resd 1
+$op_stack: resb 8192
+	extern $std_print
+	extern $std_scan
+	extern $std_strict_scan
+	extern $std_try_scan
+	extern $str_exit
+	extern $std_alloc
+	extern $std_index_error
+	extern $std_apply
+	extern $std_call
+	extern $std_curry
+	extern $std_compose
+section .text 
+	global _start
+_start: 
+	mov ebx, $op_stack
+	add ebx, 8192
+	mov [$op_stack_base], ebx
+	sub ebx, 8
+	mov [ebx], dword $lb1
+	mov eax, [ebx]
+	sub ebx, 8
+	mov [ebx], eax
+	call $std_apply
+	mov rdi, dword 0
+	call $str_exit
+$lb1: 
+	sub ebx, 8
+	mov [ebx], dword 42
+	call $std_print
+	mov eax, [ebx]
+	sub ebx, 8
+	mov [ebx], eax
+	call $std_apply
+	ret 
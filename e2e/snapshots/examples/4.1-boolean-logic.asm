@@ -0,0 +1,223 @@
+section .rodata 
+section .data 
+section .bss 
+$op_stack_base: resd 1
+$zero: resd 1
+$op_stack: resb 8192
+	extern $std_print
+	extern $std_scan
+	extern $std_strict_scan
+	extern $std_try_scan
+	extern $str_exit
+	extern $std_alloc
+	extern $std_index_error
+	extern $std_apply
+	extern $std_call
+	extern $std_curry
+	extern $std_compose
+section .text 
+	global _start
+_start: 
+	mov ebx, $op_stack
+	add ebx, 8192
+	mov [$op_stack_base], ebx
+	sub ebx, 8
+	mov [ebx], dword 1
+	sub ebx, 8
+	mov [ebx], dword 2
+	mov eax, [ebx]
+	add ebx, 8
+	cmp [ebx], eax
+	mov eax, 1
+	cmove eax, [$zero]
+	mov [ebx], eax
+	call $std_print
+	sub ebx, 8
+	mov [ebx], dword 1
+	sub ebx, 8
+	mov [ebx], dword 2
+	mov eax, [ebx]
+	add ebx, 8
+	cmp [ebx], eax
+	mov eax, 1
+	cmovne eax, [$zero]
+	mov [ebx], eax
+	call $std_print
+	sub ebx, 8
+	mov [ebx], dword 42
+	sub ebx, 8
+	mov [ebx], dword -42
+	mov eax, [ebx]
+	add ebx, 8
+	cmp [ebx], eax
+	mov eax, 1
+	cmovle eax, [$zero]
+	mov [ebx], eax
+	call $std_print
+	sub ebx, 8
+	mov [ebx], dword 42
+	sub ebx, 8
+	mov [ebx], dword -42
+	mov eax, [ebx]
+	add ebx, 8
+	cmp [ebx], eax
+	mov eax, 1
+	cmovl eax, [$zero]
+	mov [ebx], eax
+	call $std_print
+	sub ebx, 8
+	mov [ebx], dword 42
+	sub ebx, 8
+	mov [ebx], dword -42
+	mov eax, [ebx]
+	add ebx, 8
+	cmp [ebx], eax
+	mov eax, 1
+	cmovge eax, [$zero]
+	mov [ebx], eax
+	call $std_print
+	sub ebx, 8
+	mov [ebx], dword 42
+	sub ebx, 8
+	mov [ebx], dword -42
+	mov eax, [ebx]
+	add ebx, 8
+	cmp [ebx], eax
+	mov eax, 1
+	cmovg eax, [$zero]
+	mov [ebx], eax
+	call $std_print
+	sub ebx, 8
+	mov [ebx], dword 42
+	sub ebx, 8
+	mov [ebx], dword 42
+	mov eax, [ebx]
+	add ebx, 8
+	cmp [ebx], eax
+	mov eax, 1
+	cmovle eax, [$zero]
+	mov [ebx], eax
+	call $std_print
+	sub ebx, 8
+	mov [ebx], dword 42
+	sub ebx, 8
+	mov [ebx], dword 42
+	mov eax, [ebx]
+	add ebx, 8
+	cmp [ebx], eax
+	mov eax, 1
+	cmovl eax, [$zero]
+	mov [ebx], eax
+	call $std_print
+	sub ebx, 8
+	mov [ebx], dword 42
+	sub ebx, 8
+	mov [ebx], dword 42
+	mov eax, [ebx]
+	add ebx, 8
+	cmp [ebx], eax
+	mov eax, 1
+	cmovge eax, [$zero]
+	mov [ebx], eax
+	call $std_print
+	sub ebx, 8
+	mov [ebx], dword 42
+	sub ebx, 8
+	mov [ebx], dword 42
+	mov eax, [ebx]
+	add ebx, 8
+	cmp [ebx], eax
+	mov eax, 1
+	cmovg eax, [$zero]
+	mov [ebx], eax
+	call $std_print
+	sub ebx, 8
+	mov [ebx], dword 42
+	call $std_print
+	sub ebx, 8
+	mov [ebx], dword 10
+	cmp [ebx], dword 0
+	mov eax, 1
+	cmovz eax, [$zero]
+	mov [ebx], eax
+	call $std_print
+	sub ebx, 8
+	mov [ebx], dword -10
+	cmp [ebx], dword 0
+	mov eax, 1
+	cmovz eax, [$zero]
+	mov [ebx], eax
+	call $std_print
+	sub ebx, 8
+	mov [ebx], dword 0
+	cmp [ebx], dword 0
+	mov eax, 1
+	cmovz eax, [$zero]
+	mov [ebx], eax
+	call $std_print
+	sub ebx, 8
+	mov [ebx], dword 11
+	cmp [ebx], dword 0
+	mov eax, 1
+	cmovz eax, [$zero]
+	mov [ebx], eax
+	sub ebx, 8
+	mov [ebx], dword 14
+	cmp [ebx], dword 0
+	mov eax, 1
+	cmovz eax, [$zero]
+	mov [ebx], eax
+	mov eax, [ebx]
+	add ebx, 8
+	and [ebx], eax
+	call $std_print
+	sub ebx, 8
+	mov [ebx], dword 11
+	cmp [ebx], dword 0
+	mov eax, 1
+	cmovz eax, [$zero]
+	mov [ebx], eax
+	sub ebx, 8
+	mov [ebx], dword 0
+	cmp [ebx], dword 0
+	mov eax, 1
+	cmovz eax, [$zero]
+	mov [ebx], eax
+	mov eax, [ebx]
+	add ebx, 8
+	and [ebx], eax
+	call $std_print
+	sub ebx, 8
+	mov [ebx], dword 11
+	cmp [ebx], dword 0
+	mov eax, 1
+	cmovz eax, [$zero]
+	mov [ebx], eax
+	sub ebx, 8
+	mov [ebx], dword 0
+	cmp [ebx], dword 0
+	mov eax, 1
+	cmovz eax, [$zero]
+	mov [ebx], eax
+	mov eax, [ebx]
+	add ebx, 8
+	or [ebx], eax
+	call $std_print
+	sub ebx, 8
+	mov [ebx], dword 11
+	sub ebx, 8
+	mov [ebx], dword 12
+	mov eax, [ebx]
+	add ebx, 8
+	and [ebx], eax
+	call $std_print
+	sub ebx, 8
+	mov [ebx], dword 11
+	sub ebx, 8
+	mov [ebx], dword 14
+	mov eax, [ebx]
+	add ebx, 8
+	or [ebx], eax
+	call $std_print
+	mov rdi, dword 0
+	call $str_exit
@@ -0,0 +1,120 @@
+section .rodata 
+section .data 
+section .bss 
+$op_stack_base: resd 1
+$zero: resd 1
+$op_stack: resb 8192
+	extern $std_print
+	extern $std_scan
+	extern $std_strict_scan
+	extern $std_try_scan
+	extern $str_exit
+	extern $std_alloc
+	extern $std_index_error
+	extern $std_apply
+	extern $std_call
+	extern $std_curry
+	extern $std_compose
+section .text 
+	global _start
+_start: 
+	mov ebx, $op_stack
+	add ebx, 8192
+	mov [$op_stack_base], ebx
+	sub ebx, 8
+	mov [ebx], dword 5
+	sub ebx, 8
+	mov [ebx], dword 4
+	sub ebx, 8
+	mov [ebx], dword 0
+	sub ebx, 8
+	mov [ebx], dword 0
+	mov eax, [ebx]
+	add ebx, 8
+	cmp [ebx], eax
+	mov eax, 1
+	cmove eax, [$zero]
+	mov [ebx], eax
+	mov rax, [ebx]
+	mov rsi, [EBX+8]
+	add ebx, 16
+	cmp rax, 0
+	jne $lb1
+	mov [ebx], rsi
+$lb1: 
+	call $std_print
+	sub ebx, 8
+	mov [ebx], dword 5
+	sub ebx, 8
+	mov [ebx], dword $lb2
+	sub ebx, 8
+	mov [ebx], dword $lb3
+	sub ebx, 8
+	mov [ebx], dword 42
+	sub ebx, 8
+	mov [ebx], dword 0
+	mov eax, [ebx]
+	add ebx, 8
+	cmp [ebx], eax
+	mov eax, 1
+	cmovle eax, [$zero]
+	mov [ebx], eax
+	mov rax, [ebx]
+	mov rsi, [EBX+8]
+	add ebx, 16
+	cmp rax, 0
+	jne $lb4
+	mov [ebx], rsi
+$lb4: 
+	call $std_apply
+	call $std_print
+	sub ebx, 8
+	mov [ebx], dword 42
+	sub ebx, 8
+	mov [ebx], dword 0
+	mov eax, [ebx]
+	add ebx, 8
+	cmp [ebx], eax
+	mov eax, 1
+	cmovle eax, [$zero]
+	mov [ebx], eax
+	mov eax, [ebx]
+	add ebx, 8
+	cmp eax, 0
+	je $lb5
+	sub ebx, 8
+	mov [ebx], dword 1
+	call $std_print
+	jmp $lb6
+$lb5: 
+	sub ebx, 8
+	mov [ebx], dword 0
+	call $std_print
+$lb6: 
+	sub ebx, 8
+	mov [ebx], dword 1
+	mov eax, [ebx]
+	add ebx, 8
+	cmp eax, 0
+	je $lb7
+	sub ebx, 8
+	mov [ebx], dword 2
+	call $std_print
+$lb7: 
+	mov rdi, dword 0
+	call $str_exit
+$lb2: 
+	sub ebx, 8
+	mov [ebx], dword 5
+	mov eax, [ebx]
+	add ebx, 8
+	add [ebx], eax
+	ret 
+$lb3: 
+	sub ebx, 8
+	mov [ebx], dword 4
+	mov rax, [ebx]
+	add ebx, 8
+	mul dword[EBX]
+	mov [ebx], eax
+	ret 
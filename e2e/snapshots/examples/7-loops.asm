@@ -0,0 +1,97 @@
+section .rodata 
+section .data 
+section .bss 
+$op_stack_base: resd 1
+$zero: resd 1
+$op_stack: resb 8192
+	extern $std_print
+	extern $std_scan
+	extern $std_strict_scan
+	extern $std_try_scan
+	extern $str_exit
+	extern $std_alloc
+	extern $std_index_error
+	extern $std_apply
+	extern $std_call
+	extern $std_curry
+	extern $std_compose
+section .text 
+	global _start
+_start: 
+	mov ebx, $op_stack
+	add ebx, 8192
+	mov [$op_stack_base], ebx
+	sub ebx, 8
+	mov [ebx], dword 3
+	mov ecx, [ebx]
+	add ebx, 8
+	push rcx
+$lb1: 
+	cmp dword [RSP], 0
+	jle $lb2
+	sub dword [RSP], 1
+	sub ebx, 8
+	mov [ebx], dword 42
+	call $std_print
+	jmp $lb1
+$lb2: 
+	add rsp, 8
+	sub ebx, 8
+	mov [ebx], dword 1
+	sub ebx, 8
+	mov [ebx], dword 4
+	mov ecx, [ebx]
+	mov eax, [EBX+8]
+	add ebx, 16
+	push rcx
+	push rax
+$lb3: 
+	mov eax, [RSP]
+	cmp eax, [RSP+8]
+	jge $lb4
+	add dword [RSP], 1
+	sub ebx, 8
+	mov [ebx], eax
+	mov eax, [ebx]
+	sub ebx, 8
+	mov [ebx], eax
+	mov rax, [ebx]
+	add ebx, 8
+	mul dword[EBX]
+	mov [ebx], eax
+	call $std_print
+	jmp $lb3
+$lb4: 
+	add rsp, 16
+	sub ebx, 8
+	mov [ebx], dword 5
+$lb5: 
+	mov eax, [ebx]
+	sub ebx, 8
+	mov [ebx], eax
+	sub ebx, 8
+	mov [ebx], dword 0
+	mov eax, [ebx]
+	add ebx, 8
+	cmp [ebx], eax
+	mov eax, 1
+	cmovle eax, [$zero]
+	mov [ebx], eax
+	mov eax, [ebx]
+	add ebx, 8
+	cmp eax, 0
+	je $lb6
+	mov eax, [ebx]
+	sub ebx, 8
+	mov [ebx], eax
+	call $std_print
+	sub ebx, 8
+	mov [ebx], dword 1
+	mov eax, [ebx]
+	add ebx, 8
+	sub [ebx], eax
+	jmp $lb5
+$lb6: 
+	add ebx, 8
+	mov rdi, dword 0
+	call $str_exit
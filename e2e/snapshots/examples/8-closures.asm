@@ -0,0 +1,96 @@
+section .rodata 
+section .data 
+section .bss 
+$op_stack_base: resd 1
+$zero: resd 1
+$op_stack: resb 8192
+$bind_double: resq 1
+$bind_mul: resq 1
+	extern $std_print
+	extern $std_scan
+	extern $std_strict_scan
+	extern $std_try_scan
+	extern $str_exit
+	extern $std_alloc
+	extern $std_index_error
+	extern $std_apply
+	extern $std_call
+	extern $std_curry
+	extern $std_compose
+section .text 
+	global _start
+_start: 
+	mov ebx, $op_stack
+	add ebx, 8192
+	mov [$op_stack_base], ebx
+	sub ebx, 8
+	mov [ebx], dword 5
+	sub ebx, 8
+	mov [ebx], dword $lb1
+	call $std_curry
+	call $std_apply
+	call $std_print
+	sub ebx, 8
+	mov [ebx], dword $lb2
+	mov rax, [ebx]
+	add ebx, 8
+	mov [$bind_mul], rax
+	sub ebx, 8
+	mov [ebx], dword 2
+	mov rax, [$bind_mul]
+	sub ebx, 8
+	mov [ebx], rax
+	call $std_curry
+	mov rax, [ebx]
+	add ebx, 8
+	mov [$bind_double], rax
+	sub ebx, 8
+	mov [ebx], dword 21
+	mov rax, [$bind_double]
+	sub ebx, 8
+	mov [ebx], rax
+	call $std_apply
+	call $std_print
+	sub ebx, 8
+	mov [ebx], dword $lb3
+	sub ebx, 8
+	mov [ebx], dword $lb4
+	call $std_compose
+	sub ebx, 8
+	mov [ebx], dword 3
+	mov rax, [EBX+8]
+	mov rcx, [EBX+0]
+	mov [EBX+8], rcx
+	mov [ebx], rax
+	call $std_apply
+	call $std_print
+	mov rdi, dword 0
+	call $str_exit
+$lb1: 
+	sub ebx, 8
+	mov [ebx], dword 1
+	mov eax, [ebx]
+	add ebx, 8
+	add [ebx], eax
+	ret 
+$lb2: 
+	mov rax, [ebx]
+	add ebx, 8
+	mul dword[EBX]
+	mov [ebx], eax
+	ret 
+$lb3: 
+	sub ebx, 8
+	mov [ebx], dword 1
+	mov eax, [ebx]
+	add ebx, 8
+	add [ebx], eax
+	ret 
+$lb4: 
+	sub ebx, 8
+	mov [ebx], dword 2
+	mov rax, [ebx]
+	add ebx, 8
+	mul dword[EBX]
+	mov [ebx], eax
+	ret 
@@ -0,0 +1,96 @@
+section .rodata 
+section .data 
+section .bss 
+$op_stack_base: resd 1
+$zero: resd 1
+$op_stack: resb 8192
+$bind_math$fac: resq 1
+$bind_math$sq: resq 1
+	extern $std_print
+	extern $std_scan
+	extern $std_strict_scan
+	extern $std_try_scan
+	extern $str_exit
+	extern $std_alloc
+	extern $std_index_error
+	extern $std_apply
+	extern $std_call
+	extern $std_curry
+	extern $std_compose
+section .text 
+	global _start
+_start: 
+	mov ebx, $op_stack
+	add ebx, 8192
+	mov [$op_stack_base], ebx
+	sub ebx, 8
+	mov [ebx], dword $lb1
+	mov rax, [ebx]
+	add ebx, 8
+	mov [$bind_math$fac], rax
+	sub ebx, 8
+	mov [ebx], dword $lb4
+	mov rax, [ebx]
+	add ebx, 8
+	mov [$bind_math$sq], rax
+	sub ebx, 8
+	mov [ebx], dword 5
+	mov rax, [$bind_math$fac]
+	sub ebx, 8
+	mov [ebx], rax
+	call $std_apply
+	call $std_print
+	sub ebx, 8
+	mov [ebx], dword 7
+	mov rax, [$bind_math$sq]
+	sub ebx, 8
+	mov [ebx], rax
+	call $std_apply
+	call $std_print
+	mov rdi, dword 0
+	call $str_exit
+$lb1: 
+	mov eax, [ebx]
+	sub ebx, 8
+	mov [ebx], eax
+	sub ebx, 8
+	mov [ebx], dword 1
+	mov eax, [ebx]
+	add ebx, 8
+	cmp [ebx], eax
+	mov eax, 1
+	cmovle eax, [$zero]
+	mov [ebx], eax
+	mov eax, [ebx]
+	add ebx, 8
+	cmp eax, 0
+	je $lb2
+	mov eax, [ebx]
+	sub ebx, 8
+	mov [ebx], eax
+	sub ebx, 8
+	mov [ebx], dword 1
+	mov eax, [ebx]
+	add ebx, 8
+	sub [ebx], eax
+	mov rax, [$bind_math$fac]
+	sub ebx, 8
+	mov [ebx], rax
+	call $std_apply
+	mov rax, [ebx]
+	add ebx, 8
+	mul dword[EBX]
+	mov [ebx], eax
+	jmp $lb3
+$lb2: 
+$lb3: 
+	ret 
+$lb4: 
+	mov eax, [ebx]
+	sub ebx, 8
+	mov [ebx], eax
+	mov rax, [ebx]
+	add ebx, 8
+	mul dword[EBX]
+	mov [ebx], eax
+	ret 
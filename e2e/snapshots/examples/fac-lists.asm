@@ -0,0 +1,105 @@
+section .rodata 
+section .data 
+section .bss 
+$op_stack_base: resd 1
+$zero: resd 1
+$op_stack: resb 8192
+	extern $std_print
+	extern $std_scan
+	extern $std_strict_scan
+	extern $std_try_scan
+	extern $str_exit
+	extern $std_alloc
+	extern $std_index_error
+	extern $std_apply
+	extern $std_call
+	extern $std_curry
+	extern $std_compose
+section .text 
+	global _start
+_start: 
+	mov ebx, $op_stack
+	add ebx, 8192
+	mov [$op_stack_base], ebx
+	sub ebx, 8
+	mov [ebx], dword 6
+	sub ebx, 8
+	mov [ebx], dword $lb1
+	mov eax, [ebx]
+	sub ebx, 8
+	mov [ebx], eax
+	call $std_apply
+	call $std_print
+	mov rdi, dword 0
+	call $str_exit
+$lb2: 
+	mov eax, [ebx]
+	sub ebx, 8
+	mov [ebx], eax
+	sub ebx, 8
+	mov [ebx], dword 1
+	mov eax, [ebx]
+	add ebx, 8
+	sub [ebx], eax
+	mov rax, [EBX+16]
+	mov rcx, [EBX+8]
+	mov [EBX+16], rcx
+	mov rcx, [EBX+0]
+	mov [EBX+8], rcx
+	mov [ebx], rax
+	mov eax, [ebx]
+	sub ebx, 8
+	mov [ebx], eax
+	call $std_apply
+	mov rax, [ebx]
+	add ebx, 8
+	mul dword[EBX]
+	mov [ebx], eax
+	ret 
+$lb3: 
+	mov rax, [EBX+8]
+	mov rcx, [EBX+0]
+	mov [EBX+8], rcx
+	mov [ebx], rax
+	add ebx, 8
+	ret 
+$lb4: 
+	mov rax, [EBX+16]
+	mov rcx, [EBX+8]
+	mov [EBX+16], rcx
+	mov rcx, [EBX+0]
+	mov [EBX+8], rcx
+	mov [ebx], rax
+	sub ebx, 8
+	mov [ebx], dword 1
+	mov eax, [ebx]
+	add ebx, 8
+	cmp [ebx], eax
+	mov eax, 1
+	cmovle eax, [$zero]
+	mov [ebx], eax
+	ret 
+$lb1: 
+	mov rax, [EBX+8]
+	mov rcx, [EBX+0]
+	mov [EBX+8], rcx
+	mov [ebx], rax
+	mov eax, [ebx]
+	sub ebx, 8
+	mov [ebx], eax
+	sub ebx, 8
+	mov [ebx], dword $lb2
+	sub ebx, 8
+	mov [ebx], dword $lb3
+	sub ebx, 8
+	mov [ebx], dword $lb4
+	call $std_apply
+	mov rax, [ebx]
+	mov rsi, [EBX+8]
+	add ebx, 16
+	cmp rax, 0
+	jne $lb5
+	mov [ebx], rsi
+$lb5: 
+	call $std_apply
+	ret 
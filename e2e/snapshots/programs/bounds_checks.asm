@@ -0,0 +1,59 @@
+section .rodata 
+section .data 
+section .bss 
+$op_stack_base: resd 1
+$zero: resd 1
+$op_stack: resb 8192
+$bind_a: resq 1
+	extern $std_print
+	extern $std_scan
+	extern $std_strict_scan
+	extern $std_try_scan
+	extern $str_exit
+	extern $std_alloc
+	extern $std_index_error
+	extern $std_apply
+	extern $std_call
+	extern $std_curry
+	extern $std_compose
+section .text 
+	global _start
+_start: 
+	mov ebx, $op_stack
+	add ebx, 8192
+	mov [$op_stack_base], ebx
+	sub ebx, 8
+	mov [ebx], dword 3
+	call $std_alloc
+	mov rax, [ebx]
+	add ebx, 8
+	mov [$bind_a], rax
+	sub ebx, 8
+	mov [ebx], dword 7
+	mov rax, [$bind_a]
+	sub ebx, 8
+	mov [ebx], rax
+	sub ebx, 8
+	mov [ebx], dword 0
+	mov ecx, [ebx]
+	mov eax, [EBX+8]
+	mov rdx, [EBX+16]
+	add ebx, 24
+	cmp ecx, [EAX-8]
+	jae $std_index_error
+	mov [EAX+ECX*8], rdx
+	mov rax, [$bind_a]
+	sub ebx, 8
+	mov [ebx], rax
+	sub ebx, 8
+	mov [ebx], dword 0
+	mov ecx, [ebx]
+	add ebx, 8
+	mov eax, [ebx]
+	cmp ecx, [EAX-8]
+	jae $std_index_error
+	mov rax, [EAX+ECX*8]
+	mov [ebx], rax
+	call $std_print
+	mov rdi, dword 0
+	call $str_exit
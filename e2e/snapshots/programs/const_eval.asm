@@ -0,0 +1,37 @@
+section .rodata 
+section .data 
+	dq 4 
+$lb1: dq 0, 1, 4, 9
+section .bss 
+$op_stack_base: resd 1
+$zero: resd 1
+$op_stack: resb 8192
+	extern $std_print
+	extern $std_scan
+	extern $std_strict_scan
+	extern $std_try_scan
+	extern $str_exit
+	extern $std_alloc
+	extern $std_index_error
+	extern $std_apply
+	extern $std_call
+	extern $std_curry
+	extern $std_compose
+section .text 
+	global _start
+_start: 
+	mov ebx, $op_stack
+	add ebx, 8192
+	mov [$op_stack_base], ebx
+	sub ebx, 8
+	mov [ebx], dword $lb1
+	sub ebx, 8
+	mov [ebx], dword 2
+	mov ecx, [ebx]
+	add ebx, 8
+	mov eax, [ebx]
+	mov rax, [EAX+ECX*8]
+	mov [ebx], rax
+	call $std_print
+	mov rdi, dword 0
+	call $str_exit
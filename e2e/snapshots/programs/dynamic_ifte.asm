@@ -0,0 +1,60 @@
+section .rodata 
+section .data 
+section .bss 
+$op_stack_base: resd 1
+$zero: resd 1
+$op_stack: resb 8192
+$bind_no: resq 1
+$bind_yes: resq 1
+	extern $std_print
+	extern $std_scan
+	extern $std_strict_scan
+	extern $std_try_scan
+	extern $str_exit
+	extern $std_alloc
+	extern $std_index_error
+	extern $std_apply
+	extern $std_call
+	extern $std_curry
+	extern $std_compose
+section .text 
+	global _start
+_start: 
+	mov ebx, $op_stack
+	add ebx, 8192
+	mov [$op_stack_base], ebx
+	sub ebx, 8
+	mov [ebx], dword $lb1
+	mov rax, [ebx]
+	add ebx, 8
+	mov [$bind_yes], rax
+	sub ebx, 8
+	mov [ebx], dword $lb2
+	mov rax, [ebx]
+	add ebx, 8
+	mov [$bind_no], rax
+	sub ebx, 8
+	mov [ebx], dword 1
+	mov rax, [$bind_yes]
+	sub ebx, 8
+	mov [ebx], rax
+	mov rax, [$bind_no]
+	sub ebx, 8
+	mov [ebx], rax
+	mov eax, [ebx]
+	mov ecx, [EBX+8]
+	mov edx, [EBX+16]
+	add ebx, 24
+	cmp edx, 0
+	cmovne eax, ecx
+	call $std_call
+	mov rdi, dword 0
+	call $str_exit
+$lb1: 
+	sub ebx, 8
+	mov [ebx], dword 2
+	ret 
+$lb2: 
+	sub ebx, 8
+	mov [ebx], dword 3
+	ret 
@@ -0,0 +1,50 @@
+section .rodata 
+section .data 
+section .bss 
+$op_stack_base: resd 1
+$zero: resd 1
+$op_stack: resb 8192
+	extern $std_print
+	extern $std_scan
+	extern $std_strict_scan
+	extern $std_try_scan
+	extern $str_exit
+	extern $std_alloc
+	extern $std_index_error
+	extern $std_apply
+	extern $std_call
+	extern $std_curry
+	extern $std_compose
+section .text 
+	global _start
+_start: 
+	mov ebx, $op_stack
+	add ebx, 8192
+	mov [$op_stack_base], ebx
+	sub ebx, 8
+	mov [ebx], dword 0
+	sub ebx, 8
+	mov [ebx], dword 1
+	sub ebx, 8
+	mov [ebx], dword 10
+	mov ecx, [ebx]
+	mov eax, [EBX+8]
+	add ebx, 16
+	push rcx
+	push rax
+$lb1: 
+	mov eax, [RSP]
+	cmp eax, [RSP+8]
+	jge $lb2
+	add dword [RSP], 1
+	sub ebx, 8
+	mov [ebx], eax
+	mov eax, [ebx]
+	add ebx, 8
+	add [ebx], eax
+	jmp $lb1
+$lb2: 
+	add rsp, 16
+	call $std_print
+	mov rdi, dword 0
+	call $str_exit
@@ -0,0 +1,47 @@
+section .rodata 
+section .data 
+section .bss 
+$op_stack_base: resd 1
+$zero: resd 1
+$op_stack: resb 8192
+	extern $std_print
+	extern $std_scan
+	extern $std_strict_scan
+	extern $std_try_scan
+	extern $str_exit
+	extern $std_alloc
+	extern $std_index_error
+	extern $std_apply
+	extern $std_call
+	extern $std_curry
+	extern $std_compose
+section .text 
+	global _start
+_start: 
+	mov ebx, $op_stack
+	add ebx, 8192
+	mov [$op_stack_base], ebx
+	sub ebx, 8
+	mov [ebx], dword 1
+	mov eax, [ebx]
+	add ebx, 8
+	cmp eax, 0
+	je $lb1
+	sub ebx, 8
+	mov [ebx], dword 2
+	jmp $lb2
+$lb1: 
+	sub ebx, 8
+	mov [ebx], dword 3
+$lb2: 
+	sub ebx, 8
+	mov [ebx], dword 0
+	mov eax, [ebx]
+	add ebx, 8
+	cmp eax, 0
+	je $lb3
+	sub ebx, 8
+	mov [ebx], dword 4
+$lb3: 
+	mov rdi, dword 0
+	call $str_exit
@@ -0,0 +1,63 @@
+section .rodata 
+section .data 
+section .bss 
+$op_stack_base: resd 1
+$zero: resd 1
+$op_stack: resb 8192
+$bind_inc: resq 1
+	extern $std_print
+	extern $std_scan
+	extern $std_strict_scan
+	extern $std_try_scan
+	extern $str_exit
+	extern $std_alloc
+	extern $std_index_error
+	extern $std_apply
+	extern $std_call
+	extern $std_curry
+	extern $std_compose
+section .text 
+	global _start
+_start: 
+	mov ebx, $op_stack
+	add ebx, 8192
+	mov [$op_stack_base], ebx
+	sub ebx, 8
+	mov [ebx], dword $lb1
+	mov rax, [ebx]
+	add ebx, 8
+	mov [$bind_inc], rax
+	sub ebx, 8
+	mov [ebx], dword 1
+	mov rax, [$bind_inc]
+	sub ebx, 8
+	mov [ebx], rax
+	call $std_apply
+	sub ebx, 8
+	mov [ebx], dword $lb2
+	call $std_compose
+	sub ebx, 8
+	mov [ebx], dword 3
+	mov rax, [ebx]
+	mov rcx, [EBX+8]
+	mov [ebx], rcx
+	mov [EBX+8], rax
+	call $std_curry
+	call $std_apply
+	mov rdi, dword 0
+	call $str_exit
+$lb1: 
+	sub ebx, 8
+	mov [ebx], dword 1
+	mov eax, [ebx]
+	add ebx, 8
+	add [ebx], eax
+	ret 
+$lb2: 
+	sub ebx, 8
+	mov [ebx], dword 2
+	mov rax, [ebx]
+	add ebx, 8
+	mul dword[EBX]
+	mov [ebx], eax
+	ret 
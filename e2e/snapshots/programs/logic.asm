@@ -0,0 +1,73 @@
+section .rodata 
+section .data 
+section .bss 
+$op_stack_base: resd 1
+$zero: resd 1
+$op_stack: resb 8192
+	extern $std_print
+	extern $std_scan
+	extern $std_strict_scan
+	extern $std_try_scan
+	extern $str_exit
+	extern $std_alloc
+	extern $std_index_error
+	extern $std_apply
+	extern $std_call
+	extern $std_curry
+	extern $std_compose
+section .text 
+	global _start
+_start: 
+	mov ebx, $op_stack
+	add ebx, 8192
+	mov [$op_stack_base], ebx
+	sub ebx, 8
+	mov [ebx], dword 1
+	sub ebx, 8
+	mov [ebx], dword 2
+	mov eax, [ebx]
+	add ebx, 8
+	cmp [ebx], eax
+	mov eax, 1
+	cmovge eax, [$zero]
+	mov [ebx], eax
+	sub ebx, 8
+	mov [ebx], dword 3
+	sub ebx, 8
+	mov [ebx], dword 4
+	mov eax, [ebx]
+	add ebx, 8
+	cmp [ebx], eax
+	mov eax, 1
+	cmovl eax, [$zero]
+	mov [ebx], eax
+	mov eax, [ebx]
+	add ebx, 8
+	and [ebx], eax
+	sub ebx, 8
+	mov [ebx], dword 5
+	xor [ebx], dword -1
+	mov eax, 1
+	cmp [ebx], dword 0
+	cmovz eax, [$zero]
+	add [ebx], eax
+	mov eax, [ebx]
+	add ebx, 8
+	or [ebx], eax
+	cmp [ebx], dword 0
+	mov eax, 1
+	cmovz eax, [$zero]
+	mov [ebx], eax
+	sub ebx, 8
+	mov [ebx], dword 1
+	sub ebx, 8
+	mov [ebx], dword 2
+	mov rax, [ebx]
+	mov rsi, [EBX+8]
+	add ebx, 16
+	cmp rax, 0
+	jne $lb1
+	mov [ebx], rsi
+$lb1: 
+	mov rdi, dword 0
+	call $str_exit
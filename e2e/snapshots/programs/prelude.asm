@@ -0,0 +1,156 @@
+section .rodata 
+section .data 
+section .bss 
+$op_stack_base: resd 1
+$zero: resd 1
+$op_stack: resb 8192
+$bind_prelude$abs: resq 1
+$bind_prelude$gcd: resq 1
+$bind_prelude$mod: resq 1
+$bind_prelude$neg: resq 1
+	extern $std_print
+	extern $std_scan
+	extern $std_strict_scan
+	extern $std_try_scan
+	extern $str_exit
+	extern $std_alloc
+	extern $std_index_error
+	extern $std_apply
+	extern $std_call
+	extern $std_curry
+	extern $std_compose
+section .text 
+	global _start
+_start: 
+	mov ebx, $op_stack
+	add ebx, 8192
+	mov [$op_stack_base], ebx
+	sub ebx, 8
+	mov [ebx], dword $lb1
+	mov rax, [ebx]
+	add ebx, 8
+	mov [$bind_prelude$neg], rax
+	sub ebx, 8
+	mov [ebx], dword $lb2
+	mov rax, [ebx]
+	add ebx, 8
+	mov [$bind_prelude$abs], rax
+	sub ebx, 8
+	mov [ebx], dword $lb4
+	mov rax, [ebx]
+	add ebx, 8
+	mov [$bind_prelude$mod], rax
+	sub ebx, 8
+	mov [ebx], dword $lb5
+	mov rax, [ebx]
+	add ebx, 8
+	mov [$bind_prelude$gcd], rax
+	sub ebx, 8
+	mov [ebx], dword -5
+	mov rax, [$bind_prelude$abs]
+	sub ebx, 8
+	mov [ebx], rax
+	call $std_apply
+	call $std_print
+	sub ebx, 8
+	mov [ebx], dword 12
+	sub ebx, 8
+	mov [ebx], dword 18
+	mov rax, [$bind_prelude$gcd]
+	sub ebx, 8
+	mov [ebx], rax
+	call $std_apply
+	call $std_print
+	mov rdi, dword 0
+	call $str_exit
+$lb1: 
+	sub ebx, 8
+	mov [ebx], dword 0
+	mov rax, [ebx]
+	mov rcx, [EBX+8]
+	mov [ebx], rcx
+	mov [EBX+8], rax
+	mov eax, [ebx]
+	add ebx, 8
+	sub [ebx], eax
+	ret 
+$lb2: 
+	mov eax, [ebx]
+	sub ebx, 8
+	mov [ebx], eax
+	sub ebx, 8
+	mov [ebx], dword 0
+	mov eax, [ebx]
+	add ebx, 8
+	cmp [ebx], eax
+	mov eax, 1
+	cmovge eax, [$zero]
+	mov [ebx], eax
+	mov eax, [ebx]
+	add ebx, 8
+	cmp eax, 0
+	je $lb3
+	mov rax, [$bind_prelude$neg]
+	sub ebx, 8
+	mov [ebx], rax
+	call $std_apply
+$lb3: 
+	ret 
+$lb4: 
+	mov rax, [EBX+8]
+	mov rcx, [ebx]
+	sub ebx, 16
+	mov [EBX+8], rax
+	mov [ebx], rcx
+	mov eax, [ebx]
+	add ebx, 8
+	cltq 
+	mov rdi, rax
+	mov eax, [ebx]
+	cltq 
+	cqto 
+	idiv rdi
+	mov [ebx], eax
+	mov rax, [ebx]
+	add ebx, 8
+	mul dword[EBX]
+	mov [ebx], eax
+	mov eax, [ebx]
+	add ebx, 8
+	sub [ebx], eax
+	ret 
+$lb5: 
+$lb6: 
+	mov eax, [ebx]
+	sub ebx, 8
+	mov [ebx], eax
+	sub ebx, 8
+	mov [ebx], dword 0
+	mov eax, [ebx]
+	add ebx, 8
+	cmp [ebx], eax
+	mov eax, 1
+	cmove eax, [$zero]
+	mov [ebx], eax
+	mov eax, [ebx]
+	add ebx, 8
+	cmp eax, 0
+	je $lb7
+	mov rax, [ebx]
+	mov rcx, [EBX+8]
+	mov [EBX+8], rax
+	mov [ebx], rcx
+	sub ebx, 8
+	mov [ebx], rax
+	mov rax, [$bind_prelude$mod]
+	sub ebx, 8
+	mov [ebx], rax
+	call $std_apply
+	jmp $lb6
+$lb7: 
+	add ebx, 8
+	mov rax, [$bind_prelude$abs]
+	sub ebx, 8
+	mov [ebx], rax
+	call $std_apply
+	ret 
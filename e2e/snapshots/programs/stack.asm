@@ -0,0 +1,69 @@
+section .rodata 
+section .data 
+section .bss 
+$op_stack_base: resd 1
+$zero: resd 1
+$op_stack: resb 8192
+	extern $std_print
+	extern $std_scan
+	extern $std_strict_scan
+	extern $std_try_scan
+	extern $str_exit
+	extern $std_alloc
+	extern $std_index_error
+	extern $std_apply
+	extern $std_call
+	extern $std_curry
+	extern $std_compose
+section .text 
+	global _start
+_start: 
+	mov ebx, $op_stack
+	add ebx, 8192
+	mov [$op_stack_base], ebx
+	sub ebx, 8
+	mov [ebx], dword 1
+	sub ebx, 8
+	mov [ebx], dword 2
+	sub ebx, 8
+	mov [ebx], dword 3
+	mov eax, [ebx]
+	sub ebx, 8
+	mov [ebx], eax
+	add ebx, 8
+	mov rax, [ebx]
+	mov rcx, [EBX+8]
+	mov [ebx], rcx
+	mov [EBX+8], rax
+	mov rax, [EBX+8]
+	sub ebx, 8
+	mov [ebx], rax
+	mov rax, [EBX+16]
+	mov rcx, [EBX+8]
+	mov rdx, [ebx]
+	mov [EBX+16], rcx
+	mov [EBX+8], rdx
+	mov [ebx], rax
+	mov rax, [EBX+16]
+	mov rcx, [EBX+8]
+	mov rdx, [ebx]
+	mov [EBX+16], rdx
+	mov [EBX+8], rax
+	mov [ebx], rcx
+	mov rax, [ebx]
+	add ebx, 8
+	mov [ebx], rax
+	mov rax, [ebx]
+	mov rcx, [EBX+8]
+	mov [EBX+8], rax
+	mov [ebx], rcx
+	sub ebx, 8
+	mov [ebx], rax
+	mov rax, [EBX+8]
+	mov rcx, [ebx]
+	sub ebx, 16
+	mov [EBX+8], rax
+	mov [ebx], rcx
+	add ebx, 16
+	mov rdi, dword 0
+	call $str_exit
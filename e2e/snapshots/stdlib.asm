@@ -0,0 +1,163 @@
+section .rodata 
+$otemplate: db `%d`, 10, 0
+$itemplate: db `%d`, 0
+$stemplate: db `%*s`, 0
+$scan_error: db `plc: failed to read an integer from stdin`, 10, 0
+$alloc_size_error: db `plc: invalid array size`, 10, 0
+$out_of_memory_error: db `plc: out of memory`, 10, 0
+$index_error: db `plc: array index out of bounds`, 10, 0
+$apply_error: db `plc: applied value is not a quotation`, 10, 0
+section .bss 
+$heap_used: resq 1
+$heap: resb 1048576
+	global $std_print
+	global $std_scan
+	global $std_strict_scan
+	global $std_try_scan
+	global $str_exit
+	global $std_alloc
+	global $std_index_error
+	global $std_apply
+	global $std_call
+	global $std_curry
+	global $std_compose
+	extern printf
+	extern scanf
+	extern dprintf
+	extern exit
+section .text 
+$std_print: 
+	push rbp
+	mov rbp, rsp
+	and rsp, -16
+	mov rdi, $otemplate
+	xor rsi, rsi
+	mov esi, [ebx]
+	call printf
+	add ebx, 8
+	mov rsp, rbp
+	pop rbp
+	ret 
+$std_scan: 
+	call $scanf_into_top
+	ret 
+$std_strict_scan: 
+	call $scanf_into_top
+	cmp eax, 1
+	mov rsi, $scan_error
+	jne $abort
+	ret 
+$std_try_scan: 
+	call $scanf_into_top
+	xor ecx, ecx
+	cmp eax, 1
+	sete cl
+	sub ebx, 8
+	mov [ebx], ecx
+	ret 
+$scanf_into_top: 
+	push rbp
+	mov rbp, rsp
+	and rsp, -16
+	sub ebx, 8
+	mov [ebx], dword 0
+	mov rdi, $itemplate
+	xor rsi, rsi
+	mov esi, ebx
+	xor rax, rax
+	call scanf
+	cmp eax, 0
+	jne $scanf_done
+	mov rdi, $stemplate
+	xor rax, rax
+	call scanf
+	xor eax, eax
+$scanf_done: 
+	mov rsp, rbp
+	pop rbp
+	ret 
+$std_alloc: 
+	mov ecx, [ebx]
+	mov rsi, $alloc_size_error
+	cmp ecx, 0
+	jl $abort
+	mov rsi, $out_of_memory_error
+	cmp ecx, 131072
+	ja $abort
+	mov eax, ecx
+	add eax, 1
+	shl eax, 3
+	mov edx, [$heap_used]
+	add eax, edx
+	cmp eax, 1048576
+	ja $abort
+	mov [$heap_used], eax
+	mov eax, $heap
+	add eax, edx
+	mov [eax], ecx
+	add eax, 8
+	mov [ebx], eax
+	ret 
+$std_apply: 
+	mov eax, [ebx]
+	add ebx, 8
+$std_call: 
+	cmp eax, $heap
+	jb $call_code
+	cmp eax, $heap+1048576
+	jae $call_code
+	mov rsi, $apply_error
+	cmp dword [EAX-8], -1
+	jne $abort
+	cmp dword [EAX], 0
+	jne $call_composition
+	mov rcx, [EAX+8]
+	sub ebx, 8
+	mov [ebx], rcx
+	mov eax, [EAX+16]
+	jmp $std_call
+$call_composition: 
+	push rax
+	mov eax, [EAX+8]
+	call $std_call
+	pop rax
+	mov eax, [EAX+16]
+	jmp $std_call
+$call_code: 
+	jmp rax
+$std_curry: 
+	mov edx, 0
+	jmp $make_closure
+$std_compose: 
+	mov edx, 1
+	jmp $make_closure
+$make_closure: 
+	push rdx
+	sub ebx, 8
+	mov [ebx], dword 3
+	call $std_alloc
+	pop rdx
+	mov eax, [ebx]
+	add ebx, 8
+	mov dword [EAX-8], -1
+	mov [eax], edx
+	mov rcx, [EBX+8]
+	mov [EAX+8], rcx
+	mov rcx, [ebx]
+	mov [EAX+16], rcx
+	add ebx, 8
+	mov [ebx], rax
+	ret 
+$std_index_error: 
+	mov rsi, $index_error
+	jmp $abort
+$abort: 
+	and rsp, -16
+	mov rdi, 2
+	xor rax, rax
+	call dprintf
+	mov rdi, 1
+	call exit
+$str_exit: 
+	and rsp, -16
+	call exit
@@ -0,0 +1,213 @@
+//! Test cases stored as files.
+//!
+//! Every `tests/cases/*.plc` is compiled and run. Its expectations are read from sibling files
+//! `<name>.stdin`, `<name>.stdout` and `<name>.exitcode`, or from comments in the program:
+//!
+//! ```text
+//! # args: --strict-scan
+//! # stdin: 5
+//! # expect: 120
+//! # exitcode: 0
+//! ```
+//!
+//! `# stdin:` and `# expect:` may be repeated, one line each. Sibling files take precedence.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+
+use crate::util::Compiler;
+
+lazy_static! {
+    static ref compiler: Compiler =
+        Compiler::make().unwrap_or_else(|e| { panic!("Failed to make a compiler with: {}", e) });
+}
+
+#[derive(Debug, Default)]
+struct Case {
+    args: Vec<String>,
+    stdin: String,
+    stdout: String,
+    exit_code: i32,
+}
+
+impl Case {
+    fn read(path: &Path) -> Result<Case> {
+        let source = std::fs::read_to_string(path)?;
+        let mut case = Case::default();
+
+        for line in source.lines() {
+            let Some(comment) = line.trim_start().strip_prefix('#') else {
+                continue;
+            };
+            let comment = comment.trim_start();
+
+            if let Some(args) = comment.strip_prefix("args:") {
+                case.args
+                    .extend(args.split_whitespace().map(str::to_string));
+            } else if let Some(stdin) = comment.strip_prefix("stdin:") {
+                case.stdin.push_str(stdin.trim());
+                case.stdin.push('\n');
+            } else if let Some(stdout) = comment.strip_prefix("expect:") {
+                case.stdout.push_str(stdout.trim());
+                case.stdout.push('\n');
+            } else if let Some(exit_code) = comment.strip_prefix("exitcode:") {
+                case.exit_code = parse_exit_code(exit_code)?;
+            }
+        }
+
+        if let Some(stdin) = read_sibling(path, "stdin")? {
+            case.stdin = stdin;
+        }
+        if let Some(stdout) = read_sibling(path, "stdout")? {
+            case.stdout = stdout;
+        }
+        if let Some(exit_code) = read_sibling(path, "exitcode")? {
+            case.exit_code = parse_exit_code(&exit_code)?;
+        }
+
+        Ok(case)
+    }
+
+    fn run(&self, path: &Path) -> Result<()> {
+        let args: Vec<_> = self.args.iter().map(String::as_str).collect();
+        let (stdout, exit_code) = compiler
+            .compile_file_with_args(path, &args)?
+            .and_execute_with_status(&self.stdin)?;
+
+        if exit_code != Some(self.exit_code) {
+            return Err(anyhow!(
+                "expected exit code {}, got {:?}\nstdout: {:?}",
+                self.exit_code,
+                exit_code,
+                stdout
+            ));
+        }
+        if stdout != self.stdout {
+            return Err(anyhow!(
+                "expected stdout {:?}, got {:?}",
+                self.stdout,
+                stdout
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+fn read_sibling(path: &Path, extension: &str) -> Result<Option<String>> {
+    let path = path.with_extension(extension);
+    if path.exists() {
+        Ok(Some(std::fs::read_to_string(path)?))
+    } else {
+        Ok(None)
+    }
+}
+
+fn parse_exit_code(exit_code: &str) -> Result<i32> {
+    exit_code
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("invalid exit code {:?}", exit_code.trim()))
+}
+
+/// The `.plc` files right in the directory, without the modules in its subdirectories.
+fn programs(dir: &Path) -> Vec<PathBuf> {
+    let mut programs: Vec<_> = std::fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("can't read {}: {}", dir.display(), e))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|x| x == "plc"))
+        .collect();
+    programs.sort();
+    programs
+}
+
+fn assert_all(results: Vec<(PathBuf, Result<()>)>) {
+    let failures: Vec<_> = results
+        .into_iter()
+        .filter_map(|(path, result)| result.err().map(|e| format!("{}: {}", path.display(), e)))
+        .collect();
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn cases() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/cases");
+    let cases = programs(&dir);
+    assert!(!cases.is_empty(), "no test cases in {}", dir.display());
+
+    assert_all(
+        cases
+            .into_iter()
+            .map(|path| {
+                let result = Case::read(&path).and_then(|case| case.run(&path));
+                (path, result)
+            })
+            .collect(),
+    );
+}
+
+/// The examples aren't run, some of them read input or never stop; they must compile.
+#[test]
+fn examples_compile() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../examples");
+
+    assert_all(
+        programs(&dir)
+            .into_iter()
+            .map(|path| {
+                let result = compiler.compile_file_with_args(&path, &[]).map(|result| {
+                    if let Some(file) = result.output_file {
+                        let _ = std::fs::remove_file(file);
+                    }
+                });
+                (path, result)
+            })
+            .collect(),
+    );
+}
+
+/// The examples are kept formatted, the same as with `plc fmt --check` in CI.
+#[test]
+fn examples_are_formatted() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../examples");
+
+    assert_all(
+        programs(&dir)
+            .into_iter()
+            .map(|path| {
+                let result = compiler
+                    .run_command(
+                        [
+                            std::ffi::OsStr::new("fmt"),
+                            "--check".as_ref(),
+                            path.as_os_str(),
+                        ],
+                        "",
+                    )
+                    .map(|_| ());
+                (path, result)
+            })
+            .collect(),
+    );
+}
+
+/// The stack depth tracing of `plc check` has no false alarms on the examples.
+#[test]
+fn examples_check() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../examples");
+
+    assert_all(
+        programs(&dir)
+            .into_iter()
+            .map(|path| {
+                let result = compiler
+                    .run_command([std::ffi::OsStr::new("check"), path.as_os_str()], "")
+                    .map(|_| ());
+                (path, result)
+            })
+            .collect(),
+    );
+}
@@ -0,0 +1,404 @@
+//! Differential testing: random programs are compiled natively and interpreted,
+//! and both runs must print the same output and exit with the same code.
+
+use std::io::Cursor;
+
+use lazy_static::lazy_static;
+use lib::Term;
+use proptest::{prelude::*, test_runner::TestCaseError};
+
+use crate::util::Compiler;
+
+lazy_static! {
+    static ref compiler: Compiler =
+        Compiler::make().unwrap_or_else(|e| { panic!("Failed to make a compiler with: {}", e) });
+}
+
+/// Runtime errors abort both the native program and the interpreter with this code.
+const ERROR_EXIT_CODE: i32 = 1;
+
+/// Names bound by the generated programs.
+const NAMES: usize = 3;
+
+/// A generated step of a program.
+///
+/// Any sequence of steps is turned into a well-formed program by [`Lowering`]: steps which
+/// need more values than the stack has are skipped, and loop bodies are balanced so that
+/// they leave the stack as deep as they found it. So a shrunk sequence is a valid program too.
+#[derive(Clone, Debug)]
+enum Step {
+    Int(i32),
+    /// Binary operations on numbers
+    Binary(Term),
+    /// `x n /` with a non-zero literal divisor
+    Div(i32),
+    Unary(Term),
+    Shuffle(Term),
+    Take(usize),
+    Pick(usize),
+    Print,
+    Scan,
+    TryScan,
+    /// `a b cond ?`
+    If,
+    Bind(usize),
+    Put(usize),
+    /// `{ ... } index @`, the index may be out of bounds
+    Load(Vec<i32>, i32),
+    /// `{ ... } :name ... value name index @=` through a binding
+    Store(Vec<i32>, i32),
+    Apply(Vec<Step>),
+    Curry(i32, Vec<Step>),
+    Compose(Vec<Step>, Vec<Step>),
+    Ifte(Vec<Step>, Vec<Step>),
+    Times(i32, Vec<Step>),
+    Each(i32, i32, Vec<Step>),
+}
+
+fn int() -> impl Strategy<Value = i32> {
+    prop_oneof![3 => -10..10, 1 => any::<i32>()]
+}
+
+/// Mostly in bounds of the generated arrays, sometimes out of them.
+fn index() -> impl Strategy<Value = i32> {
+    prop_oneof![6 => 0..1, 3 => 0..3, 1 => -1..5]
+}
+
+fn step() -> impl Strategy<Value = Step> {
+    let leaf = prop_oneof![
+        4 => int().prop_map(Step::Int),
+        4 => prop::sample::select(vec![
+            Term::Add,
+            Term::Sub,
+            Term::Mul,
+            Term::And,
+            Term::Or,
+            Term::Equals,
+            Term::NotEquals,
+            Term::Less,
+            Term::LessEquals,
+            Term::Greater,
+            Term::GreaterEquals,
+        ])
+        .prop_map(Step::Binary),
+        1 => int()
+            .prop_filter("divisor must not be zero", |x| *x != 0)
+            .prop_map(Step::Div),
+        1 => prop::sample::select(vec![Term::Not, Term::Bool]).prop_map(Step::Unary),
+        3 => prop::sample::select(vec![
+            Term::Dup,
+            Term::Drop,
+            Term::Swap,
+            Term::Over,
+            Term::Rot,
+            Term::MinusRot,
+            Term::Nip,
+            Term::Tuck,
+            Term::TwoDup,
+            Term::TwoDrop,
+        ])
+        .prop_map(Step::Shuffle),
+        1 => (0..4usize).prop_map(Step::Take),
+        1 => (0..4usize).prop_map(Step::Pick),
+        2 => Just(Step::Print),
+        1 => Just(Step::Scan),
+        1 => Just(Step::TryScan),
+        1 => Just(Step::If),
+        1 => (0..NAMES).prop_map(Step::Bind),
+        1 => (0..NAMES).prop_map(Step::Put),
+        1 => (prop::collection::vec(int(), 1..4), index()).prop_map(|(a, i)| Step::Load(a, i)),
+        1 => (prop::collection::vec(int(), 1..4), index()).prop_map(|(a, i)| Step::Store(a, i)),
+    ];
+
+    leaf.prop_recursive(3, 48, 6, |inner| {
+        let body = || prop::collection::vec(inner.clone(), 0..6);
+        prop_oneof![
+            body().prop_map(Step::Apply),
+            (int(), body()).prop_map(|(x, q)| Step::Curry(x, q)),
+            (body(), body()).prop_map(|(p, q)| Step::Compose(p, q)),
+            (body(), body()).prop_map(|(a, b)| Step::Ifte(a, b)),
+            (-1..4, body()).prop_map(|(n, q)| Step::Times(n, q)),
+            (-2..3, -2..4, body()).prop_map(|(from, to, q)| Step::Each(from, to, q)),
+        ]
+    })
+}
+
+fn program() -> impl Strategy<Value = Vec<Step>> {
+    prop::collection::vec(step(), 0..24)
+}
+
+/// Numbers for `&`; once they run out, a strict `&` aborts the program.
+fn stdin() -> impl Strategy<Value = String> {
+    prop::collection::vec(int(), 0..6).prop_map(|xs| {
+        xs.iter()
+            .map(|x| format!("{}\n", x))
+            .collect::<Vec<_>>()
+            .concat()
+    })
+}
+
+/// Turns steps into terms, tracking how many numbers are on the stack.
+struct Lowering {
+    /// Names bound at the top level so far; they are bound before any later use
+    bound: Vec<usize>,
+}
+
+impl Lowering {
+    fn program(steps: &[Step]) -> Vec<Term> {
+        let mut lowering = Lowering { bound: Vec::new() };
+        let mut terms = Vec::new();
+        let mut depth = 0;
+
+        lowering.lower(steps, &mut depth, true, &mut terms);
+        // the stack left by the program is printed, so it's compared too
+        terms.extend(std::iter::repeat_n(Term::Print, depth));
+
+        terms
+    }
+
+    fn lower(&mut self, steps: &[Step], depth: &mut usize, top_level: bool, terms: &mut Vec<Term>) {
+        for step in steps {
+            self.lower_step(step, depth, top_level, terms);
+        }
+    }
+
+    fn lower_step(
+        &mut self,
+        step: &Step,
+        depth: &mut usize,
+        top_level: bool,
+        terms: &mut Vec<Term>,
+    ) {
+        let d = *depth;
+        match step {
+            Step::Int(x) => {
+                terms.push(Term::Int(*x));
+                *depth += 1;
+            }
+            Step::Binary(term) if d >= 2 => {
+                terms.push(term.clone());
+                *depth -= 1;
+            }
+            Step::Div(x) if d >= 1 => terms.extend([Term::Int(*x), Term::Div]),
+            Step::Unary(term) if d >= 1 => terms.push(term.clone()),
+            Step::Shuffle(term) => {
+                let (needs, gives) = match term {
+                    Term::Dup => (1, 2),
+                    Term::Drop => (1, 0),
+                    Term::Swap => (2, 2),
+                    Term::Over => (2, 3),
+                    Term::Rot | Term::MinusRot => (3, 3),
+                    Term::Nip => (2, 1),
+                    Term::Tuck => (2, 3),
+                    Term::TwoDup => (2, 4),
+                    Term::TwoDrop => (2, 0),
+                    _ => unreachable!(),
+                };
+                if d >= needs {
+                    terms.push(term.clone());
+                    *depth = d - needs + gives;
+                }
+            }
+            Step::Take(n) if d > *n => terms.extend([Term::Int(*n as i32), Term::Take]),
+            Step::Pick(n) if d > *n => {
+                terms.extend([Term::Int(*n as i32), Term::Pick]);
+                *depth += 1;
+            }
+            Step::Print if d >= 1 => {
+                terms.push(Term::Print);
+                *depth -= 1;
+            }
+            Step::Scan => {
+                terms.push(Term::Scan);
+                *depth += 1;
+            }
+            Step::TryScan => {
+                terms.push(Term::TryScan);
+                *depth += 2;
+            }
+            Step::If if d >= 3 => {
+                terms.push(Term::If);
+                *depth -= 2;
+            }
+            Step::Bind(name) if top_level && d >= 1 => {
+                terms.push(Term::Bind {
+                    identifier: identifier(*name),
+                });
+                self.bound.push(*name);
+                *depth -= 1;
+            }
+            Step::Put(name) if self.bound.contains(name) => {
+                terms.push(Term::Put {
+                    identifier: identifier(*name),
+                });
+                *depth += 1;
+            }
+            Step::Load(values, index) => {
+                terms.extend([
+                    Term::Array {
+                        values: values.clone(),
+                    },
+                    Term::Int(*index),
+                    Term::Load,
+                ]);
+                *depth += 1;
+            }
+            Step::Store(values, index) if d >= 1 => {
+                let array = || Term::Put {
+                    identifier: "array".to_string(),
+                };
+                terms.extend([
+                    Term::Array {
+                        values: values.clone(),
+                    },
+                    Term::Bind {
+                        identifier: "array".to_string(),
+                    },
+                    array(),
+                    Term::Int(*index),
+                    Term::Store,
+                    array(),
+                    Term::Int(*index),
+                    Term::Load,
+                ]);
+            }
+            Step::Apply(body) => {
+                let body = self.body(body, depth, None);
+                terms.extend([Term::List { terms: body }, Term::Apply]);
+            }
+            Step::Curry(x, q) => {
+                *depth += 1;
+                let q = self.body(q, depth, None);
+                terms.extend([
+                    Term::Int(*x),
+                    Term::List { terms: q },
+                    Term::Curry,
+                    Term::Apply,
+                ]);
+            }
+            Step::Compose(p, q) => {
+                let p = self.body(p, depth, None);
+                let q = self.body(q, depth, None);
+                terms.extend([
+                    Term::List { terms: p },
+                    Term::List { terms: q },
+                    Term::Compose,
+                    Term::Apply,
+                ]);
+            }
+            Step::Ifte(a, b) if d >= 1 => {
+                *depth -= 1;
+                let target = Some(*depth);
+                let a = self.body(a, &mut depth.clone(), target);
+                let b = self.body(b, &mut depth.clone(), target);
+                terms.extend([Term::List { terms: a }, Term::List { terms: b }, Term::Ifte]);
+            }
+            Step::Times(n, body) => {
+                let body = self.body(body, &mut depth.clone(), Some(d));
+                terms.extend([Term::Int(*n), Term::List { terms: body }, Term::Times]);
+            }
+            Step::Each(from, to, body) => {
+                let body = self.body(body, &mut (d + 1), Some(d));
+                terms.extend([
+                    Term::Int(*from),
+                    Term::Int(*to),
+                    Term::List { terms: body },
+                    Term::Each,
+                ]);
+            }
+            _ => {}
+        }
+    }
+
+    /// Lowers a list body starting at `depth`.
+    /// A body which is executed repeatedly is balanced to leave the stack `target` deep.
+    fn body(&mut self, steps: &[Step], depth: &mut usize, target: Option<usize>) -> Vec<Term> {
+        let mut terms = Vec::new();
+        self.lower(steps, depth, false, &mut terms);
+
+        if let Some(target) = target {
+            terms.extend(std::iter::repeat_n(
+                Term::Print,
+                depth.saturating_sub(target),
+            ));
+            terms.extend(std::iter::repeat_n(
+                Term::Int(0),
+                target.saturating_sub(*depth),
+            ));
+            *depth = target;
+        }
+
+        terms
+    }
+}
+
+fn identifier(name: usize) -> String {
+    format!("v{}", name)
+}
+
+fn render(terms: &[Term]) -> String {
+    terms.iter().map(render_term).collect::<Vec<_>>().join(" ")
+}
+
+fn render_term(term: &Term) -> String {
+    match term {
+        Term::Int(x) => x.to_string(),
+        Term::List { terms } => format!("[ {} ]", render(terms)),
+        Term::Array { values } => format!(
+            "{{ {} }}",
+            values
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        ),
+        Term::Bind { identifier } => format!(":{}", identifier),
+        Term::Put { identifier } => identifier.clone(),
+        term => term
+            .keyword()
+            .unwrap_or_else(|| unreachable!("{:?} is not generated", term))
+            .to_string(),
+    }
+}
+
+fn native(source: &str, stdin: &str) -> anyhow::Result<(String, Option<i32>)> {
+    compiler
+        .compile_with_args(source, &["--strict-scan", "--debug-checks"])?
+        .and_execute_with_status(stdin)
+}
+
+fn interpreted(source: &str, stdin: &str) -> anyhow::Result<(String, Option<i32>)> {
+    let ast = lib::Loader::new(Vec::new(), true).load_source(source, &std::env::temp_dir())?;
+    let options = lib::TranslationOptions {
+        strict_scan: true,
+        debug_checks: true,
+        debug_info: false,
+    };
+
+    let mut output = Vec::new();
+    let code = match lib::interpret(&ast, &options, &mut Cursor::new(stdin), &mut output) {
+        Ok(()) => 0,
+        Err(_) => ERROR_EXIT_CODE,
+    };
+
+    Ok((String::from_utf8(output)?, Some(code)))
+}
+
+proptest! {
+    // every case is compiled, assembled and linked, so the number of cases is kept small
+    #![proptest_config(ProptestConfig {
+        cases: 48,
+        max_shrink_iters: 512,
+        ..ProptestConfig::default()
+    })]
+
+    #[test]
+    fn native_matches_interpreter(steps in program(), stdin in stdin()) {
+        let source = render(&Lowering::program(&steps));
+
+        let expected = interpreted(&source, &stdin).map_err(|e| TestCaseError::fail(e.to_string()))?;
+        let actual = native(&source, &stdin).map_err(|e| TestCaseError::fail(e.to_string()))?;
+
+        prop_assert_eq!(expected, actual, "program: {}\nstdin: {:?}", source, stdin);
+    }
+}
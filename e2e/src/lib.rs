@@ -1,6 +1,15 @@
 #[allow(dead_code)]
 mod util;
 
+#[cfg(test)]
+mod cases;
+
+#[cfg(test)]
+mod differential;
+
+#[cfg(test)]
+mod snapshots;
+
 #[cfg(test)]
 mod tests {
     use std::process::Command;
@@ -28,6 +37,58 @@ mod tests {
         compile_run_assert_with_stdin("& .", "1\n", "1\n")
     }
 
+    #[test]
+    fn scan_operator_eof() -> Result<()> {
+        compile_run_assert_with_stdin("& .", "0\n", "")
+    }
+
+    #[test]
+    fn scan_operator_malformed_input() -> Result<()> {
+        compile_run_assert_with_stdin("& .", "0\n", "abc\n")
+    }
+
+    #[test]
+    fn scan_operator_skips_malformed_word() -> Result<()> {
+        compile_run_assert_with_stdin("& . & . & . &? . .", "1\n0\n2\n0\n0\n", "1 x 2")
+    }
+
+    #[test]
+    fn try_scan_operator() -> Result<()> {
+        compile_run_assert_with_stdin("&? . .", "1\n5\n", "5\n")
+    }
+
+    #[test]
+    fn try_scan_operator_eof() -> Result<()> {
+        compile_run_assert_with_stdin("&? . .", "0\n0\n", "")
+    }
+
+    #[test]
+    fn try_scan_until_eof() -> Result<()> {
+        compile_run_assert_with_stdin(
+            "0 [ &? [ + sum! ] [ drop ] 2 take ? ! ] :sum sum! .",
+            "6\n",
+            "1 2 3\n",
+        )
+    }
+
+    #[test]
+    fn strict_scan_flag_aborts_on_eof() -> Result<()> {
+        let result = compiler
+            .compile_with_args("& .", &["--strict-scan"])?
+            .and_execute_once("");
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn strict_scan_flag_reads_number() -> Result<()> {
+        let actual_output = &compiler
+            .compile_with_args("& .", &["--strict-scan"])?
+            .and_execute_once("7\n")?;
+        assert_eq!("7\n", actual_output);
+        Ok(())
+    }
+
     #[test]
     fn ignore_comments() -> Result<()> {
         compile_run_assert("1 2 # 5 * \n+ .", "3\n")
@@ -58,6 +119,11 @@ mod tests {
         compile_run_assert("5 2 / .", "2\n")
     }
 
+    #[test]
+    fn div_operator_negative() -> Result<()> {
+        compile_run_assert("-7 2 / . 7 -2 / . -8 -2 / .", "-3\n-3\n4\n")
+    }
+
     #[test]
     fn dup_operator() -> Result<()> {
         compile_run_assert("2 dup . .", "2\n2\n")
@@ -78,6 +144,67 @@ mod tests {
         compile_run_assert("1 2 3 0 take . . .", "3\n2\n1\n")
     }
 
+    #[test]
+    fn take_operator_computed_depth() -> Result<()> {
+        compile_run_assert("1 2 3 1 1 + take . . .", "1\n3\n2\n")
+    }
+
+    #[test]
+    fn take_operator_deep() -> Result<()> {
+        compile_run_assert(
+            "1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 19 take . . .",
+            "1\n20\n19\n",
+        )
+    }
+
+    #[test]
+    fn swap_operator() -> Result<()> {
+        compile_run_assert("1 2 swap . .", "1\n2\n")
+    }
+
+    #[test]
+    fn over_operator() -> Result<()> {
+        compile_run_assert("1 2 over . . .", "1\n2\n1\n")
+    }
+
+    #[test]
+    fn rot_operator() -> Result<()> {
+        compile_run_assert("1 2 3 rot . . .", "1\n3\n2\n")
+    }
+
+    #[test]
+    fn minus_rot_operator() -> Result<()> {
+        compile_run_assert("1 2 3 -rot . . .", "2\n1\n3\n")
+    }
+
+    #[test]
+    fn nip_operator() -> Result<()> {
+        compile_run_assert("1 2 3 nip . .", "3\n1\n")
+    }
+
+    #[test]
+    fn tuck_operator() -> Result<()> {
+        compile_run_assert("1 2 tuck . . .", "2\n1\n2\n")
+    }
+
+    #[test]
+    fn pick_operator() -> Result<()> {
+        compile_run_assert_many(&[
+            ("1 2 3 0 pick . . . .", "3\n3\n2\n1\n", "0 pick"),
+            ("1 2 3 2 pick . . . .", "1\n3\n2\n1\n", "2 pick"),
+        ])
+    }
+
+    #[test]
+    fn two_dup_operator() -> Result<()> {
+        compile_run_assert("1 2 2dup . . . .", "2\n1\n2\n1\n")
+    }
+
+    #[test]
+    fn two_drop_operator() -> Result<()> {
+        compile_run_assert("1 2 3 2drop .", "1\n")
+    }
+
     #[test]
     fn make_list() -> Result<()> {
         compile_run_assert("[ 1 2 3 + + .]", "")
@@ -205,13 +332,775 @@ mod tests {
         compile_run_assert("42 :foo foo .", "42\n")
     }
 
+    #[test]
+    fn alloc_operator() -> Result<()> {
+        compile_run_assert("3 alloc len .", "3\n")
+    }
+
+    #[test]
+    fn alloc_operator_zeroed() -> Result<()> {
+        compile_run_assert("2 alloc dup 0 @ . 1 @ .", "0\n0\n")
+    }
+
+    #[test]
+    fn store_and_load_operators() -> Result<()> {
+        compile_run_assert(
+            "3 alloc :arr 42 arr 1 @= -7 arr 2 @= arr 1 @ . arr 2 @ . arr 0 @ .",
+            "42\n-7\n0\n",
+        )
+    }
+
+    #[test]
+    fn arrays_do_not_overlap() -> Result<()> {
+        compile_run_assert(
+            "1 alloc :first 1 alloc :second 1 first 0 @= 2 second 0 @= first 0 @ . second 0 @ .",
+            "1\n2\n",
+        )
+    }
+
+    #[test]
+    fn out_of_bounds_with_debug_checks() -> Result<()> {
+        let result = compiler
+            .compile_with_args("2 alloc 2 @ .", &["--debug-checks"])?
+            .and_execute_once("");
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn negative_index_with_debug_checks() -> Result<()> {
+        let result = compiler
+            .compile_with_args("2 alloc -1 @ .", &["--debug-checks"])?
+            .and_execute_once("");
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn in_bounds_with_debug_checks() -> Result<()> {
+        let actual_output = &compiler
+            .compile_with_args("2 alloc :a 5 a 1 @= a 1 @ .", &["--debug-checks"])?
+            .and_execute_once("")?;
+        assert_eq!("5\n", actual_output);
+        Ok(())
+    }
+
+    #[test]
+    fn alloc_negative_size() -> Result<()> {
+        let result = compiler.compile("-1 alloc")?.and_execute_once("");
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[parameterized(
+        size = { 536870912, 2147483647 }
+    )]
+    fn alloc_huge_size(size: i32) -> Result<()> {
+        let program = format!("1 . {} alloc len .", size);
+        for args in [&[][..], &["--debug-checks"]] {
+            let result = compiler
+                .compile_with_args(&program, args)?
+                .and_execute_once("");
+            let error = result.unwrap_err().to_string();
+            assert!(error.contains("stdout: Ok(\"1\\n\")"), "{}", error);
+        }
+        assert!(compiler.interpret_with_args(&program, &[], "").is_err());
+        Ok(())
+    }
+
+    #[parameterized(
+        program = {
+            "0 200 [ 1000 alloc drop 1 + ] times .",
+            "0 50000 [ 1 [ drop ] curry drop 1 + ] times .",
+        }
+    )]
+    fn alloc_heap_exhausted(program: &str) -> Result<()> {
+        let program = format!("1 . {}", program);
+        let result = compiler.compile(&program)?.and_execute_once("");
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("stdout: Ok(\"1\\n\")"), "{}", error);
+        assert!(compiler.interpret_with_args(&program, &[], "").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn times_operator() -> Result<()> {
+        compile_run_assert("3 [ 1 . ] times", "1\n1\n1\n")
+    }
+
+    #[test]
+    fn times_operator_no_iterations() -> Result<()> {
+        compile_run_assert_many(&[
+            ("0 [ 1 . ] times 2 .", "2\n", "0 times"),
+            ("-3 [ 1 . ] times 2 .", "2\n", "-3 times"),
+        ])
+    }
+
+    #[test]
+    fn times_operator_bound_body() -> Result<()> {
+        compile_run_assert("[ 7 . ] :body 2 body times", "7\n7\n")
+    }
+
+    #[test]
+    fn times_operator_nested() -> Result<()> {
+        compile_run_assert("0 2 [ 3 [ 1 + ] times ] times .", "6\n")
+    }
+
+    #[test]
+    fn times_operator_many_iterations() -> Result<()> {
+        compile_run_assert("0 1000000 [ 1 + ] times .", "1000000\n")
+    }
+
+    #[test]
+    fn while_operator() -> Result<()> {
+        compile_run_assert("3 [ dup 0 > ] [ dup . 1 - ] while .", "3\n2\n1\n0\n")
+    }
+
+    #[test]
+    fn while_operator_bound_quotations() -> Result<()> {
+        compile_run_assert(
+            "[ dup 0 > ] :cond [ dup . 1 - ] :body 2 cond body while drop",
+            "2\n1\n",
+        )
+    }
+
+    #[test]
+    fn each_operator() -> Result<()> {
+        compile_run_assert("0 3 [ . ] each", "0\n1\n2\n")
+    }
+
+    #[test]
+    fn each_operator_empty_range() -> Result<()> {
+        compile_run_assert("3 3 [ . ] each 5 2 [ . ] each 1 .", "1\n")
+    }
+
+    #[test]
+    fn each_operator_bound_body() -> Result<()> {
+        compile_run_assert("[ dup * . ] :square -1 2 square each", "1\n0\n1\n")
+    }
+
+    #[test]
+    fn ifte_operator() -> Result<()> {
+        compile_run_assert_many(&[
+            ("1 [ 5 . ] [ 4 . ] ifte", "5\n", "true"),
+            ("0 [ 5 . ] [ 4 . ] ifte", "4\n", "false"),
+        ])
+    }
+
+    #[test]
+    fn ifte_operator_bound_branches() -> Result<()> {
+        compile_run_assert_many(&[
+            ("[ 5 . ] :t [ 4 . ] :f 1 t f ifte", "5\n", "true"),
+            ("[ 5 . ] :t [ 4 . ] :f 0 t f ifte", "4\n", "false"),
+        ])
+    }
+
+    #[test]
+    fn ifte_operator_recursion() -> Result<()> {
+        compile_run_assert(
+            "[ dup 1 > [ dup 1 - fac! * ] [ ] ifte ] :fac 6 fac! .",
+            "720\n",
+        )
+    }
+
+    #[test]
+    fn when_operator() -> Result<()> {
+        compile_run_assert_many(&[
+            ("1 [ 5 . ] when 1 .", "5\n1\n", "true"),
+            ("0 [ 5 . ] when 1 .", "1\n", "false"),
+        ])
+    }
+
+    #[test]
+    fn when_operator_bound_branch() -> Result<()> {
+        compile_run_assert_many(&[
+            ("[ 5 . ] :t 1 t when", "5\n", "true"),
+            ("[ 5 . ] :t 0 t when", "", "false"),
+        ])
+    }
+
+    #[test]
+    fn curry_operator() -> Result<()> {
+        compile_run_assert("5 [ 1 + ] curry ! .", "6\n")
+    }
+
+    #[test]
+    fn curry_operator_partial_application() -> Result<()> {
+        compile_run_assert(
+            "[ * ] :mul 2 mul curry :double 5 double! . 7 double! .",
+            "10\n14\n",
+        )
+    }
+
+    #[test]
+    fn compose_operator() -> Result<()> {
+        compile_run_assert("[ 1 + ] [ 2 * ] compose :f 5 f! . 1 f! .", "12\n4\n")
+    }
+
+    #[test]
+    fn curry_and_compose_nested() -> Result<()> {
+        compile_run_assert(
+            "3 [ 1 + ] curry [ 2 * ] compose 10 [ - ] curry compose ! .",
+            "-2\n",
+        )
+    }
+
+    #[test]
+    fn closures_in_combinators() -> Result<()> {
+        compile_run_assert_many(&[
+            ("10 [ + ] curry :add10 0 3 add10 times .", "30\n", "times"),
+            ("1 [ 5 . ] curry :f 1 f [ ] ifte .", "5\n1\n", "ifte"),
+            ("2 [ * . ] curry :f 0 3 f each", "0\n2\n4\n", "each"),
+        ])
+    }
+
+    #[test]
+    fn bindings_named_like_asm() -> Result<()> {
+        compile_run_assert("1 :mov 2 :rax 3 :section mov rax section + + .", "6\n")
+    }
+
+    #[test]
+    fn include_module() -> Result<()> {
+        let include_dir = compiler.make_tmp_path();
+        std::fs::create_dir_all(&include_dir)?;
+        std::fs::write(
+            include_dir.join("math.plc"),
+            "[ dup 1 > [ dup 1 - fac! * ] [ ] ifte ] :fac",
+        )?;
+
+        let result = compiler
+            .compile_with_args(
+                "include \"math.plc\" [ 2 * ] :fac 5 math::fac! fac! .",
+                &["-I", osstr_to_str(include_dir.as_os_str())?],
+            )?
+            .and_execute_once("");
+
+        let _ = std::fs::remove_dir_all(&include_dir);
+
+        assert_eq!(result?, "240\n");
+        Ok(())
+    }
+
+    #[test]
+    fn include_missing_file() -> Result<()> {
+        let result = compiler.compile("include \"missing.plc\"");
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn prelude_math() -> Result<()> {
+        compile_run_assert_many(&[
+            ("-5 abs! . 5 abs! .", "5\n5\n", "abs"),
+            ("7 neg! . -7 sign! . 0 sign! .", "-7\n-1\n0\n", "neg, sign"),
+            ("3 7 min! . 3 7 max! .", "3\n7\n", "min, max"),
+            ("7 3 mod! . -7 3 mod! .", "1\n-1\n", "mod"),
+            ("2 10 pow! . 5 0 pow! .", "1024\n1\n", "pow"),
+            ("12 18 gcd! . -4 6 gcd! . 0 0 gcd! .", "6\n2\n0\n", "gcd"),
+        ])
+    }
+
+    #[test]
+    fn prelude_logic() -> Result<()> {
+        compile_run_assert_many(&[
+            ("4 even! . 4 odd! .", "1\n0\n", "even, odd"),
+            ("5 0 xor! . 5 3 xor! .", "1\n0\n", "xor"),
+            ("1 0 implies! . 0 0 implies! .", "0\n1\n", "implies"),
+        ])
+    }
+
+    #[test]
+    fn prelude_combinators() -> Result<()> {
+        compile_run_assert_many(&[
+            ("1 2 [ 10 + ] dip! . .", "2\n11\n", "dip"),
+            ("5 [ 1 + ] keep! . .", "5\n6\n", "keep"),
+            ("6 [ 1 + ] [ 2 * ] bi! . .", "12\n7\n", "bi"),
+            ("3 [ dup . 1 - dup ] loop! drop", "3\n2\n1\n", "loop"),
+        ])
+    }
+
+    #[test]
+    fn prelude_shadowed_by_user_definition() -> Result<()> {
+        compile_run_assert("[ drop 42 ] :abs -5 abs! .", "42\n")
+    }
+
+    #[test]
+    fn no_prelude_flag() -> Result<()> {
+        let result = compiler.compile_with_args("-5 abs! .", &["--no-prelude"]);
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn macros() -> Result<()> {
+        compile_run_assert_many(&[
+            ("macro sq [ dup * ] 3 sq . 2 [ sq ] ! .", "9\n4\n", "splice"),
+            (
+                "macro flip [ :x :y x y ] 1 :x 2 3 flip . . x .",
+                "2\n3\n1\n",
+                "hygiene",
+            ),
+            (
+                "macro one [ 1 ] macro two [ one one + ] two .",
+                "2\n",
+                "nested",
+            ),
+        ])
+    }
+
+    #[test]
+    fn macro_recursion() -> Result<()> {
+        let result = compiler.compile("macro m [ m ] m");
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn const_eval() -> Result<()> {
+        compile_run_assert_many(&[
+            ("[ 1 2 3 4 5 6 * * * * * ] const-eval .", "720\n", "number"),
+            (
+                "[ 10 alloc :t 0 10 [ :i i i * t i @= ] each t ] const-eval :sq sq 7 @ . sq len .",
+                "49\n10\n",
+                "table",
+            ),
+            (
+                "[ dup 1 > [ dup 1 - fac! * ] [ ] ifte ] :fac [ 10 fac! ] const-eval .",
+                "3628800\n",
+                "definitions",
+            ),
+            ("[ 3 [ * ] curry ] const-eval 5 swap ! .", "15\n", "closure"),
+        ])
+    }
+
+    #[test]
+    fn const_eval_runtime_value() -> Result<()> {
+        let result = compiler.compile("1 :x [ x ] const-eval");
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn array_literal() -> Result<()> {
+        compile_run_assert_many(&[
+            ("{ 4 -5 6 } 1 @ .", "-5\n", "load"),
+            ("{ 4 5 6 } len . { } len .", "3\n0\n", "len"),
+            ("{ 1 2 3 } :a 7 a 0 @= a 0 @ .", "7\n", "store"),
+        ])
+    }
+
+    #[test]
+    fn run_subcommand() -> Result<()> {
+        let result = compiler.interpret_with_args(
+            "& :n 0 1 n 1 + [ + ] each . [ 3 * ] [ 1 + ] compose :f 4 f! .",
+            &[],
+            "10\n",
+        )?;
+        assert_eq!("55\n13\n", result);
+        Ok(())
+    }
+
+    #[test]
+    fn non_ascii_bindings() -> Result<()> {
+        compile_run_assert("[ dup * ] :квадрат 3 квадрат! .", "9\n")
+    }
+
+    #[test]
+    fn unbound_name() -> Result<()> {
+        let result = compiler.compile("1 :x y .");
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn rebind() -> Result<()> {
+        compile_run_assert("1 :x x . 2 :x x . [ 3 :x ] ! x .", "1\n2\n3\n")
+    }
+
+    #[test]
+    fn repl() -> Result<()> {
+        let result = compiler.repl(&[], "1 2\n[ dup\n* ] :sq\n3 sq! .\n:clear\n& -5 abs!\n7\n")?;
+        assert_eq!("<2> 1 2\n<2> 1 2\n9\n<2> 1 2\n<2> 7 5\n", result);
+        Ok(())
+    }
+
+    #[test]
+    fn repl_commands() -> Result<()> {
+        let result = compiler.repl(&[], "[ ] :f 1 :x\n:bindings\n{ 1 2 }\n")?;
+        assert_eq!("<0>\nf = [...]\nx = 1\n<1> { 1 2 }\n", result);
+        Ok(())
+    }
+
+    #[test]
+    fn repl_keeps_stack_after_error() -> Result<()> {
+        let result = compiler.repl(&["--no-prelude"], "1\nabs!\n2 +\n")?;
+        assert_eq!("<1> 1\n<1> 1\n<1> 3\n", result);
+        Ok(())
+    }
+
+    #[test]
+    fn run_subcommand_native() -> Result<()> {
+        let input_path = compiler.make_tmp_path();
+        std::fs::write(&input_path, "& 2 * .")?;
+
+        let result = compiler.run_command(
+            [
+                std::ffi::OsStr::new("run"),
+                input_path.as_os_str(),
+                std::ffi::OsStr::new("--"),
+                std::ffi::OsStr::new("arg"),
+            ],
+            "21\n",
+        );
+        let _ = std::fs::remove_file(&input_path);
+
+        assert_eq!("42\n", result?);
+        Ok(())
+    }
+
+    #[test]
+    fn run_subcommand_stdin() -> Result<()> {
+        let result = compiler.run_stdin_with_args("1 2 + . 5 abs! .", &[])?;
+        assert_eq!(("3\n5\n".to_string(), Some(0)), result);
+        Ok(())
+    }
+
+    #[parameterized(
+        args = { &["--debug-checks"], &["--interpret"] }
+    )]
+    fn run_subcommand_exit_code(args: &[&str]) -> Result<()> {
+        let result = compiler.run_stdin_with_args("1 . { 1 } 5 @ .", args)?;
+        assert_eq!(("1\n".to_string(), Some(1)), result);
+        Ok(())
+    }
+
+    #[test]
+    fn run_subcommand_matches_native() -> Result<()> {
+        let programs = [
+            ("[ dup 1 > [ dup 1 - fac! * ] [ ] ifte ] :fac 10 fac! .", ""),
+            ("1 2 3 4 2 take . . . . 1 2 3 1 pick . . . .", ""),
+            ("-7 2 / . 7 -2 / . 1 not . -1 not . 5 b .", ""),
+            ("[ 1 ] [ 2 ] 0 ? ! . { 3 4 } :a a 1 @ . a len .", ""),
+            ("0 [ &? ] [ + ] while drop .", "1 2 3 x"),
+            ("& . & . & .", "1 x2 -y 2"),
+            ("12 18 gcd! . 2 10 pow! .", ""),
+        ];
+
+        for (program, stdin) in programs {
+            let native = compiler.compile(program)?.and_execute_once(stdin)?;
+            let interpreted = compiler.interpret_with_args(program, &[], stdin)?;
+            assert_eq!(native, interpreted, "{}", program);
+        }
+        Ok(())
+    }
+
+    #[parameterized(
+        args = { &[], &["build"] }
+    )]
+    fn build_subcommand(args: &[&str]) -> Result<()> {
+        let result = compiler
+            .compile_with_args("1 2 + .", args)?
+            .and_execute_once("")?;
+        assert_eq!("3\n", result);
+        Ok(())
+    }
+
+    #[test]
+    fn check_subcommand() -> Result<()> {
+        assert_eq!("", compiler.run_command(["check", "-"], "1 :x x .")?);
+        assert!(compiler.run_command(["check", "-"], "1 :x y .").is_err());
+        assert!(compiler.run_command(["check", "-"], "1 [").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn check_subcommand_stack_underflow() -> Result<()> {
+        assert_eq!(
+            (
+                "<stdin>:2:9: error[E0003]: stack underflow\n".to_string(),
+                Some(1)
+            ),
+            compiler.run_for_errors(["check", "-"], "1 .\n5 [ . ] times")?
+        );
+        assert_eq!(
+            "",
+            compiler.run_command(["check", "-"], "3 [ 1 . ] times -5 abs! .")?
+        );
+        assert_eq!(
+            "",
+            compiler.run_command(["check", "-"], "1 [ 2 ] [ drop ] ifte . 0 [ . ] when")?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn fmt_subcommand() -> Result<()> {
+        let result = compiler.run_command(["fmt", "-"], "1  2\t+ .  # sum \n\n\n3 .")?;
+        assert_eq!("1 2 + . # sum\n\n3 .\n", result);
+
+        let input_path = compiler.make_tmp_path();
+        std::fs::write(&input_path, "[  dup * ]   :sq\n")?;
+        let result =
+            compiler.run_command([std::ffi::OsStr::new("fmt"), input_path.as_os_str()], "");
+        let formatted = std::fs::read_to_string(&input_path);
+        let _ = std::fs::remove_file(&input_path);
+
+        assert_eq!("", result?);
+        assert_eq!("[ dup * ] :sq\n", formatted?);
+        Ok(())
+    }
+
+    #[test]
+    fn fmt_check() -> Result<()> {
+        let source = "[\n  1 take   # n\n  dup # copy\n]\n";
+        assert_eq!(
+            ("1 file is not formatted\n".to_string(), Some(1)),
+            compiler
+                .run_for_errors(["fmt", "--check", "-"], source)
+                .map(|(stderr, code)| (stderr.replace("error: ", ""), code))?
+        );
+
+        let formatted = compiler.run_command(["fmt", "-"], source)?;
+        assert_eq!("[\n    1 take # n\n    dup    # copy\n]\n", formatted);
+        assert_eq!(
+            "",
+            compiler.run_command(["fmt", "--check", "-"], &formatted)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn human_diagnostics() -> Result<()> {
+        let result = compiler.run_for_errors(["check", "-"], "1 :x\n y .")?;
+        assert_eq!(
+            (
+                "<stdin>:2:2: error[E0002]: `y` is not bound\n".to_string(),
+                Some(1)
+            ),
+            result
+        );
+        Ok(())
+    }
+
+    #[parameterized(
+        args = {
+            &["check", "-"],
+            &["check", "-W", "warnings", "-A", "constant_condition", "-"],
+            &["check", "--deny", "noop_take", "-A", "unused_binding", "-"],
+            &["check", "-D", "warnings", "-"],
+            &["check", "-A", "warnings", "-D", "unused_binding", "-"],
+            &["check", "-A", "warnings", "-"],
+            &["check", "-D", "no_such_lint", "-"],
+        },
+        expected = {
+            ("<stdin>:1:3: warning[unused_binding]: `x` is bound but never used\n<stdin>:2:5: warning[noop_take]: `0 take` does nothing\n", Some(0)),
+            ("<stdin>:1:3: warning[unused_binding]: `x` is bound but never used\n<stdin>:2:5: warning[noop_take]: `0 take` does nothing\n", Some(0)),
+            ("<stdin>:2:5: error[noop_take]: `0 take` does nothing\nerror: could not compile `<stdin>` because of 1 denied lint\n", Some(1)),
+            ("<stdin>:1:3: error[unused_binding]: `x` is bound but never used\n<stdin>:2:5: error[noop_take]: `0 take` does nothing\nerror: could not compile `<stdin>` because of 2 denied lints\n", Some(1)),
+            ("<stdin>:1:3: error[unused_binding]: `x` is bound but never used\nerror: could not compile `<stdin>` because of 1 denied lint\n", Some(1)),
+            ("", Some(0)),
+            ("error: unknown lint `no_such_lint`\n", Some(1)),
+        }
+    )]
+    fn lints(args: &[&str], expected: (&str, Option<i32>)) -> Result<()> {
+        let result = compiler.run_for_errors(args, "1 :x\n2 3 0 take . .")?;
+        assert_eq!((expected.0.to_string(), expected.1), result);
+        Ok(())
+    }
+
+    #[test]
+    fn allow_comments() -> Result<()> {
+        let source = "#!allow(unused_binding)\n1 :x\n[ 1 ] drop # not allowed\n2 3 0 take . . #!allow(noop_take)";
+        let result = compiler.run_for_errors(["check", "-D", "warnings", "-"], source)?;
+        assert_eq!(
+            (
+                "<stdin>:3:1: error[unused_list]: the list is never used\nerror: could not compile `<stdin>` because of 1 denied lint\n".to_string(),
+                Some(1)
+            ),
+            result
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn explain() -> Result<()> {
+        let explanation = compiler.run_command(["--explain", "E0003"], "")?;
+        assert!(
+            explanation.starts_with("A term needs more values than there are on the stack.\n"),
+            "{}",
+            explanation
+        );
+        assert!(compiler.run_command(["--explain", "E9999"], "").is_err());
+        Ok(())
+    }
+
+    #[parameterized(
+        args = {
+            &["check", "--message-format", "json", "-"],
+            &["run", "--message-format=json", "-"],
+            &["--message-format=json", "-S", "-"],
+        }
+    )]
+    fn json_diagnostics(args: &[&str]) -> Result<()> {
+        let (stderr, code) = compiler.run_for_errors(args, "1 [ 2 ) ]")?;
+        assert_eq!(Some(1), code);
+
+        let diagnostics: Vec<serde_json::Value> = stderr
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        assert_eq!(
+            vec![serde_json::json!({
+                "severity": "error",
+                "code": "E0001",
+                "message": "unexpected `)`",
+                "file": "<stdin>",
+                "span": {
+                    "byte_start": 6,
+                    "byte_end": 7,
+                    "line_start": 1,
+                    "column_start": 7,
+                    "line_end": 1,
+                    "column_end": 8,
+                },
+                "notes": [],
+            })],
+            diagnostics
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn json_runtime_errors() -> Result<()> {
+        let (stderr, code) = compiler.run_for_errors(
+            ["run", "--interpret", "--message-format=json", "-"],
+            "1 0 / .",
+        )?;
+        assert_eq!(Some(1), code);
+
+        let diagnostic: serde_json::Value = serde_json::from_str(stderr.trim_end())?;
+        assert_eq!(serde_json::json!("E0015"), diagnostic["code"]);
+        assert_eq!(serde_json::json!("division by zero"), diagnostic["message"]);
+        Ok(())
+    }
+
+    #[parameterized(
+        args = { &["--emit", "ast"], &["build", "--emit=ast"] }
+    )]
+    fn emit_flag(args: &[&str]) -> Result<()> {
+        let input_path = compiler.make_tmp_path();
+        std::fs::write(&input_path, "2 :x x .")?;
+
+        let result = compiler.run_command(
+            args.iter()
+                .map(std::ffi::OsStr::new)
+                .chain([input_path.as_os_str()]),
+            "",
+        );
+        let _ = std::fs::remove_file(&input_path);
+
+        assert_eq!("program\n├── 2\n├── :x\n├── x\n└── .\n", result?);
+        Ok(())
+    }
+
+    #[test]
+    fn emit_subcommand() -> Result<()> {
+        let ast = compiler.run_command(["emit", "--stage", "ast", "-"], "1 [ 2 ] .")?;
+        assert_eq!("program\n├── 1\n├── [ ]\n│   └── 2\n└── .\n", ast);
+
+        let ast = compiler.run_command(["emit", "--stage", "ast-json", "-"], "1 [ 2 ] .")?;
+        assert_eq!(
+            "{\n  \"terms\": [\n    {\n      \"int\": 1\n    },\n    {\n      \"list\": {\n        \"terms\": [\n          {\n            \"int\": 2\n          }\n        ]\n      }\n    },\n    \"print\"\n  ]\n}\n",
+            ast
+        );
+
+        let ir = compiler.run_command(["emit", "--stage", "ir", "-"], "-5 abs! .")?;
+        assert!(ir.contains("├── :prelude::abs\n"), "{}", ir);
+
+        let ir = compiler.run_command(["emit", "--stage", "ir-json", "-"], "-5 abs! .")?;
+        assert!(ir.contains("\"identifier\": \"prelude::abs\""), "{}", ir);
+
+        let asm = compiler.run_command(["emit", "--stage", "asm", "-"], "1 .")?;
+        assert!(asm.contains("section .text"), "{}", asm);
+
+        let output_path = compiler.make_tmp_path();
+        let result = compiler.run_command(
+            [
+                std::ffi::OsStr::new("emit"),
+                std::ffi::OsStr::new("--stage"),
+                std::ffi::OsStr::new("obj"),
+                std::ffi::OsStr::new("-o"),
+                output_path.as_os_str(),
+                std::ffi::OsStr::new("-"),
+            ],
+            "1 .",
+        );
+        let exists = output_path.exists();
+        let _ = std::fs::remove_file(&output_path);
+
+        result?;
+        assert!(exists, "object file exists");
+        Ok(())
+    }
+
+    #[test]
+    fn debug_info() -> Result<()> {
+        let source = "macro sq [ dup * ]\n3 sq\n.\n0 [ dup 3 < ]\n[ 1 + ] while .";
+        let asm = compiler.run_command(["emit", "--stage", "asm", "-"], source)?;
+        let debug_asm = compiler.run_command(["emit", "--stage", "asm", "-g", "-"], source)?;
+
+        let (lines, rest): (Vec<_>, Vec<_>) = debug_asm
+            .lines()
+            .partition(|x| x.trim_start().starts_with("%line"));
+        assert_eq!(
+            vec![
+                "\t%line 2+0 <stdin>",
+                "\t%line 1+0 <stdin>",
+                "\t%line 3+0 <stdin>",
+                "\t%line 4+0 <stdin>",
+                "\t%line 5+0 <stdin>",
+            ],
+            lines
+        );
+        // the directives don't change the code
+        assert_eq!(asm.lines().collect::<Vec<_>>(), rest);
+
+        let result = compiler
+            .compile_with_args(source, &["-g"])?
+            .and_execute_once("")?;
+        assert_eq!("9\n3\n", result);
+        Ok(())
+    }
+
+    #[test]
+    fn run_subcommand_strict_scan() -> Result<()> {
+        let result = compiler.interpret_with_args("& .", &["--strict-scan"], "");
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn run_subcommand_runtime_error() -> Result<()> {
+        let result = compiler.interpret_with_args("1 . 3 alloc 5 @", &[], "");
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("stdout: Ok(\"1\\n\")"), "{}", error);
+        Ok(())
+    }
+
+    #[test]
+    fn apply_array() -> Result<()> {
+        let result = compiler.compile("1 alloc !")?.and_execute_once("");
+        assert!(result.is_err());
+        Ok(())
+    }
+
     #[parameterized(
         flag = { "-h", "--help" }
     )]
     fn help_message(flag: &str) -> Result<()> {
         run_assert(
             &[flag],
-            "postfix language compiler\n\nUsage: plc [OPTIONS] <FILE>\n\nArguments:\n  <FILE>  \n\nOptions:\n  -S, --compile-only   Only compile file to nasm; do not assemble or link\n  -c, --assemble-only  Compile and assemble, but do not link\n  -o, --output <FILE>  Place the output file into FILE\n  -h, --help           Print help\n  -V, --version        Print version\n",
+            "postfix language compiler\n\nUsage: plc [OPTIONS] <FILE>\n       plc <COMMAND>\n\nCommands:\n  check  Parse and analyse the program without generating code\n  build  Compile the program into an executable\n  run    Compile the program into a temporary executable and run it\n  fmt    Format the program files in place\n  emit   Print the program at a stage of the compilation\n  repl   Execute lines interactively on a persistent stack\n  help   Print this message or the help of the given subcommand(s)\n\nArguments:\n  <FILE>  \n\nOptions:\n  -S, --compile-only                 Only compile file to nasm; do not assemble or link\n  -c, --assemble-only                Compile and assemble, but do not link\n      --emit <STAGE>                 Print the program at STAGE instead of building it, the same as `plc emit --stage`\n  -o, --output <FILE>                Place the output file into FILE\n      --strict-scan                  Abort the program with an error if `&` can't read a number\n      --debug-checks                 Insert runtime checks into the program, e.g. array bounds checking\n  -g                                 Generate DWARF debug info, so a debugger shows the lines of the program\n      --no-prelude                   Don't include the standard prelude\n  -I <DIR>                           Add DIR to the list of directories searched for included files\n  -W, --warn <LINT>                  Warn about LINT\n  -A, --allow <LINT>                 Allow LINT\n  -D, --deny <LINT>                  Report LINT as an error, e.g. `-D warnings`\n      --message-format <human|json>  How to print the errors [default: human]\n      --explain <CODE>               Print the long description of an error code, e.g. E0002\n  -h, --help                         Print help\n  -V, --version                      Print version\n",
         )
     }
 
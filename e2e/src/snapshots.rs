@@ -0,0 +1,189 @@
+//! Golden-file tests of the generated assembly.
+//!
+//! Every `examples/*.plc` and every program from [`PROGRAMS`] is translated and compared with
+//! the checked-in `snapshots/<group>/<name>.asm`. After an intended change of the codegen
+//! the snapshots are rewritten with `UPDATE_SNAPSHOTS=1 cargo test -p e2e snapshots`.
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use similar::TextDiff;
+
+const UPDATE_ENV: &str = "UPDATE_SNAPSHOTS";
+
+/// Focused programs: each of them pins the code of a single construct.
+const PROGRAMS: &[(&str, &str, Options)] = &[
+    ("arithmetic", "1 2 + 3 - 4 * 5 / .", Options::NONE),
+    ("scan", "& &? . .", Options::NONE),
+    ("strict_scan", "& .", Options::STRICT_SCAN),
+    (
+        "stack",
+        "1 2 3 dup drop swap over rot -rot nip tuck 2dup 2drop",
+        Options::NONE,
+    ),
+    (
+        "literal_take",
+        "1 2 3 4 0 take 1 take 2 take 3 take",
+        Options::NONE,
+    ),
+    ("dynamic_take", "1 2 3 1 1 + take 0 pick", Options::NONE),
+    (
+        "lists",
+        "[ 1 + ] :inc 1 inc! [ 2 * ] compose 3 swap curry !",
+        Options::NONE,
+    ),
+    ("ifte", "1 [ 2 ] [ 3 ] ifte 0 [ 4 ] when", Options::NONE),
+    (
+        "dynamic_ifte",
+        "[ 2 ] :yes [ 3 ] :no 1 yes no ifte",
+        Options::NONE,
+    ),
+    ("logic", "1 2 < 3 4 >= and 5 not or b 1 2 ?", Options::NONE),
+    ("times", "0 5 [ 1 + ] times .", Options::NONE),
+    ("each", "0 1 10 [ + ] each .", Options::NONE),
+    ("while", "0 [ dup 10 < ] [ 1 + ] while .", Options::NONE),
+    (
+        "arrays",
+        "3 alloc :a 7 a 0 @= a 0 @ . a len .",
+        Options::NONE,
+    ),
+    (
+        "bounds_checks",
+        "3 alloc :a 7 a 0 @= a 0 @ .",
+        Options::DEBUG_CHECKS,
+    ),
+    ("array_literal", "{ 1 2 3 } 1 @ .", Options::NONE),
+    ("bindings", "1 :x x x + :x x .", Options::NONE),
+    ("prelude", "-5 abs! . 12 18 gcd! .", Options::NONE),
+    (
+        "const_eval",
+        "[ 4 alloc :t 0 4 [ :i i i * t i @= ] each t ] const-eval 2 @ .",
+        Options::NONE,
+    ),
+];
+
+#[derive(Clone, Copy)]
+struct Options {
+    strict_scan: bool,
+    debug_checks: bool,
+}
+
+impl Options {
+    const NONE: Options = Options {
+        strict_scan: false,
+        debug_checks: false,
+    };
+    const STRICT_SCAN: Options = Options {
+        strict_scan: true,
+        debug_checks: false,
+    };
+    const DEBUG_CHECKS: Options = Options {
+        strict_scan: false,
+        debug_checks: true,
+    };
+
+    fn translation_options(self) -> lib::TranslationOptions {
+        lib::TranslationOptions {
+            strict_scan: self.strict_scan,
+            debug_checks: self.debug_checks,
+            debug_info: false,
+        }
+    }
+}
+
+fn snapshots_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("snapshots")
+}
+
+fn examples_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../examples")
+}
+
+/// Compares the assembly with the snapshot, or rewrites the snapshot in the update mode.
+/// A mismatch is returned as a diff from the snapshot to the actual assembly.
+fn check(snapshot: &Path, actual: &str) -> Result<()> {
+    if env::var_os(UPDATE_ENV).is_some() {
+        std::fs::create_dir_all(snapshot.parent().unwrap())?;
+        std::fs::write(snapshot, actual)?;
+        return Ok(());
+    }
+
+    let expected = std::fs::read_to_string(snapshot)
+        .map_err(|e| anyhow!("can't read {}: {}", snapshot.display(), e))?;
+    if expected == actual {
+        return Ok(());
+    }
+
+    let diff = TextDiff::from_lines(expected.as_str(), actual)
+        .unified_diff()
+        .context_radius(3)
+        .header("snapshot", "actual")
+        .to_string();
+
+    Err(anyhow!("{}", diff))
+}
+
+/// Checks every snapshot and reports all mismatches at once.
+fn check_all(snapshots: impl IntoIterator<Item = (PathBuf, Result<String>)>) {
+    let failures: Vec<_> = snapshots
+        .into_iter()
+        .filter_map(|(snapshot, actual)| {
+            actual
+                .and_then(|actual| check(&snapshot, &actual))
+                .err()
+                .map(|e| format!("{}:\n{}", snapshot.display(), e))
+        })
+        .collect();
+
+    assert!(
+        failures.is_empty(),
+        "{}\n\nrun with {}=1 to accept the changes",
+        failures.join("\n"),
+        UPDATE_ENV
+    );
+}
+
+fn translate(ast: &lib::Ast, options: Options) -> String {
+    lib::translate(ast, &options.translation_options())
+        .unwrap()
+        .into_assembly()
+}
+
+#[test]
+fn examples() {
+    let mut examples: Vec<_> = std::fs::read_dir(examples_dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|x| x == "plc"))
+        .collect();
+    examples.sort();
+
+    check_all(examples.into_iter().map(|example| {
+        let name = example.file_stem().unwrap().to_string_lossy().to_string();
+        let snapshot = snapshots_dir().join("examples").join(name + ".asm");
+        let actual = lib::load(&example, &[], true).map(|ast| translate(&ast, Options::NONE));
+        (snapshot, actual)
+    }));
+}
+
+#[test]
+fn programs() {
+    check_all(PROGRAMS.iter().map(|(name, source, options)| {
+        let snapshot = snapshots_dir().join("programs").join(format!("{name}.asm"));
+        let actual = lib::Loader::new(Vec::new(), true)
+            .load_source(source, &examples_dir())
+            .map(|ast| translate(&ast, *options));
+        (snapshot, actual)
+    }));
+}
+
+#[test]
+fn stdlib() {
+    check_all([(
+        snapshots_dir().join("stdlib.asm"),
+        Ok(lib::make_std_lib().into_assembly()),
+    )]);
+}
@@ -3,8 +3,8 @@ use core::str;
 use std::{
     env,
     ffi::OsStr,
-    io::Write,
-    path::PathBuf,
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
     process::{Command, ExitStatus, Output, Stdio},
 };
 
@@ -34,6 +34,29 @@ impl CompilationResult {
             Err(anyhow!("no output file"))
         }
     }
+
+    /// Runs the program and returns its stdout and exit code, even if it fails.
+    pub fn and_execute_with_status(self, stdin: &str) -> Result<(String, Option<i32>)> {
+        let file = self.output_file.ok_or(anyhow!("no output file"))?;
+
+        let child = Command::new(&file)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn();
+        let result = child.and_then(|child| {
+            // the program may exit without reading its input
+            match child.stdin.as_ref().unwrap().write_all(stdin.as_bytes()) {
+                Err(e) if e.kind() != ErrorKind::BrokenPipe => return Err(e),
+                _ => {}
+            }
+            child.wait_with_output()
+        });
+        let _ = std::fs::remove_file(&file);
+
+        let output = result?;
+        Ok((String::from_utf8(output.stdout)?, output.status.code()))
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -87,19 +110,30 @@ impl Compiler {
 
     pub fn compile_with_args(&self, input: &str, args: &[&str]) -> Result<CompilationResult> {
         let input_path = self.make_tmp_path();
-        let output_path = self.make_tmp_path();
 
         std::fs::write(input_path.clone(), input)?;
 
+        let result = self.compile_file_with_args(&input_path, args);
+
+        let _ = std::fs::remove_file(&input_path);
+
+        result
+    }
+
+    /// Compiles a program file in place, so its includes are found next to it.
+    pub fn compile_file_with_args(
+        &self,
+        input_path: &Path,
+        args: &[&str],
+    ) -> Result<CompilationResult> {
+        let output_path = self.make_tmp_path();
+
         let compilation_output = Command::new(self.executable.as_path())
             .args(args)
             .args(["--output"])
-            .args([&output_path, &input_path])
-            .output();
-
-        let _ = std::fs::remove_file(&input_path);
+            .args([output_path.as_path(), input_path])
+            .output()?;
 
-        let compilation_output = compilation_output?;
         if !compilation_output.status.success() {
             return Err(anyhow!(
                 "Compilation failure: {}",
@@ -116,6 +150,72 @@ impl Compiler {
         Ok(CompilationResult::new(compilation_output, output_file_path))
     }
 
+    /// Runs the program with `plc run --interpret`.
+    pub fn interpret_with_args(&self, input: &str, args: &[&str], stdin: &str) -> Result<String> {
+        let input_path = self.make_tmp_path();
+
+        std::fs::write(input_path.clone(), input)?;
+
+        let result = run_command(
+            Command::new(self.executable.as_path())
+                .args(["run", "--interpret"])
+                .args(args)
+                .arg(&input_path),
+            stdin,
+        );
+
+        let _ = std::fs::remove_file(&input_path);
+
+        result
+    }
+
+    /// Compiles and runs the program read from stdin with `plc run -`,
+    /// returns its stdout and exit code.
+    pub fn run_stdin_with_args(&self, input: &str, args: &[&str]) -> Result<(String, Option<i32>)> {
+        let child = Command::new(self.executable.as_path())
+            .arg("run")
+            .args(args)
+            .arg("-")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        child.stdin.as_ref().unwrap().write_all(input.as_bytes())?;
+        let output = child.wait_with_output()?;
+
+        Ok((String::from_utf8(output.stdout)?, output.status.code()))
+    }
+
+    /// Runs `plc` and returns its stderr and exit code.
+    pub fn run_for_errors<A, S>(&self, args: A, stdin: &str) -> Result<(String, Option<i32>)>
+    where
+        A: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let child = Command::new(self.executable.as_path())
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        child.stdin.as_ref().unwrap().write_all(stdin.as_bytes())?;
+        let output = child.wait_with_output()?;
+
+        Ok((String::from_utf8(output.stderr)?, output.status.code()))
+    }
+
+    /// Feeds the lines to `plc repl`.
+    pub fn repl(&self, args: &[&str], stdin: &str) -> Result<String> {
+        run_command(
+            Command::new(self.executable.as_path())
+                .arg("repl")
+                .args(args),
+            stdin,
+        )
+    }
+
     pub fn make_tmp_path(&self) -> PathBuf {
         env::temp_dir()
             .join(&self.tmp_dir)
@@ -0,0 +1,32 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// a program accepted by the compiler must always assemble
+fuzz_target!(|source: &str| {
+    let dir = std::env::temp_dir();
+    let Ok(ast) = lib::Loader::new(Vec::new(), true).load_source(source, &dir) else {
+        return;
+    };
+
+    let options = lib::TranslationOptions {
+        strict_scan: true,
+        debug_checks: true,
+        debug_info: false,
+    };
+    let asm = lib::translate(&ast, &options).unwrap();
+
+    lib::check_tmp_dir().unwrap();
+    let asm_path = lib::make_tmp_path();
+    let object_path = lib::make_tmp_path();
+
+    let result = lib::make_asm_file(asm, &asm_path)
+        .and_then(|_| lib::make_object_file(&asm_path, &object_path, false).map(|_| {}));
+
+    let _ = std::fs::remove_file(&asm_path);
+    let _ = std::fs::remove_file(&object_path);
+
+    if let Err(e) = result {
+        panic!("{}\n{}", e, source);
+    }
+});
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// any input is either parsed or rejected with an error, never a panic
+fuzz_target!(|source: &str| {
+    let _ = lib::parse(source);
+});
@@ -7,7 +7,7 @@ use std::{
     process::Command,
 };
 
-use crate::translator::Asm;
+use crate::{err::CompilerError, translator::Asm};
 
 const TMP_SUBDIR: &str = "plc";
 
@@ -37,19 +37,23 @@ pub fn link_to_executable_file<'a>(
         let ld_exit_code: std::process::ExitStatus = ld_command.status()?;
 
         if !ld_exit_code.success() {
-            let msg = match ld_exit_code.code() {
-                Some(code) => format!("ld returned code {code}"),
-                None => "ld was interrupted".to_string(),
-            };
-
-            return Err(anyhow!(msg));
+            return Err(CompilerError::LinkerFailed {
+                status: ld_exit_code.code(),
+            }
+            .into());
         }
     }
 
     Ok(output_path)
 }
 
-pub fn make_object_file<'a>(asm_file_path: &'a Path, output_path: &'a Path) -> Result<&'a Path> {
+/// With debug info nasm writes the DWARF line table, the lines come from the `%line`
+/// directives of the translated program.
+pub fn make_object_file<'a>(
+    asm_file_path: &'a Path,
+    output_path: &'a Path,
+    debug_info: bool,
+) -> Result<&'a Path> {
     {
         let output_path = output_path
             .to_str()
@@ -58,20 +62,22 @@ pub fn make_object_file<'a>(asm_file_path: &'a Path, output_path: &'a Path) -> R
             .to_str()
             .ok_or(anyhow!("path contains non-utf8 characters"))?;
 
-        let nasm_exit_status = Command::new("nasm")
-            .args(["-f", "elf64"])
-            .arg("-w-orphan-labels") // TODO: set only in release mode
+        let mut nasm_command = Command::new("nasm");
+        nasm_command.args(["-f", "elf64"]).arg("-w-orphan-labels"); // TODO: set only in release mode
+        if debug_info {
+            nasm_command.args(["-g", "-F", "dwarf"]);
+        }
+
+        let nasm_exit_status = nasm_command
             .args(["-o", output_path])
             .arg(asm_file_path)
             .status()?;
 
         if !nasm_exit_status.success() {
-            let msg = match nasm_exit_status.code() {
-                Some(code) => format!("nasm returned code {code}"),
-                None => "nasm was interrupted".to_string(),
-            };
-
-            return Err(anyhow!(msg));
+            return Err(CompilerError::AssemblerFailed {
+                status: nasm_exit_status.code(),
+            }
+            .into());
         }
     }
 
@@ -1,4 +1,7 @@
-#[derive(Clone, PartialEq, Debug)]
+use serde::Serialize;
+
+#[derive(Clone, PartialEq, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Term {
     Int(i32),
 
@@ -10,17 +13,38 @@ pub enum Term {
 
     Print,
     Scan,
+    TryScan,
 
     Dup,
     Drop,
     Take,
+    Swap,
+    Over,
+    Rot,
+    MinusRot,
+    Nip,
+    Tuck,
+    Pick,
+    TwoDup,
+    TwoDrop,
 
     // Lists
-    List { terms: Vec<Term> },
+    List {
+        terms: Vec<Term>,
+    },
     Apply,
+    Curry,
+    Compose,
+
+    // Loops
+    Times,
+    While,
+    Each,
 
     // Logical
     If,
+    Ifte,
+    When,
     Bool,
     Not,
     And,
@@ -32,18 +56,173 @@ pub enum Term {
     Greater,
     GreaterEquals,
 
+    // Arrays
+    Alloc,
+    Load,
+    Store,
+    Len,
+    /// Statically allocated array, e.g. a table computed by `const-eval`
+    Array {
+        values: Vec<i32>,
+    },
+
     // Bindings
-    Bind { identifier: String },
-    Put { identifier: String },
+    Bind {
+        identifier: String,
+    },
+    Put {
+        identifier: String,
+    },
+
+    // Modules
+    Include {
+        path: String,
+    },
+
+    // Compile time
+    Macro {
+        name: String,
+        terms: Vec<Term>,
+    },
+    ConstEval,
+
+    // Debug info
+    /// The place of the next term in the source, inserted by the loader when asked for
+    /// line info; it does nothing at runtime
+    Line {
+        file: String,
+        line: usize,
+    },
 }
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize)]
 pub struct Ast {
     pub terms: Vec<Term>,
 }
 
+/// A range of bytes in the source.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A term together with its place in the source; the terms of lists and macros are nested.
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct Node {
+    pub term: Term,
+    pub span: Span,
+    pub children: Vec<Node>,
+}
+
+/// The terms which are written as a single word, see [`Term::keyword`].
+pub const KEYWORD_TERMS: &[Term] = &[
+    Term::Add,
+    Term::Sub,
+    Term::Mul,
+    Term::Div,
+    Term::Print,
+    Term::Scan,
+    Term::TryScan,
+    Term::Dup,
+    Term::Drop,
+    Term::Take,
+    Term::Swap,
+    Term::Over,
+    Term::Rot,
+    Term::MinusRot,
+    Term::Nip,
+    Term::Tuck,
+    Term::Pick,
+    Term::TwoDup,
+    Term::TwoDrop,
+    Term::Apply,
+    Term::Curry,
+    Term::Compose,
+    Term::Times,
+    Term::While,
+    Term::Each,
+    Term::If,
+    Term::Ifte,
+    Term::When,
+    Term::Bool,
+    Term::Not,
+    Term::And,
+    Term::Or,
+    Term::Equals,
+    Term::NotEquals,
+    Term::Less,
+    Term::LessEquals,
+    Term::Greater,
+    Term::GreaterEquals,
+    Term::Alloc,
+    Term::Load,
+    Term::Store,
+    Term::Len,
+    Term::ConstEval,
+];
+
 impl Ast {
     pub fn from_terms(terms: Vec<Term>) -> Ast {
         Ast { terms }
     }
 }
+
+impl Term {
+    /// The spelling of a term which is a single word, e.g. `dup` or `@=`.
+    pub fn keyword(&self) -> Option<&'static str> {
+        Some(match self {
+            Term::Add => "+",
+            Term::Sub => "-",
+            Term::Mul => "*",
+            Term::Div => "/",
+            Term::Print => ".",
+            Term::Scan => "&",
+            Term::TryScan => "&?",
+            Term::Dup => "dup",
+            Term::Drop => "drop",
+            Term::Take => "take",
+            Term::Swap => "swap",
+            Term::Over => "over",
+            Term::Rot => "rot",
+            Term::MinusRot => "-rot",
+            Term::Nip => "nip",
+            Term::Tuck => "tuck",
+            Term::Pick => "pick",
+            Term::TwoDup => "2dup",
+            Term::TwoDrop => "2drop",
+            Term::Apply => "!",
+            Term::Curry => "curry",
+            Term::Compose => "compose",
+            Term::Times => "times",
+            Term::While => "while",
+            Term::Each => "each",
+            Term::If => "?",
+            Term::Ifte => "ifte",
+            Term::When => "when",
+            Term::Bool => "b",
+            Term::Not => "not",
+            Term::And => "and",
+            Term::Or => "or",
+            Term::Equals => "==",
+            Term::NotEquals => "!=",
+            Term::Less => "<",
+            Term::LessEquals => "<=",
+            Term::Greater => ">",
+            Term::GreaterEquals => ">=",
+            Term::Alloc => "alloc",
+            Term::Load => "@",
+            Term::Store => "@=",
+            Term::Len => "len",
+            Term::ConstEval => "const-eval",
+            Term::Int(_)
+            | Term::List { .. }
+            | Term::Array { .. }
+            | Term::Bind { .. }
+            | Term::Put { .. }
+            | Term::Include { .. }
+            | Term::Macro { .. }
+            | Term::Line { .. } => return None,
+        })
+    }
+}
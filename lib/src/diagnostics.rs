@@ -0,0 +1,264 @@
+use std::fmt::Display;
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::{common::Span, err::CompilerError};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A span with 1-based lines and columns, columns are counted in characters.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct Location {
+    pub byte_start: usize,
+    pub byte_end: usize,
+    pub line_start: usize,
+    pub column_start: usize,
+    pub line_end: usize,
+    pub column_end: usize,
+}
+
+impl Location {
+    pub fn new(source: &str, span: Span) -> Location {
+        let (line_start, column_start) = line_column(source, span.start);
+        let (line_end, column_end) = line_column(source, span.end);
+
+        Location {
+            byte_start: span.start,
+            byte_end: span.end,
+            line_start,
+            column_start,
+            line_end,
+            column_end,
+        }
+    }
+}
+
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line_start = before.rfind('\n').map_or(0, |x| x + 1);
+
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+/// A related place of a diagnostic, e.g. the first definition of a name defined twice.
+/// A note placed in the diagnostic's own file gets the file with [`Diagnostic::in_file`].
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct Note {
+    pub message: String,
+    pub file: Option<String>,
+    pub span: Option<Location>,
+}
+
+impl Note {
+    pub fn new(message: impl Into<String>) -> Note {
+        Note {
+            message: message.into(),
+            file: None,
+            span: None,
+        }
+    }
+
+    pub fn in_file(mut self, file: impl Into<String>) -> Note {
+        self.file = Some(file.into());
+        self
+    }
+
+    pub fn at(mut self, source: &str, span: Span) -> Note {
+        self.span = Some(Location::new(source, span));
+        self
+    }
+}
+
+impl From<String> for Note {
+    fn from(message: String) -> Note {
+        Note::new(message)
+    }
+}
+
+impl From<&str> for Note {
+    fn from(message: &str) -> Note {
+        Note::new(message)
+    }
+}
+
+/// A message about the program, optionally pointing at the place in a file it's about.
+///
+/// Errors which can be placed in the source are returned by the loader as a `Diagnostic`
+/// inside `anyhow::Error`; [`Diagnostic::from_error`] gets it back.
+#[derive(Clone, PartialEq, Eq, Debug, Error, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<String>,
+    pub message: String,
+    pub file: Option<String>,
+    pub span: Option<Location>,
+    pub notes: Vec<Note>,
+}
+
+impl Diagnostic {
+    pub fn error(message: impl Into<String>) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            code: None,
+            message: message.into(),
+            file: None,
+            span: None,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn in_file(mut self, file: impl Into<String>) -> Diagnostic {
+        let file = file.into();
+        for note in &mut self.notes {
+            if note.file.is_none() && note.span.is_some() {
+                note.file = Some(file.clone());
+            }
+        }
+        self.file = Some(file);
+        self
+    }
+
+    pub fn at(mut self, source: &str, span: Span) -> Diagnostic {
+        self.span = Some(Location::new(source, span));
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<Note>) -> Diagnostic {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// The diagnostic carried by the error, or an error diagnostic with the error's message
+    /// and the code of the [`CompilerError`] in it, if any.
+    pub fn from_error(e: &anyhow::Error) -> Diagnostic {
+        if let Some(diagnostic) = e.downcast_ref::<Diagnostic>() {
+            return diagnostic.clone();
+        }
+
+        let mut diagnostic = Diagnostic::error(format!("{:#}", e));
+        diagnostic.code = e
+            .downcast_ref::<CompilerError<'static>>()
+            .map(|x| x.code().to_string());
+        diagnostic
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+fn write_location(
+    f: &mut std::fmt::Formatter<'_>,
+    file: &Option<String>,
+    span: &Option<Location>,
+) -> std::fmt::Result {
+    match (file, span) {
+        (Some(file), Some(span)) => {
+            write!(f, "{}:{}:{}: ", file, span.line_start, span.column_start)
+        }
+        (Some(file), None) => write!(f, "{}: ", file),
+        (None, Some(span)) => write!(f, "{}:{}: ", span.line_start, span.column_start),
+        (None, None) => Ok(()),
+    }
+}
+
+/// `file:line:column: message`
+impl Display for Note {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_location(f, &self.file, &self.span)?;
+        write!(f, "{}", self.message)
+    }
+}
+
+/// `file:line:column: error: message`, followed by the notes.
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_location(f, &self.file, &self.span)?;
+
+        write!(f, "{}", self.severity)?;
+        if let Some(code) = &self.code {
+            write!(f, "[{}]", code)?;
+        }
+        write!(f, ": {}", self.message)?;
+
+        for note in &self.notes {
+            write!(f, "\n  note: {}", note)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn location() {
+        let source = "1 2\n+ ж x .";
+        assert_eq!(
+            Location::new(source, Span { start: 9, end: 10 }),
+            Location {
+                byte_start: 9,
+                byte_end: 10,
+                line_start: 2,
+                column_start: 5,
+                line_end: 2,
+                column_end: 6,
+            }
+        );
+    }
+
+    #[test]
+    fn display() {
+        let diagnostic = Diagnostic::error("`x` is not bound")
+            .in_file("main.plc")
+            .at("1\n x", Span { start: 3, end: 4 })
+            .with_note("bind it with `:x`");
+        assert_eq!(
+            diagnostic.to_string(),
+            "main.plc:2:2: error: `x` is not bound\n  note: bind it with `:x`"
+        );
+        let diagnostic = Diagnostic::error("`x` is already bound")
+            .at("1 :x :x", Span { start: 5, end: 7 })
+            .with_note(
+                Note::new("`x` is first bound here").at("1 :x :x", Span { start: 2, end: 4 }),
+            )
+            .in_file("main.plc");
+        assert_eq!(
+            diagnostic.to_string(),
+            "main.plc:1:6: error: `x` is already bound\n  note: main.plc:1:3: `x` is first bound here"
+        );
+        assert_eq!(Diagnostic::error("oops").to_string(), "error: oops");
+    }
+
+    #[test]
+    fn from_error() {
+        let diagnostic = Diagnostic::error("x").in_file("a.plc");
+        let e = anyhow::Error::new(diagnostic.clone());
+        assert_eq!(Diagnostic::from_error(&e), diagnostic);
+        assert_eq!(
+            Diagnostic::from_error(&anyhow::anyhow!("y")),
+            Diagnostic::error("y")
+        );
+
+        let e = anyhow::Error::new(CompilerError::StackUnderflow).context("const-eval");
+        let diagnostic = Diagnostic::from_error(&e);
+        assert_eq!("const-eval: stack underflow", diagnostic.message);
+        assert_eq!(Some("E0003".to_string()), diagnostic.code);
+    }
+}
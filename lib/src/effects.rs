@@ -0,0 +1,477 @@
+use std::collections::HashMap;
+
+use crate::common::{Node, Span, Term};
+
+/// How a term changes the stack.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct StackEffect {
+    /// In the Forth notation, e.g. `( a b -- b a )`
+    pub notation: &'static str,
+    /// How many values are popped and pushed, unless it depends on the values,
+    /// e.g. on the list which is applied
+    pub arity: Option<(usize, usize)>,
+}
+
+impl Term {
+    pub fn stack_effect(&self) -> StackEffect {
+        let (notation, arity) = match self {
+            Term::Int(_) => ("( -- n )", Some((0, 1))),
+            Term::Add | Term::Sub | Term::Mul | Term::Div => ("( a b -- c )", Some((2, 1))),
+            Term::Print => ("( n -- )", Some((1, 0))),
+            Term::Scan => ("( -- n )", Some((0, 1))),
+            Term::TryScan => ("( -- n ok )", Some((0, 2))),
+            Term::Dup => ("( a -- a a )", Some((1, 2))),
+            Term::Drop => ("( a -- )", Some((1, 0))),
+            Term::Take => ("( xn .. x0 n -- xn-1 .. x0 xn )", None),
+            Term::Swap => ("( a b -- b a )", Some((2, 2))),
+            Term::Over => ("( a b -- a b a )", Some((2, 3))),
+            Term::Rot => ("( a b c -- b c a )", Some((3, 3))),
+            Term::MinusRot => ("( a b c -- c a b )", Some((3, 3))),
+            Term::Nip => ("( a b -- b )", Some((2, 1))),
+            Term::Tuck => ("( a b -- b a b )", Some((2, 3))),
+            Term::Pick => ("( xn .. x0 n -- xn .. x0 xn )", None),
+            Term::TwoDup => ("( a b -- a b a b )", Some((2, 4))),
+            Term::TwoDrop => ("( a b -- )", Some((2, 0))),
+            Term::List { .. } => ("( -- [q] )", Some((0, 1))),
+            Term::Apply => ("( .. [q] -- .. )", None),
+            Term::Curry => ("( x [q] -- [x q] )", Some((2, 1))),
+            Term::Compose => ("( [p] [q] -- [p q] )", Some((2, 1))),
+            Term::Times => ("( n [q] -- .. )", None),
+            Term::While => ("( [cond] [body] -- .. )", None),
+            Term::Each => ("( from to [q] -- .. )", None),
+            Term::If => ("( a b cond -- a|b )", Some((3, 1))),
+            Term::Ifte => ("( cond [then] [else] -- .. )", None),
+            Term::When => ("( cond [q] -- .. )", None),
+            Term::Bool | Term::Not => ("( a -- b )", Some((1, 1))),
+            Term::And | Term::Or => ("( a b -- c )", Some((2, 1))),
+            Term::Equals
+            | Term::NotEquals
+            | Term::Less
+            | Term::LessEquals
+            | Term::Greater
+            | Term::GreaterEquals => ("( a b -- bool )", Some((2, 1))),
+            Term::Alloc => ("( n -- array )", Some((1, 1))),
+            Term::Load => ("( array i -- x )", Some((2, 1))),
+            Term::Store => ("( x array i -- )", Some((3, 0))),
+            Term::Len => ("( array -- n )", Some((1, 1))),
+            Term::Array { .. } => ("( -- array )", Some((0, 1))),
+            Term::Bind { .. } => ("( x -- )", Some((1, 0))),
+            Term::Put { .. } => ("( -- x )", Some((0, 1))),
+            Term::Include { .. } | Term::Macro { .. } | Term::Line { .. } => {
+                ("( -- )", Some((0, 0)))
+            }
+            Term::ConstEval => ("( [q] -- .. )", None),
+        };
+
+        StackEffect { notation, arity }
+    }
+}
+
+/// The stack depth before and after a term; `None` where it can't be known,
+/// e.g. after applying a list which comes from the input.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Depth {
+    pub span: Span,
+    pub before: Option<i32>,
+    pub after: Option<i32>,
+}
+
+/// How deep the applied lists may nest before the depth is given up on.
+const MAX_CALLS: usize = 64;
+
+/// Follows the stack depth through the program, applying the lists it can trace.
+///
+/// Every term, nested ones included, gets its depth. The depths inside a list are
+/// counted from the start of the list, so they may be negative. `definitions` are
+/// run before the program without their depths recorded, e.g. the prelude.
+pub fn stack_depths(nodes: &[Node], definitions: &[Node]) -> Vec<Depth> {
+    let mut machine = Machine::default();
+    machine.run(definitions, None);
+    machine.depth = Some(0);
+    machine.values.clear();
+
+    let mut depths = Vec::new();
+    machine.run(nodes, Some(&mut depths));
+    depths.sort_by_key(|x| x.span.start);
+    depths
+}
+
+/// The first term of the program which takes more values than the stack has,
+/// as far as the depth can be traced. A list the term applies counts as a part of it,
+/// e.g. in `5 [ . ] times` it's `times`. Lists which may not run, e.g. the body of
+/// `times` with an unknown count or a branch of `ifte`, only count if they run for sure.
+pub fn stack_underflow(nodes: &[Node], definitions: &[Node]) -> Option<Span> {
+    let mut machine = Machine::default();
+    machine.run(definitions, None);
+    machine.depth = Some(0);
+    machine.values.clear();
+
+    for node in nodes {
+        machine.step(node);
+        if machine.underflow {
+            return Some(node.span);
+        }
+        if machine.depth.is_none() {
+            break;
+        }
+    }
+
+    None
+}
+
+/// What is known about a value on the stack.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Value<'a> {
+    Unknown,
+    Int(i32),
+    List(&'a [Node]),
+}
+
+#[derive(Clone)]
+struct Machine<'a> {
+    depth: Option<i32>,
+    /// The known values on top of the stack, the ones below them are unknown
+    values: Vec<Value<'a>>,
+    bindings: HashMap<&'a str, Value<'a>>,
+    macros: HashMap<&'a str, &'a [Node]>,
+    /// The lists being applied
+    calls: Vec<&'a [Node]>,
+    /// A value was popped from the empty stack
+    underflow: bool,
+}
+
+impl Default for Machine<'_> {
+    fn default() -> Self {
+        Machine {
+            depth: Some(0),
+            values: Vec::new(),
+            bindings: HashMap::new(),
+            macros: HashMap::new(),
+            calls: Vec::new(),
+            underflow: false,
+        }
+    }
+}
+
+impl<'a> Machine<'a> {
+    fn run(&mut self, nodes: &'a [Node], mut depths: Option<&mut Vec<Depth>>) {
+        for node in nodes {
+            if let Some(depths) = depths.as_deref_mut() {
+                if let Term::List { .. } | Term::Macro { .. } = node.term {
+                    let mut list = Machine {
+                        depth: Some(0),
+                        values: Vec::new(),
+                        calls: Vec::new(),
+                        ..self.clone()
+                    };
+                    list.run(&node.children, Some(depths));
+                }
+            }
+
+            let before = self.depth;
+            if before.is_some() {
+                self.step(node);
+            }
+
+            if let Some(depths) = depths.as_deref_mut() {
+                depths.push(Depth {
+                    span: node.span,
+                    before,
+                    after: self.depth,
+                });
+            }
+        }
+    }
+
+    fn step(&mut self, node: &'a Node) {
+        match &node.term {
+            Term::Int(n) => self.push(Value::Int(*n)),
+            Term::List { .. } => self.push(Value::List(&node.children)),
+            Term::Bind { identifier } => {
+                let value = self.pop();
+                self.bindings.insert(identifier, value);
+            }
+            Term::Put { identifier } => match self.macros.get(identifier.as_str()) {
+                Some(body) => self.call(body),
+                None => {
+                    let value = self.bindings.get(identifier.as_str());
+                    self.push(value.copied().unwrap_or(Value::Unknown));
+                }
+            },
+            Term::Macro { name, .. } => {
+                self.macros.insert(name, &node.children);
+            }
+            Term::Apply | Term::ConstEval => {
+                let list = self.pop();
+                self.apply(list);
+            }
+            Term::Dup => {
+                let a = self.pop();
+                self.push_all(&[a, a]);
+            }
+            Term::Swap => self.shuffle(2, &[1, 0]),
+            Term::Over => self.shuffle(2, &[0, 1, 0]),
+            Term::Rot => self.shuffle(3, &[1, 2, 0]),
+            Term::MinusRot => self.shuffle(3, &[2, 0, 1]),
+            Term::Nip => self.shuffle(2, &[1]),
+            Term::Tuck => self.shuffle(2, &[1, 0, 1]),
+            Term::TwoDup => self.shuffle(2, &[0, 1, 0, 1]),
+            Term::Take | Term::Pick => {
+                self.pop();
+                // a value from below is moved or copied to the top
+                self.values.clear();
+                if node.term == Term::Pick {
+                    self.push(Value::Unknown);
+                }
+            }
+            Term::Times => {
+                let body = self.pop();
+                match self.pop() {
+                    Value::Int(n) if n > 0 => self.repeat(body, 0),
+                    _ => self.repeat_optional(body, 0),
+                }
+            }
+            Term::While => {
+                let body = self.pop();
+                let cond = self.pop();
+                self.repeat(cond, 1);
+                self.pop();
+                self.repeat_optional(body, 0);
+            }
+            Term::Each => {
+                let body = self.pop();
+                self.pop();
+                self.pop();
+                self.push(Value::Unknown);
+                self.repeat_optional(body, -1);
+            }
+            Term::If => {
+                self.pop();
+                let b = self.pop();
+                let a = self.pop();
+                self.push(if a == b { a } else { Value::Unknown });
+            }
+            Term::Ifte => {
+                let else_branch = self.pop();
+                let then_branch = self.pop();
+                let cond = self.pop();
+
+                let mut other = self.clone();
+                self.apply(then_branch);
+                other.apply(else_branch);
+                self.underflow = match cond {
+                    Value::Int(0) => other.underflow,
+                    Value::Int(_) => self.underflow,
+                    // either branch may be the one which runs
+                    _ => self.underflow && other.underflow,
+                };
+                if self.depth != other.depth {
+                    self.depth = None;
+                }
+            }
+            Term::When => {
+                let body = self.pop();
+                match self.pop() {
+                    Value::Int(n) if n != 0 => self.repeat(body, 0),
+                    _ => self.repeat_optional(body, 0),
+                }
+            }
+            term => match term.stack_effect().arity {
+                Some((pops, pushes)) => {
+                    for _ in 0..pops {
+                        self.pop();
+                    }
+                    for _ in 0..pushes {
+                        self.push(Value::Unknown);
+                    }
+                }
+                None => self.depth = None,
+            },
+        }
+    }
+
+    /// Applies a list which is run any number of times, so it must change the depth by `change`.
+    fn repeat(&mut self, list: Value<'a>, change: i32) {
+        let before = self.depth;
+        self.apply(list);
+        if self.depth != before.map(|x| x + change) {
+            self.depth = None;
+        }
+        // the values after zero or several runs are not the ones after one
+        self.values.clear();
+    }
+
+    /// Like [`Machine::repeat`], for a list which may not run at all, so its underflows don't count.
+    fn repeat_optional(&mut self, list: Value<'a>, change: i32) {
+        let underflow = self.underflow;
+        self.repeat(list, change);
+        self.underflow = underflow;
+    }
+
+    fn apply(&mut self, list: Value<'a>) {
+        match list {
+            Value::List(body) => self.call(body),
+            Value::Unknown | Value::Int(_) => self.depth = None,
+        }
+    }
+
+    fn call(&mut self, body: &'a [Node]) {
+        let recursive = self.calls.iter().any(|x| std::ptr::eq(*x, body));
+        if recursive || self.calls.len() >= MAX_CALLS {
+            self.depth = None;
+            return;
+        }
+
+        self.calls.push(body);
+        for node in body {
+            if self.depth.is_none() {
+                break;
+            }
+            self.step(node);
+        }
+        self.calls.pop();
+    }
+
+    /// Pops `n` values and pushes the ones at the given indices, counted from the deepest.
+    fn shuffle(&mut self, n: usize, order: &[usize]) {
+        let mut popped: Vec<_> = (0..n).map(|_| self.pop()).collect();
+        popped.reverse();
+        let pushed: Vec<_> = order.iter().map(|&i| popped[i]).collect();
+        self.push_all(&pushed);
+    }
+
+    fn pop(&mut self) -> Value<'a> {
+        self.underflow |= self.depth == Some(0);
+        self.depth = self.depth.map(|x| x - 1);
+        self.values.pop().unwrap_or(Value::Unknown)
+    }
+
+    fn push(&mut self, value: Value<'a>) {
+        self.depth = self.depth.map(|x| x + 1);
+        self.values.push(value);
+    }
+
+    fn push_all(&mut self, values: &[Value<'a>]) {
+        for value in values {
+            self.push(*value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_nodes;
+
+    /// The depth after each term of the source, nested ones included.
+    fn depths(source: &str) -> Vec<(String, Option<i32>)> {
+        let nodes = parse_nodes(source).unwrap();
+        stack_depths(&nodes, &[])
+            .into_iter()
+            .map(|x| (source[x.span.start..x.span.end].to_string(), x.after))
+            .collect()
+    }
+
+    fn after(source: &str) -> Option<i32> {
+        let nodes = parse_nodes(source).unwrap();
+        let last = nodes.last().unwrap().span;
+        stack_depths(&nodes, &[])
+            .into_iter()
+            .find(|x| x.span == last)
+            .unwrap()
+            .after
+    }
+
+    #[test]
+    fn terms() {
+        assert_eq!(
+            vec![
+                ("1".to_string(), Some(1)),
+                ("2".to_string(), Some(2)),
+                ("dup".to_string(), Some(3)),
+                ("+".to_string(), Some(2)),
+                (".".to_string(), Some(1)),
+                ("&?".to_string(), Some(3)),
+            ],
+            depths("1 2 dup + . &?")
+        );
+    }
+
+    #[test]
+    fn lists() {
+        assert_eq!(
+            vec![
+                ("1".to_string(), Some(1)),
+                ("[ 1 + ]".to_string(), Some(2)),
+                ("1".to_string(), Some(1)),
+                ("+".to_string(), Some(0)),
+                ("!".to_string(), Some(1)),
+            ],
+            depths("1 [ 1 + ] !")
+        );
+        assert_eq!(Some(1), after("[ dup * ] :sq 3 sq!"));
+        assert_eq!(Some(2), after("[ 1 ] dup ! swap !"));
+        assert_eq!(Some(2), after("macro two [ 1 1 ] two"));
+    }
+
+    #[test]
+    fn unknown() {
+        assert_eq!(None, after("& !"));
+        assert_eq!(None, after("1 2 [ 1 ] curry !"));
+        assert_eq!(None, after("[ 42 . dup ! ] dup ! 1"));
+        assert_eq!(None, after("1 [ 1 ] when"));
+        assert_eq!(Some(2), after("5 & 1 take"));
+    }
+
+    #[test]
+    fn control_flow() {
+        assert_eq!(Some(1), after("5 [ dup 0 > ] [ 1 - ] while"));
+        assert_eq!(Some(0), after("1 10 [ . ] each"));
+        assert_eq!(Some(1), after("0 3 [ 1 + ] times"));
+        assert_eq!(Some(1), after("1 [ 2 ] [ 3 ] ifte"));
+        assert_eq!(None, after("1 [ 2 ] [ ] ifte"));
+        assert_eq!(Some(0), after("1 [ . ] dup 1 ? !"));
+        assert_eq!(None, after("1 [ . ] [ drop ] 1 ? !"));
+    }
+
+    fn underflow(source: &str) -> Option<&str> {
+        let nodes = parse_nodes(source).unwrap();
+        stack_underflow(&nodes, &[]).map(|x| &source[x.start..x.end])
+    }
+
+    #[test]
+    fn underflows() {
+        assert_eq!(Some("+"), underflow("1 . 2 +"));
+        assert_eq!(Some("times"), underflow("5 [ . ] times"));
+        assert_eq!(Some("ifte"), underflow("0 [ 2 ] [ drop ] ifte"));
+        assert_eq!(Some("ifte"), underflow("& [ drop ] [ 2 drop drop ] ifte"));
+        assert_eq!(Some("sq"), underflow("macro sq [ dup * ] sq"));
+        assert_eq!(None, underflow("[ . ] :p 1 p! [ + ]"));
+        assert_eq!(None, underflow("& ! ."));
+    }
+
+    #[test]
+    fn lists_which_may_not_run() {
+        assert_eq!(None, underflow("1 [ 2 ] [ drop ] ifte ."));
+        assert_eq!(None, underflow("0 [ . ] when"));
+        assert_eq!(None, underflow("0 [ . ] times"));
+        assert_eq!(None, underflow("& [ . ] times"));
+        assert_eq!(None, underflow("& [ 2 ] [ drop ] ifte"));
+    }
+
+    #[test]
+    fn definitions() {
+        let prelude = parse_nodes("[ 0 swap - ] :neg").unwrap();
+        let nodes = parse_nodes("5 neg!").unwrap();
+        assert_eq!(
+            Some(1),
+            stack_depths(&nodes, &prelude).last().unwrap().after
+        );
+    }
+
+    #[test]
+    fn effects() {
+        assert_eq!("( a b -- b a )", Term::Swap.stack_effect().notation);
+        assert_eq!(Some((3, 0)), Term::Store.stack_effect().arity);
+    }
+}
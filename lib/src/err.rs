@@ -1,14 +1,51 @@
 use std::fmt::Display;
 
-use nom::error::{convert_error, VerboseError};
+use nom::error::{convert_error, VerboseError, VerboseErrorKind};
 use thiserror::Error;
 
+use crate::{common::Span, diagnostics::Diagnostic, parser::innermost_error};
+
+/// The errors the compiler reports, each with a stable code, see [`explain`].
 #[derive(Clone, Error, Debug)]
 pub enum CompilerError<'a> {
+    /// E0001
     ParserError {
         inp: &'a str,
         e: VerboseError<&'a str>,
     },
+    /// E0002
+    UnboundName { identifier: String },
+    /// E0003
+    StackUnderflow,
+    /// E0004
+    TypeMismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// E0005
+    AssemblerFailed { status: Option<i32> },
+    /// E0006
+    LinkerFailed { status: Option<i32> },
+    /// E0007
+    IncludeCycle { file: String },
+    /// E0008
+    IncludedFileNotFound { path: String },
+    /// E0009
+    DuplicateModule { module: String, file: String },
+    /// E0010
+    InvalidModuleName { path: String },
+    /// E0011, `what` is e.g. "`include`" or "macro `sq`"
+    NotAtTopLevel { what: String },
+    /// E0012
+    DuplicateMacro { name: String },
+    /// E0013
+    RecursiveMacro { name: String },
+    /// E0014
+    ConstEvalWithoutList,
+    /// E0015
+    DivisionByZero,
+    /// E0016, `keyword` is `include`, `macro` or `const-eval`
+    UnresolvedTerm { keyword: &'static str },
 }
 
 impl<'a> Display for CompilerError<'a> {
@@ -17,12 +54,208 @@ impl<'a> Display for CompilerError<'a> {
             CompilerError::ParserError { inp: input, e } => {
                 write!(f, "{}", convert_error(*input, e.to_owned()))
             }
+            CompilerError::UnboundName { identifier } => write!(f, "`{}` is not bound", identifier),
+            CompilerError::StackUnderflow => write!(f, "stack underflow"),
+            CompilerError::TypeMismatch { expected, found } => {
+                write!(f, "expected {}, found {}", expected, found)
+            }
+            CompilerError::AssemblerFailed { status } => tool_failed(f, "nasm", *status),
+            CompilerError::LinkerFailed { status } => tool_failed(f, "ld", *status),
+            CompilerError::IncludeCycle { file } => {
+                write!(f, "include cycle: {} includes itself", file)
+            }
+            CompilerError::IncludedFileNotFound { path } => {
+                write!(f, "can't find included file \"{}\"", path)
+            }
+            CompilerError::DuplicateModule { module, file } => {
+                write!(f, "module `{}` is defined again by {}", module, file)
+            }
+            CompilerError::InvalidModuleName { path } => {
+                write!(f, "{}: file name can't be used as a module name", path)
+            }
+            CompilerError::NotAtTopLevel { what } => {
+                write!(f, "{} is only allowed at the top level", what)
+            }
+            CompilerError::DuplicateMacro { name } => {
+                write!(f, "macro `{}` is defined more than once", name)
+            }
+            CompilerError::RecursiveMacro { name } => {
+                write!(f, "macro `{}` expands to itself", name)
+            }
+            CompilerError::ConstEvalWithoutList => {
+                write!(f, "`const-eval` expects a list literal before it")
+            }
+            CompilerError::DivisionByZero => write!(f, "division by zero"),
+            CompilerError::UnresolvedTerm { keyword } => {
+                write!(
+                    f,
+                    "`{}` must be resolved by the loader before translation",
+                    keyword
+                )
+            }
         }
     }
 }
 
+fn tool_failed(
+    f: &mut std::fmt::Formatter<'_>,
+    tool: &str,
+    status: Option<i32>,
+) -> std::fmt::Result {
+    match status {
+        Some(code) => write!(f, "{} returned code {}", tool, code),
+        None => write!(f, "{} was interrupted", tool),
+    }
+}
+
+const EXPLANATIONS: &[(&str, &str)] = &[
+    ("E0001", include_str!("explanations/E0001.md")),
+    ("E0002", include_str!("explanations/E0002.md")),
+    ("E0003", include_str!("explanations/E0003.md")),
+    ("E0004", include_str!("explanations/E0004.md")),
+    ("E0005", include_str!("explanations/E0005.md")),
+    ("E0006", include_str!("explanations/E0006.md")),
+    ("E0007", include_str!("explanations/E0007.md")),
+    ("E0008", include_str!("explanations/E0008.md")),
+    ("E0009", include_str!("explanations/E0009.md")),
+    ("E0010", include_str!("explanations/E0010.md")),
+    ("E0011", include_str!("explanations/E0011.md")),
+    ("E0012", include_str!("explanations/E0012.md")),
+    ("E0013", include_str!("explanations/E0013.md")),
+    ("E0014", include_str!("explanations/E0014.md")),
+    ("E0015", include_str!("explanations/E0015.md")),
+    ("E0016", include_str!("explanations/E0016.md")),
+];
+
+/// The long description of an error code, with examples.
+pub fn explain(code: &str) -> Option<&'static str> {
+    EXPLANATIONS
+        .iter()
+        .find(|(x, _)| x.eq_ignore_ascii_case(code))
+        .map(|(_, explanation)| *explanation)
+}
+
 impl<'a> CompilerError<'a> {
     pub fn parser_error(inp: &'a str, e: VerboseError<&'a str>) -> CompilerError<'a> {
         CompilerError::ParserError { inp, e }
     }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            CompilerError::ParserError { .. } => "E0001",
+            CompilerError::UnboundName { .. } => "E0002",
+            CompilerError::StackUnderflow => "E0003",
+            CompilerError::TypeMismatch { .. } => "E0004",
+            CompilerError::AssemblerFailed { .. } => "E0005",
+            CompilerError::LinkerFailed { .. } => "E0006",
+            CompilerError::IncludeCycle { .. } => "E0007",
+            CompilerError::IncludedFileNotFound { .. } => "E0008",
+            CompilerError::DuplicateModule { .. } => "E0009",
+            CompilerError::InvalidModuleName { .. } => "E0010",
+            CompilerError::NotAtTopLevel { .. } => "E0011",
+            CompilerError::DuplicateMacro { .. } => "E0012",
+            CompilerError::RecursiveMacro { .. } => "E0013",
+            CompilerError::ConstEvalWithoutList => "E0014",
+            CompilerError::DivisionByZero => "E0015",
+            CompilerError::UnresolvedTerm { .. } => "E0016",
+        }
+    }
+
+    /// A parse error points at the place where parsing stopped,
+    /// with what was expected there if known.
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let mut diagnostic = match self {
+            CompilerError::ParserError { inp, e } => {
+                let (rest, list) = innermost_error(e.errors.first().map_or("", |x| x.0));
+                match list.filter(|_| rest.is_empty()) {
+                    Some(list) => {
+                        let start = inp.len() - list.len();
+                        Diagnostic::error("unclosed `[`").at(
+                            inp,
+                            Span {
+                                start,
+                                end: start + 1,
+                            },
+                        )
+                    }
+                    None => unexpected(inp, rest, e),
+                }
+            }
+            error => Diagnostic::error(error.to_string()),
+        };
+
+        diagnostic.code = Some(self.code().to_string());
+        diagnostic
+    }
+}
+
+fn unexpected(inp: &str, rest: &str, e: &VerboseError<&str>) -> Diagnostic {
+    let start = inp.len() - rest.len();
+    let token = rest
+        .split(|x: char| x.is_whitespace())
+        .next()
+        .unwrap_or_default();
+    let expected = e.errors.iter().find_map(|x| match x.1 {
+        VerboseErrorKind::Context(context) => Some(context),
+        _ => None,
+    });
+
+    let message = match expected {
+        Some(expected) => format!("expected {}", expected),
+        None if token.is_empty() => "unexpected end of input".to_string(),
+        None => format!("unexpected `{}`", token),
+    };
+    Diagnostic::error(message).at(
+        inp,
+        Span {
+            start,
+            end: start + token.len(),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{diagnostics::Diagnostic, loader::Loader};
+
+    use super::*;
+
+    fn code(source: &str) -> Option<String> {
+        let e = Loader::new(Vec::new(), false)
+            .load_source(source, Path::new("/"))
+            .unwrap_err();
+        Diagnostic::from_error(&e).code
+    }
+
+    #[test]
+    fn codes() {
+        assert_eq!(Some("E0001"), code("1 2 ) .").as_deref());
+        assert_eq!(Some("E0001"), code("[ 1 2 + .").as_deref());
+        assert_eq!(Some("E0002"), code("3 sqaure! .").as_deref());
+        assert_eq!(Some("E0003"), code("[ 1 + ] const-eval .").as_deref());
+        assert_eq!(Some("E0004"), code("[ 1 ! ] const-eval").as_deref());
+        assert_eq!(Some("E0004"), code("[ [ 1 ] 2 + ] const-eval").as_deref());
+        assert_eq!(Some("E0008"), code("include \"missing.plc\"").as_deref());
+        assert_eq!(Some("E0011"), code("[ include \"a.plc\" ]").as_deref());
+        assert_eq!(Some("E0011"), code("[ macro m [ 1 ] ]").as_deref());
+        assert_eq!(
+            Some("E0012"),
+            code("macro m [ 1 ] macro m [ 2 ]").as_deref()
+        );
+        assert_eq!(Some("E0013"), code("macro m [ m ] m").as_deref());
+        assert_eq!(Some("E0014"), code("1 const-eval").as_deref());
+        assert_eq!(Some("E0015"), code("[ 1 0 / ] const-eval").as_deref());
+    }
+
+    #[test]
+    fn explanations() {
+        for (code, explanation) in EXPLANATIONS {
+            assert_eq!(Some(*explanation), explain(code));
+            assert!(explanation.ends_with('\n'), "{}", code);
+        }
+        assert!(explain("e0001").is_some());
+        assert!(explain("E9999").is_none());
+    }
 }
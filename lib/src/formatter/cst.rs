@@ -0,0 +1,212 @@
+use std::fmt::Display;
+
+use crate::{
+    common::{Node, Term},
+    err::CompilerError,
+    parser::parse_nodes,
+};
+
+/// A lossless syntax tree: writing it out gives back the source it was built from,
+/// including the whitespace and the comments.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Element<'s> {
+    /// A term which is not a list, e.g. `dup`, `:x` or `{ 1 2 }`
+    Term(&'s str),
+    List(List<'s>),
+    /// From `#` up to the end of the line
+    Comment(&'s str),
+    /// Spaces, tabs and newlines
+    Whitespace(&'s str),
+}
+
+/// `[ ... ]`, or a `macro name [ ... ]` definition, in which case the head holds
+/// everything before the `[`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct List<'s> {
+    pub head: Vec<Element<'s>>,
+    pub elements: Vec<Element<'s>>,
+}
+
+pub fn build(source: &str) -> Result<Vec<Element<'_>>, CompilerError<'_>> {
+    let nodes = parse_nodes(source)?;
+
+    Ok(elements_between(source, 0, source.len(), &nodes))
+}
+
+/// The elements of `source[start..end]`, which holds the nodes and the trivia between them.
+fn elements_between<'s>(
+    source: &'s str,
+    start: usize,
+    end: usize,
+    nodes: &[Node],
+) -> Vec<Element<'s>> {
+    let mut elements = Vec::new();
+    let mut position = start;
+
+    for node in nodes {
+        elements.extend(trivia(&source[position..node.span.start]));
+        elements.push(match node.term {
+            Term::List { .. } => Element::List(List {
+                head: Vec::new(),
+                elements: elements_between(
+                    source,
+                    node.span.start + 1,
+                    node.span.end - 1,
+                    &node.children,
+                ),
+            }),
+            Term::Macro { .. } => macro_list(source, node),
+            _ => Element::Term(&source[node.span.start..node.span.end]),
+        });
+        position = node.span.end;
+    }
+    elements.extend(trivia(&source[position..end]));
+
+    elements
+}
+
+/// `macro`, the trivia, the name and the trivia again, then the list itself.
+fn macro_list<'s>(source: &'s str, node: &Node) -> Element<'s> {
+    let text = &source[node.span.start..node.span.end];
+    let mut head = vec![Element::Term(&text[.."macro".len()])];
+
+    let mut rest = &text["macro".len()..];
+    let name_start = trivia_len(rest);
+    head.extend(trivia(&rest[..name_start]));
+    rest = &rest[name_start..];
+
+    let name_len = rest
+        .find(|x: char| x.is_whitespace() || x == '#' || x == '[')
+        .unwrap_or(rest.len());
+    head.push(Element::Term(&rest[..name_len]));
+    rest = &rest[name_len..];
+
+    let open = trivia_len(rest);
+    head.extend(trivia(&rest[..open]));
+
+    let start = node.span.end - rest.len() + open + 1;
+    Element::List(List {
+        head,
+        elements: elements_between(source, start, node.span.end - 1, &node.children),
+    })
+}
+
+/// Splits the text between terms into whitespace and comments.
+fn trivia(mut text: &str) -> Vec<Element<'_>> {
+    let mut elements = Vec::new();
+
+    while !text.is_empty() {
+        let len = if text.starts_with('#') {
+            text.find('\n').unwrap_or(text.len())
+        } else {
+            text.find('#').unwrap_or(text.len())
+        };
+        let (element, rest) = text.split_at(len);
+        elements.push(if element.starts_with('#') {
+            Element::Comment(element)
+        } else {
+            Element::Whitespace(element)
+        });
+        text = rest;
+    }
+
+    elements
+}
+
+fn trivia_len(text: &str) -> usize {
+    let mut rest = text;
+
+    loop {
+        rest = rest.trim_start();
+        match rest.strip_prefix('#') {
+            Some(comment) => rest = &comment[comment.find('\n').unwrap_or(comment.len())..],
+            None => return text.len() - rest.len(),
+        }
+    }
+}
+
+impl Display for Element<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Element::Term(text) | Element::Comment(text) | Element::Whitespace(text) => {
+                write!(f, "{}", text)
+            }
+            Element::List(list) => {
+                for element in &list.head {
+                    write!(f, "{}", element)?;
+                }
+                write!(f, "[")?;
+                for element in &list.elements {
+                    write!(f, "{}", element)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(elements: &[Element]) -> String {
+        elements.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn lossless() {
+        let sources = [
+            "",
+            "  1 2+ . # sum\n\n",
+            "[1 [ 2]! # c\n\t] dup !",
+            "macro  sq # square\n [ dup * ]\n5 sq .",
+            "{ 1  2 } include \"a # b.plc\" [ ]",
+        ];
+        for source in sources {
+            assert_eq!(source, write(&build(source).unwrap()));
+        }
+
+        let examples = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../examples");
+        for entry in std::fs::read_dir(examples).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|x| x == "plc") {
+                let source = std::fs::read_to_string(&path).unwrap();
+                assert_eq!(source, write(&build(&source).unwrap()), "{:?}", path);
+            }
+        }
+    }
+
+    #[test]
+    fn elements() {
+        assert_eq!(
+            vec![
+                Element::Term("1"),
+                Element::Whitespace(" "),
+                Element::List(List {
+                    head: Vec::new(),
+                    elements: vec![
+                        Element::Whitespace(" "),
+                        Element::Term("dup"),
+                        Element::Whitespace(" "),
+                        Element::Comment("# c"),
+                        Element::Whitespace("\n"),
+                    ]
+                }),
+                Element::Term("!"),
+            ],
+            build("1 [ dup # c\n]!").unwrap()
+        );
+        assert_eq!(
+            vec![Element::List(List {
+                head: vec![
+                    Element::Term("macro"),
+                    Element::Whitespace(" "),
+                    Element::Term("sq"),
+                    Element::Whitespace(" "),
+                ],
+                elements: vec![Element::Term("dup")],
+            })],
+            build("macro sq [dup]").unwrap()
+        );
+    }
+}
@@ -0,0 +1,352 @@
+mod cst;
+
+use std::mem;
+
+use crate::err::CompilerError;
+use cst::{Element, List};
+
+const INDENT: &str = "    ";
+
+/// Formats a program, keeping its comments and line breaks.
+///
+/// Terms on a line are separated by one space, unless they were written together, e.g.
+/// `f!`. A list on one line is written as `[ a b ]`; a list spanning several lines gets
+/// its body indented and `]` on a line of its own. The trailing comments of a run of
+/// lines without blank ones are aligned, repeated blank lines are collapsed. Programs
+/// which don't parse aren't formatted.
+pub fn format(source: &str) -> Result<String, CompilerError<'_>> {
+    let elements = cst::build(source)?;
+
+    let mut printer = Printer {
+        block_start: true,
+        ..Printer::default()
+    };
+    printer.sequence(&elements);
+    printer.finish_line();
+    while printer.lines.last().is_some_and(Line::is_blank) {
+        printer.lines.pop();
+    }
+
+    Ok(printer.write())
+}
+
+#[derive(Default)]
+struct Line {
+    indent: usize,
+    code: String,
+    comment: Option<String>,
+}
+
+impl Line {
+    fn is_blank(&self) -> bool {
+        self.code.is_empty() && self.comment.is_none()
+    }
+
+    fn width(&self) -> usize {
+        self.indent * INDENT.len() + self.code.chars().count()
+    }
+}
+
+#[derive(Default)]
+struct Printer {
+    lines: Vec<Line>,
+    line: Line,
+    indent: usize,
+    /// No blank lines are kept at the start of a file or a list
+    block_start: bool,
+}
+
+impl Printer {
+    fn sequence(&mut self, elements: &[Element]) {
+        // whether the element is written right after the previous one, without a space
+        let mut glued = false;
+
+        for element in elements {
+            match element {
+                Element::Whitespace(text) => {
+                    let newlines = text.matches('\n').count();
+                    if newlines > 0 {
+                        self.finish_line();
+                    }
+                    if newlines > 1 {
+                        self.blank_line();
+                    }
+                    glued = false;
+                }
+                Element::Comment(text) => {
+                    self.comment(text);
+                    glued = false;
+                }
+                Element::Term(text) => {
+                    self.push(&term(text), glued);
+                    glued = true;
+                }
+                Element::List(list) => {
+                    self.list(list, glued);
+                    glued = true;
+                }
+            }
+        }
+    }
+
+    fn list(&mut self, list: &List, glued: bool) {
+        if !is_multiline(list) {
+            self.push(&inline_list(list), glued);
+            return;
+        }
+
+        self.push(&format!("{}[", head(list)), glued);
+        self.indent += 1;
+
+        // a comment right after `[` stays on its line
+        let elements = match list.elements.as_slice() {
+            [Element::Whitespace(space), Element::Comment(text), rest @ ..]
+                if !space.contains('\n') =>
+            {
+                self.comment(text);
+                rest
+            }
+            [Element::Comment(text), rest @ ..] => {
+                self.comment(text);
+                rest
+            }
+            elements => elements,
+        };
+        self.finish_line();
+        self.block_start = true;
+
+        for element in &list.head {
+            if let Element::Comment(text) = element {
+                self.comment(text);
+            }
+        }
+        self.sequence(elements);
+        self.finish_line();
+        while self.lines.last().is_some_and(Line::is_blank) {
+            self.lines.pop();
+        }
+
+        self.indent -= 1;
+        self.push("]", false);
+    }
+
+    fn push(&mut self, text: &str, glued: bool) {
+        if self.line.code.is_empty() {
+            self.line.indent = self.indent;
+        } else if !glued {
+            self.line.code.push(' ');
+        }
+        self.line.code.push_str(text);
+        self.block_start = false;
+    }
+
+    /// A comment after code is a trailing one, otherwise it's on a line of its own.
+    fn comment(&mut self, text: &str) {
+        if self.line.code.is_empty() {
+            self.line.indent = self.indent;
+        }
+        self.line.comment = Some(text.trim_end().to_string());
+        self.finish_line();
+    }
+
+    fn finish_line(&mut self) {
+        if !self.line.is_blank() {
+            self.lines.push(mem::take(&mut self.line));
+            self.block_start = false;
+        }
+    }
+
+    fn blank_line(&mut self) {
+        if !self.block_start && self.lines.last().is_some_and(|x| !x.is_blank()) {
+            self.lines.push(Line::default());
+        }
+    }
+
+    fn write(self) -> String {
+        let mut formatted = String::new();
+        let mut lines = self.lines.as_slice();
+
+        while !lines.is_empty() {
+            // the trailing comments in a run of lines without blank ones are aligned
+            let group = lines.iter().take_while(|x| !x.is_blank()).count().max(1);
+            let column = lines[..group]
+                .iter()
+                .filter(|x| !x.code.is_empty() && x.comment.is_some())
+                .map(Line::width)
+                .max()
+                .unwrap_or(0)
+                + 1;
+
+            for line in &lines[..group] {
+                let mut text = INDENT.repeat(line.indent) + &line.code;
+                if let Some(comment) = &line.comment {
+                    if !line.code.is_empty() {
+                        text += &" ".repeat(column - line.width());
+                    }
+                    text += comment;
+                }
+                formatted += text.trim_end();
+                formatted.push('\n');
+            }
+
+            lines = &lines[group..];
+        }
+
+        formatted
+    }
+}
+
+/// Whether the list is written on several lines: it spans several lines in the source
+/// or has a comment, which always ends a line.
+fn is_multiline(list: &List) -> bool {
+    list.head.iter().chain(&list.elements).any(|x| match x {
+        Element::Whitespace(text) => text.contains('\n'),
+        Element::Comment(_) => true,
+        Element::List(list) => is_multiline(list),
+        Element::Term(_) => false,
+    })
+}
+
+/// `macro name `, or nothing for a plain list.
+fn head(list: &List) -> String {
+    list.head
+        .iter()
+        .filter_map(|x| match x {
+            Element::Term(text) => Some(format!("{} ", text)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn inline_list(list: &List) -> String {
+    let mut text = head(list) + "[";
+    let mut glued = false;
+
+    for element in &list.elements {
+        match element {
+            Element::Term(term_text) => {
+                if !glued {
+                    text.push(' ');
+                }
+                text += &term(term_text);
+                glued = true;
+            }
+            Element::List(list) => {
+                if !glued {
+                    text.push(' ');
+                }
+                text += &inline_list(list);
+                glued = true;
+            }
+            Element::Whitespace(_) | Element::Comment(_) => glued = false,
+        }
+    }
+
+    text + " ]"
+}
+
+/// Collapses the spaces inside a term which has them, e.g. `{ 1  2 }` or `include "a.plc"`.
+/// The path of an `include` is kept as it is, as are terms with comments inside.
+fn term(text: &str) -> String {
+    let (words, path) = text.split_at(text.find('"').unwrap_or(text.len()));
+    if words.contains('#') {
+        return text.to_string();
+    }
+
+    match words.strip_prefix('{').and_then(|x| x.strip_suffix('}')) {
+        Some(values) if values.trim().is_empty() => "{ }".to_string(),
+        Some(values) => format!(
+            "{{ {} }}",
+            values.split_whitespace().collect::<Vec<_>>().join(" ")
+        ),
+        None if path.is_empty() => words.split_whitespace().collect::<Vec<_>>().join(" "),
+        None => format!("{} {}", words.trim(), path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn spaces() {
+        assert_eq!(
+            format("1  2\t+ .   \n\n\n\t[ 1 ]  # a  comment \n").unwrap(),
+            "1 2 + .\n\n[ 1 ] # a  comment\n"
+        );
+        assert_eq!(
+            format("1 2+ f! [1]! {1  2} { }").unwrap(),
+            "1 2+ f! [ 1 ]! { 1 2 } { }\n"
+        );
+    }
+
+    #[test]
+    fn blank_lines_at_the_ends() {
+        assert_eq!(format("\n\n1 .\n\n").unwrap(), "1 .\n");
+        assert_eq!(format("").unwrap(), "");
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(
+            format("include  \"a  b.plc\"").unwrap(),
+            "include \"a  b.plc\"\n"
+        );
+    }
+
+    #[test]
+    fn lists() {
+        assert_eq!(
+            format("[]  [ [1 2]  dup]").unwrap(),
+            "[ ] [ [ 1 2 ] dup ]\n"
+        );
+        assert_eq!(
+            format("[ 1\n  [ 2\n3 ] !\n\n\n 4 ] dup !").unwrap(),
+            "[\n    1\n    [\n        2\n        3\n    ] !\n\n    4\n] dup !\n"
+        );
+        assert_eq!(format("[\n\n  1\n\n]").unwrap(), "[\n    1\n]\n");
+        assert_eq!(
+            format("macro  sq [dup *]\nmacro cube [\ndup dup * *]").unwrap(),
+            "macro sq [ dup * ]\nmacro cube [\n    dup dup * *\n]\n"
+        );
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(
+            format("# header\n1 # one\n[ # list\n  2   # two\n # own line\n] ! # apply\n\n3 # three").unwrap(),
+            "# header\n1     # one\n[     # list\n    2 # two\n    # own line\n] !   # apply\n\n3 # three\n"
+        );
+        assert_eq!(
+            format("macro m # comment\n[ 1 ]").unwrap(),
+            "macro m [\n    # comment\n    1\n]\n"
+        );
+    }
+
+    #[test]
+    fn examples() {
+        let examples = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../examples");
+        for entry in std::fs::read_dir(examples).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|x| x != "plc") {
+                continue;
+            }
+
+            let source = std::fs::read_to_string(&path).unwrap();
+            let formatted = format(&source).unwrap();
+            assert_eq!(
+                parse(&source).unwrap(),
+                parse(&formatted).unwrap(),
+                "{:?}",
+                path
+            );
+            assert_eq!(formatted, format(&formatted).unwrap(), "{:?}", path);
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(format("1 [").is_err());
+    }
+}
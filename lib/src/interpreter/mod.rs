@@ -0,0 +1,840 @@
+use anyhow::{anyhow, Result};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt::Display,
+    io::{BufRead, Write},
+    rc::Rc,
+};
+
+use crate::{
+    common::{Ast, Term},
+    err::CompilerError,
+    translator::{
+        consts::{CLOSURE_CELLS, HEAP_SIZE, OP_SIZE_BYTES},
+        TranslationOptions,
+    },
+};
+
+/// Deeper nesting of calls is reported as an error instead of exhausting the memory.
+const MAX_FRAMES: usize = 1 << 20;
+
+/// A value on the operand stack.
+#[derive(Clone, Debug)]
+pub enum Value {
+    Int(i32),
+    Quotation(Rc<Quotation>),
+    Array(Rc<RefCell<Vec<Value>>>),
+}
+
+#[derive(Debug)]
+pub enum Quotation {
+    List(Code),
+    /// `x [q] curry`: puts `x` and applies `q`
+    Curry(Value, Value),
+    /// `[p] [q] compose`: applies `p`, then `q`
+    Compose(Value, Value),
+}
+
+/// Terms prepared for execution: lists and arrays are built once, so executing
+/// a list literal doesn't copy its terms and an array literal is the same object every time.
+#[derive(Clone, Debug)]
+pub struct Code(Rc<Vec<Node>>);
+
+#[derive(Debug)]
+enum Node {
+    Term(Term),
+    List(Code),
+    Array(Rc<RefCell<Vec<Value>>>),
+}
+
+enum Frame {
+    Code {
+        code: Code,
+        pc: usize,
+    },
+    Call(Value),
+    Times {
+        body: Value,
+        remaining: i32,
+    },
+    Each {
+        body: Value,
+        next: i32,
+        end: i32,
+    },
+    While {
+        cond: Value,
+        body: Value,
+        test: bool,
+    },
+}
+
+/// Executes terms directly, without compiling them to assembly.
+///
+/// Calls are kept in an explicit frame stack, so deep recursion in a program
+/// doesn't overflow the interpreter's own stack.
+pub struct Interpreter {
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    bindings: HashMap<String, Value>,
+    fuel: Option<u64>,
+    strict_scan: bool,
+    /// The cells a compiled program would have taken from its heap, which is never freed
+    heap_used: i64,
+}
+
+/// The program's stdin and stdout.
+pub struct Io<'a> {
+    pub input: &'a mut dyn BufRead,
+    pub output: &'a mut dyn Write,
+}
+
+/// Executes the program reading `&` from `input` and printing `.` to `output`.
+/// Runtime errors which abort a native program are returned as errors.
+pub fn interpret(
+    ast: &Ast,
+    options: &TranslationOptions,
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+) -> Result<()> {
+    Interpreter::new()
+        .with_strict_scan(options.strict_scan)
+        .run_with_io(&ast.terms, &mut Io { input, output })
+}
+
+impl Code {
+    pub fn new(terms: &[Term]) -> Code {
+        Code(Rc::new(terms.iter().map(Node::new).collect()))
+    }
+
+    pub fn to_terms(&self) -> Result<Vec<Term>> {
+        self.0
+            .iter()
+            .map(|node| match node {
+                Node::Term(term) => Ok(term.clone()),
+                Node::List(code) => Ok(Term::List {
+                    terms: code.to_terms()?,
+                }),
+                Node::Array(array) => array_to_term(&array.borrow()),
+            })
+            .collect()
+    }
+}
+
+impl Node {
+    fn new(term: &Term) -> Node {
+        match term {
+            Term::List { terms } => Node::List(Code::new(terms)),
+            Term::Array { values } => Node::Array(Rc::new(RefCell::new(
+                values.iter().map(|value| Value::Int(*value)).collect(),
+            ))),
+            term => Node::Term(term.clone()),
+        }
+    }
+}
+
+impl Value {
+    pub fn quotation(terms: &[Term]) -> Value {
+        Value::Quotation(Rc::new(Quotation::List(Code::new(terms))))
+    }
+
+    /// Terms which put an equal value on the stack.
+    pub fn to_terms(&self) -> Result<Vec<Term>> {
+        match self {
+            Value::Int(value) => Ok(vec![Term::Int(*value)]),
+            Value::Quotation(quotation) => match quotation.as_ref() {
+                Quotation::List(code) => Ok(vec![Term::List {
+                    terms: code.to_terms()?,
+                }]),
+                Quotation::Curry(x, q) => Ok([x.to_terms()?, q.to_terms()?, vec![Term::Curry]]
+                    .into_iter()
+                    .flatten()
+                    .collect()),
+                Quotation::Compose(p, q) => Ok([p.to_terms()?, q.to_terms()?, vec![Term::Compose]]
+                    .into_iter()
+                    .flatten()
+                    .collect()),
+            },
+            Value::Array(array) => Ok(vec![array_to_term(&array.borrow())?]),
+        }
+    }
+}
+
+fn type_mismatch(expected: &'static str, found: &Value) -> anyhow::Error {
+    let found = match found {
+        Value::Int(_) => "a number",
+        Value::Quotation(_) => "a quotation",
+        Value::Array(_) => "an array",
+    };
+
+    CompilerError::TypeMismatch { expected, found }.into()
+}
+
+/// Numbers are shown as is, arrays by their elements and lists by their kind only.
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Quotation(_) => write!(f, "[...]"),
+            Value::Array(array) => {
+                write!(f, "{{")?;
+                for value in array.borrow().iter() {
+                    write!(f, " {}", value)?;
+                }
+                write!(f, " }}")
+            }
+        }
+    }
+}
+
+fn array_to_term(values: &[Value]) -> Result<Term> {
+    values
+        .iter()
+        .map(|value| match value {
+            Value::Int(value) => Ok(*value),
+            _ => Err(anyhow!("only arrays of numbers can be embedded")),
+        })
+        .collect::<Result<Vec<_>>>()
+        .map(|values| Term::Array { values })
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        Interpreter {
+            stack: Vec::new(),
+            frames: Vec::new(),
+            bindings: HashMap::new(),
+            fuel: None,
+            strict_scan: false,
+            heap_used: 0,
+        }
+    }
+
+    /// Limits the number of execution steps.
+    pub fn with_fuel(mut self, fuel: u64) -> Interpreter {
+        self.fuel = Some(fuel);
+        self
+    }
+
+    /// Fails `&` if a number can't be read, as `--strict-scan` does.
+    pub fn with_strict_scan(mut self, strict_scan: bool) -> Interpreter {
+        self.strict_scan = strict_scan;
+        self
+    }
+
+    pub fn bind(&mut self, identifier: &str, value: Value) {
+        self.bindings.insert(identifier.to_string(), value);
+    }
+
+    pub fn stack(&self) -> &[Value] {
+        &self.stack
+    }
+
+    pub fn bindings(&self) -> &HashMap<String, Value> {
+        &self.bindings
+    }
+
+    pub fn clear(&mut self) {
+        self.stack.clear();
+    }
+
+    /// Runs terms which don't use input and output.
+    pub fn run(&mut self, terms: &[Term]) -> Result<()> {
+        self.execute(terms, None)
+    }
+
+    pub fn run_with_io(&mut self, terms: &[Term], io: &mut Io) -> Result<()> {
+        let result = self.execute(terms, Some(io));
+        io.output.flush()?;
+        result
+    }
+
+    fn execute(&mut self, terms: &[Term], mut io: Option<&mut Io>) -> Result<()> {
+        self.frames.clear();
+        self.frames.push(Frame::Code {
+            code: Code::new(terms),
+            pc: 0,
+        });
+
+        while let Some(frame) = self.frames.pop() {
+            if let Some(fuel) = self.fuel.as_mut() {
+                if *fuel == 0 {
+                    return Err(anyhow!("the evaluation takes too long"));
+                }
+                *fuel -= 1;
+            }
+
+            match frame {
+                Frame::Code { code, pc } => {
+                    // the last term is executed without its frame, so tail calls don't pile up
+                    if pc + 1 < code.0.len() {
+                        self.push_frame(Frame::Code {
+                            code: code.clone(),
+                            pc: pc + 1,
+                        })?;
+                    }
+                    if let Some(node) = code.0.get(pc) {
+                        self.step(node, io.as_deref_mut())?;
+                    }
+                }
+                Frame::Call(value) => self.apply(value)?,
+                Frame::Times { body, remaining } => {
+                    if remaining > 0 {
+                        self.push_frame(Frame::Times {
+                            body: body.clone(),
+                            remaining: remaining - 1,
+                        })?;
+                        self.apply(body)?;
+                    }
+                }
+                Frame::Each { body, next, end } => {
+                    if next < end {
+                        self.push_frame(Frame::Each {
+                            body: body.clone(),
+                            next: next + 1,
+                            end,
+                        })?;
+                        self.stack.push(Value::Int(next));
+                        self.apply(body)?;
+                    }
+                }
+                Frame::While { cond, body, test } => {
+                    if !test {
+                        self.push_frame(Frame::While {
+                            cond: cond.clone(),
+                            body,
+                            test: true,
+                        })?;
+                        self.apply(cond)?;
+                    } else if self.pop_int()? != 0 {
+                        self.push_frame(Frame::While {
+                            cond,
+                            body: body.clone(),
+                            test: false,
+                        })?;
+                        self.apply(body)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn step(&mut self, node: &Node, io: Option<&mut Io>) -> Result<()> {
+        let term = match node {
+            Node::Term(term) => term,
+            Node::List(code) => {
+                self.stack
+                    .push(Value::Quotation(Rc::new(Quotation::List(code.clone()))));
+                return Ok(());
+            }
+            Node::Array(array) => {
+                self.stack.push(Value::Array(array.clone()));
+                return Ok(());
+            }
+        };
+
+        match term {
+            Term::Int(value) => self.stack.push(Value::Int(*value)),
+            Term::Add => self.binary(|a, b| Ok(a.wrapping_add(b)))?,
+            Term::Sub => self.binary(|a, b| Ok(a.wrapping_sub(b)))?,
+            Term::Mul => self.binary(|a, b| Ok(a.wrapping_mul(b)))?,
+            Term::Div => self.binary(|a, b| {
+                if b == 0 {
+                    Err(CompilerError::DivisionByZero.into())
+                } else {
+                    Ok(a.wrapping_div(b))
+                }
+            })?,
+            Term::Print | Term::Scan | Term::TryScan => {
+                let io = io.ok_or_else(|| anyhow!("input and output are not available here"))?;
+                self.io(term, io)?;
+            }
+
+            Term::Dup => {
+                let a = self.peek(0)?;
+                self.stack.push(a);
+            }
+            Term::Drop => {
+                self.pop()?;
+            }
+            Term::Take => {
+                let depth = self.pop_depth()?;
+                let a = self.stack.remove(self.stack.len() - 1 - depth);
+                self.stack.push(a);
+            }
+            Term::Swap => self.shuffle(2, &[1, 0])?,
+            Term::Over => self.shuffle(2, &[0, 1, 0])?,
+            Term::Rot => self.shuffle(3, &[1, 2, 0])?,
+            Term::MinusRot => self.shuffle(3, &[2, 0, 1])?,
+            Term::Nip => self.shuffle(2, &[1])?,
+            Term::Tuck => self.shuffle(2, &[1, 0, 1])?,
+            Term::Pick => {
+                let depth = self.pop_depth()?;
+                let a = self.peek(depth)?;
+                self.stack.push(a);
+            }
+            Term::TwoDup => self.shuffle(2, &[0, 1, 0, 1])?,
+            Term::TwoDrop => self.shuffle(2, &[])?,
+
+            Term::List { terms } => self.stack.push(Value::quotation(terms)),
+            Term::Apply | Term::ConstEval => {
+                let q = self.pop()?;
+                self.apply(q)?;
+            }
+            Term::Curry => {
+                self.allocate(CLOSURE_CELLS)?;
+                let q = self.pop()?;
+                let x = self.pop()?;
+                self.stack
+                    .push(Value::Quotation(Rc::new(Quotation::Curry(x, q))));
+            }
+            Term::Compose => {
+                self.allocate(CLOSURE_CELLS)?;
+                let q = self.pop()?;
+                let p = self.pop()?;
+                self.stack
+                    .push(Value::Quotation(Rc::new(Quotation::Compose(p, q))));
+            }
+
+            Term::Times => {
+                let body = self.pop()?;
+                let remaining = self.pop_int()?;
+                self.push_frame(Frame::Times { body, remaining })?;
+            }
+            Term::While => {
+                let body = self.pop()?;
+                let cond = self.pop()?;
+                self.push_frame(Frame::While {
+                    cond,
+                    body,
+                    test: false,
+                })?;
+            }
+            Term::Each => {
+                let body = self.pop()?;
+                let end = self.pop_int()?;
+                let next = self.pop_int()?;
+                self.push_frame(Frame::Each { body, next, end })?;
+            }
+
+            Term::If => {
+                let cond = self.pop_int()?;
+                let b = self.pop()?;
+                let a = self.pop()?;
+                self.stack.push(if cond != 0 { a } else { b });
+            }
+            Term::Ifte => {
+                let else_branch = self.pop()?;
+                let then_branch = self.pop()?;
+                let cond = self.pop_int()?;
+                self.apply(if cond != 0 { then_branch } else { else_branch })?;
+            }
+            Term::When => {
+                let then_branch = self.pop()?;
+                if self.pop_int()? != 0 {
+                    self.apply(then_branch)?;
+                }
+            }
+            Term::Bool => self.unary(|a| (a != 0) as i32)?,
+            Term::Not => self.unary(|a| if a == -1 { 0 } else { a.wrapping_neg() })?,
+            Term::And => self.binary(|a, b| Ok(a & b))?,
+            Term::Or => self.binary(|a, b| Ok(a | b))?,
+            Term::Equals => self.binary(|a, b| Ok((a == b) as i32))?,
+            Term::NotEquals => self.binary(|a, b| Ok((a != b) as i32))?,
+            Term::Less => self.binary(|a, b| Ok((a < b) as i32))?,
+            Term::LessEquals => self.binary(|a, b| Ok((a <= b) as i32))?,
+            Term::Greater => self.binary(|a, b| Ok((a > b) as i32))?,
+            Term::GreaterEquals => self.binary(|a, b| Ok((a >= b) as i32))?,
+
+            Term::Alloc => {
+                let len =
+                    usize::try_from(self.pop_int()?).map_err(|_| anyhow!("invalid array size"))?;
+                self.allocate(len as i64)?;
+                self.stack.push(Value::Array(Rc::new(RefCell::new(vec![
+                    Value::Int(0);
+                    len
+                ]))));
+            }
+            Term::Load => {
+                let index = self.pop_int()?;
+                let array = self.pop_array()?;
+                let value = array
+                    .borrow()
+                    .get(index as usize)
+                    .cloned()
+                    .ok_or_else(|| anyhow!("array index out of bounds"))?;
+                self.stack.push(value);
+            }
+            Term::Store => {
+                let index = self.pop_int()?;
+                let array = self.pop_array()?;
+                let value = self.pop()?;
+                let mut array = array.borrow_mut();
+                let cell = array
+                    .get_mut(index as usize)
+                    .ok_or_else(|| anyhow!("array index out of bounds"))?;
+                *cell = value;
+            }
+            Term::Len => {
+                let array = self.pop_array()?;
+                let len = array.borrow().len() as i32;
+                self.stack.push(Value::Int(len));
+            }
+            Term::Array { values } => self.stack.push(Value::Array(Rc::new(RefCell::new(
+                values.iter().map(|value| Value::Int(*value)).collect(),
+            )))),
+
+            Term::Bind { identifier } => {
+                let value = self.pop()?;
+                self.bindings.insert(identifier.clone(), value);
+            }
+            Term::Put { identifier } => {
+                let value = self.bindings.get(identifier).cloned().ok_or_else(|| {
+                    CompilerError::UnboundName {
+                        identifier: identifier.clone(),
+                    }
+                })?;
+                self.stack.push(value);
+            }
+
+            Term::Line { .. } => {}
+
+            Term::Include { .. } | Term::Macro { .. } => {
+                return Err(anyhow!(
+                    "includes and macros must be resolved before interpretation"
+                ))
+            }
+        }
+
+        Ok(())
+    }
+
+    fn io(&mut self, term: &Term, io: &mut Io) -> Result<()> {
+        match term {
+            Term::Print => {
+                let value = self.pop_int()?;
+                writeln!(io.output, "{}", value)?;
+            }
+            Term::Scan => {
+                io.output.flush()?;
+                let value = match scan(io.input)? {
+                    Some(value) => value,
+                    None if self.strict_scan => {
+                        return Err(anyhow!("failed to read an integer from stdin"))
+                    }
+                    None => 0,
+                };
+                self.stack.push(Value::Int(value));
+            }
+            Term::TryScan => {
+                io.output.flush()?;
+                let value = scan(io.input)?;
+                self.stack.push(Value::Int(value.unwrap_or(0)));
+                self.stack.push(Value::Int(value.is_some() as i32));
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn apply(&mut self, value: Value) -> Result<()> {
+        let quotation = match value {
+            Value::Quotation(quotation) => quotation,
+            value => return Err(type_mismatch("a quotation", &value)),
+        };
+
+        match quotation.as_ref() {
+            Quotation::List(code) => self.push_frame(Frame::Code {
+                code: code.clone(),
+                pc: 0,
+            }),
+            Quotation::Curry(x, q) => {
+                self.stack.push(x.clone());
+                self.apply(q.clone())
+            }
+            Quotation::Compose(p, q) => {
+                self.push_frame(Frame::Call(q.clone()))?;
+                self.apply(p.clone())
+            }
+        }
+    }
+
+    fn push_frame(&mut self, frame: Frame) -> Result<()> {
+        if self.frames.len() >= MAX_FRAMES {
+            return Err(anyhow!("call stack overflow"));
+        }
+        self.frames.push(frame);
+        Ok(())
+    }
+
+    /// Takes the cells and a header cell from the heap, as `$std_alloc` of a compiled program.
+    fn allocate(&mut self, cells: i64) -> Result<()> {
+        let heap_used = self.heap_used + cells + 1;
+        if heap_used > HEAP_SIZE / OP_SIZE_BYTES {
+            return Err(anyhow!("out of memory"));
+        }
+        self.heap_used = heap_used;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<Value> {
+        self.stack
+            .pop()
+            .ok_or_else(|| CompilerError::StackUnderflow.into())
+    }
+
+    fn pop_int(&mut self) -> Result<i32> {
+        match self.pop()? {
+            Value::Int(value) => Ok(value),
+            value => Err(type_mismatch("a number", &value)),
+        }
+    }
+
+    fn pop_array(&mut self) -> Result<Rc<RefCell<Vec<Value>>>> {
+        match self.pop()? {
+            Value::Array(array) => Ok(array),
+            value => Err(type_mismatch("an array", &value)),
+        }
+    }
+
+    /// Pops a depth for `take` and `pick`, 0 is the top of the remaining stack.
+    fn pop_depth(&mut self) -> Result<usize> {
+        let depth = self.pop_int()?;
+        usize::try_from(depth)
+            .ok()
+            .filter(|depth| *depth < self.stack.len())
+            .ok_or_else(|| CompilerError::StackUnderflow.into())
+    }
+
+    fn peek(&self, depth: usize) -> Result<Value> {
+        self.stack
+            .iter()
+            .rev()
+            .nth(depth)
+            .cloned()
+            .ok_or_else(|| CompilerError::StackUnderflow.into())
+    }
+
+    /// Replaces the top `n` values with the values at the given positions
+    /// counted from the deepest of them.
+    fn shuffle(&mut self, n: usize, positions: &[usize]) -> Result<()> {
+        if self.stack.len() < n {
+            return Err(CompilerError::StackUnderflow.into());
+        }
+        let top = self.stack.split_off(self.stack.len() - n);
+        self.stack
+            .extend(positions.iter().map(|position| top[*position].clone()));
+        Ok(())
+    }
+
+    fn unary(&mut self, f: impl Fn(i32) -> i32) -> Result<()> {
+        let a = self.pop_int()?;
+        self.stack.push(Value::Int(f(a)));
+        Ok(())
+    }
+
+    fn binary(&mut self, f: impl Fn(i32, i32) -> Result<i32>) -> Result<()> {
+        let b = self.pop_int()?;
+        let a = self.pop_int()?;
+        self.stack.push(Value::Int(f(a, b)?));
+        Ok(())
+    }
+}
+
+/// Reads a number like `scanf("%d")`: skips whitespace, and on malformed input skips
+/// the rest of the word, so the next read doesn't fail on it again.
+fn scan(input: &mut dyn BufRead) -> Result<Option<i32>> {
+    loop {
+        let buf = input.fill_buf()?;
+        if buf.is_empty() {
+            return Ok(None);
+        }
+        let spaces = buf.iter().take_while(|x| x.is_ascii_whitespace()).count();
+        let exhausted = spaces == buf.len();
+        input.consume(spaces);
+        if !exhausted {
+            break;
+        }
+    }
+
+    fn peek(input: &mut dyn BufRead) -> Result<Option<u8>> {
+        Ok(input.fill_buf()?.first().copied())
+    }
+
+    let mut number = String::new();
+
+    if let Some(sign @ (b'-' | b'+')) = peek(input)? {
+        number.push(sign as char);
+        input.consume(1);
+    }
+    while let Some(digit @ b'0'..=b'9') = peek(input)? {
+        number.push(digit as char);
+        input.consume(1);
+    }
+
+    if number.ends_with(|x: char| x.is_ascii_digit()) {
+        // like scanf, out of range numbers are clamped to 64 bits and then truncated
+        let value = number.parse::<i64>().unwrap_or(if number.starts_with('-') {
+            i64::MIN
+        } else {
+            i64::MAX
+        });
+        Ok(Some(value as i32))
+    } else {
+        while let Some(byte) = peek(input)? {
+            if byte.is_ascii_whitespace() {
+                break;
+            }
+            input.consume(1);
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse;
+
+    use super::*;
+
+    fn eval(source: &str) -> Result<Vec<i32>> {
+        let ast = parse(source).map_err(|e| anyhow!(e.to_string()))?;
+        let mut interpreter = Interpreter::new().with_fuel(100_000);
+        interpreter.run(&ast.terms)?;
+        interpreter
+            .stack()
+            .iter()
+            .map(|value| match value {
+                Value::Int(value) => Ok(*value),
+                _ => Err(anyhow!("not a number")),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(vec![-3, 6, 2], eval("-7 2 / 2 3 * 5 3 -").unwrap());
+    }
+
+    #[test]
+    fn stack_keywords() {
+        assert_eq!(vec![2, 3, 1], eval("1 2 3 rot").unwrap());
+        assert_eq!(vec![2, 3, 1], eval("1 2 3 2 take").unwrap());
+        assert_eq!(vec![1, 2, 3, 1], eval("1 2 3 2 pick").unwrap());
+        assert_eq!(vec![2, 1, 2], eval("1 2 tuck").unwrap());
+    }
+
+    #[test]
+    fn branches_and_loops() {
+        assert_eq!(vec![7], eval("1 [ 7 ] [ 8 ] ifte").unwrap());
+        assert_eq!(vec![8], eval("7 8 0 ?").unwrap());
+        assert_eq!(vec![10], eval("0 0 5 [ + ] each").unwrap());
+        assert_eq!(vec![8], eval("1 3 [ 2 * ] times").unwrap());
+        assert_eq!(vec![0], eval("5 [ dup 0 > ] [ 1 - ] while").unwrap());
+    }
+
+    #[test]
+    fn deep_recursion() {
+        let source = "[ dup 0 > [ 1 - f! ] when ] :f 100000 f!";
+        let ast = parse(source).unwrap();
+        let mut interpreter = Interpreter::new();
+        assert!(interpreter.run(&ast.terms).is_ok());
+    }
+
+    #[test]
+    fn closures() {
+        assert_eq!(
+            vec![-2],
+            eval("3 [ 1 + ] curry [ 2 * ] compose 10 [ - ] curry compose !").unwrap()
+        );
+    }
+
+    #[test]
+    fn arrays() {
+        assert_eq!(vec![3, 5], eval("3 alloc :a 5 a 1 @= a len a 1 @").unwrap());
+        assert!(eval("3 alloc 3 @").is_err());
+        assert!(eval("536870912 alloc").is_err());
+        // the heap is never freed, as in a compiled program
+        assert!(eval("100 [ 1000 alloc drop ] times").is_ok());
+        assert!(eval("200 [ 1000 alloc drop ] times").is_err());
+    }
+
+    fn interpret_with_input(source: &str, input: &str) -> Result<String> {
+        let ast = parse(source).map_err(|e| anyhow!(e.to_string()))?;
+        let mut output = Vec::new();
+        interpret(
+            &ast,
+            &TranslationOptions::default(),
+            &mut input.as_bytes(),
+            &mut output,
+        )?;
+        Ok(String::from_utf8(output)?)
+    }
+
+    #[test]
+    fn print_and_scan() {
+        assert_eq!("3\n", interpret_with_input("& & + .", " 1\n2 ").unwrap());
+        assert_eq!("0\n", interpret_with_input("& .", "").unwrap());
+        assert_eq!(
+            "-5\n1\n0\n0\n",
+            interpret_with_input("&? swap . . &? swap . .", "-5 abc").unwrap()
+        );
+        assert_eq!(
+            "1\n0\n2\n",
+            interpret_with_input("& . & . & .", "1 x 2").unwrap()
+        );
+    }
+
+    #[test]
+    fn strict_scan() {
+        let ast = parse("& .").unwrap();
+        let options = TranslationOptions {
+            strict_scan: true,
+            ..Default::default()
+        };
+        let result = interpret(&ast, &options, &mut "x".as_bytes(), &mut Vec::new());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn output_before_error() {
+        let ast = parse("1 . 0 alloc 0 @").unwrap();
+        let mut output = Vec::new();
+        let result = interpret(
+            &ast,
+            &TranslationOptions::default(),
+            &mut "".as_bytes(),
+            &mut output,
+        );
+        assert!(result.is_err());
+        assert_eq!(b"1\n", output.as_slice());
+    }
+
+    #[test]
+    fn errors() {
+        assert!(eval("+").is_err());
+        assert!(eval("1 0 /").is_err());
+        assert!(eval("1 !").is_err());
+        assert!(eval("x").is_err());
+        assert!(eval("[ f! ] :f f!").is_err());
+    }
+
+    #[test]
+    fn display_values() {
+        let ast = parse("1 [ 2 ] { 3 4 }").unwrap();
+        let mut interpreter = Interpreter::new();
+        assert!(interpreter.run(&ast.terms).is_ok());
+        let act: Vec<_> = interpreter.stack().iter().map(Value::to_string).collect();
+        assert_eq!(vec!["1", "[...]", "{ 3 4 }"], act);
+    }
+}
@@ -1,17 +1,31 @@
 mod builder;
 mod common;
+mod diagnostics;
+mod effects;
 mod err;
+mod formatter;
+mod interpreter;
+mod lints;
+mod loader;
+mod macros;
 mod parser;
 mod translator;
+mod tree;
 
 pub use {
     builder::{
         check_tmp_dir, link_to_executable_file, make_asm_file, make_object_file, make_tmp_path,
     },
-    common::{Ast, Term},
-    err::CompilerError,
-    parser::parse,
-    translator::{make_std_lib, translate},
+    common::{Ast, Node, Span, Term, KEYWORD_TERMS},
+    diagnostics::{Diagnostic, Location, Note, Severity},
+    effects::{stack_depths, stack_underflow, Depth, StackEffect},
+    err::{explain, CompilerError},
+    formatter::format,
+    interpreter::{interpret, Interpreter, Io, Value},
+    lints::{lint, Level, Lint, LintLevels, LINTS},
+    loader::{load, Loader, PRELUDE},
+    parser::{parse, parse_nodes, textmate_grammar, tokenize, Token, TokenKind},
+    translator::{make_std_lib, translate, TranslationOptions},
 };
 
 #[cfg(test)]
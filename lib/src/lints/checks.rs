@@ -0,0 +1,309 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    common::{Node, Span, Term},
+    loader::PRELUDE,
+    parser::parse,
+};
+
+use super::Warning;
+
+pub fn unused_bindings(nodes: &[Node], warnings: &mut Vec<Warning>) {
+    let mut binds = Vec::new();
+    let mut puts = HashSet::new();
+    walk(nodes, &mut |node| match &node.term {
+        Term::Bind { identifier } => binds.push((identifier.as_str(), node.span)),
+        Term::Put { identifier } => {
+            puts.insert(identifier.as_str());
+        }
+        _ => {}
+    });
+
+    let mut reported = HashSet::new();
+    for (identifier, span) in binds {
+        if identifier.starts_with('_') || puts.contains(identifier) || !reported.insert(identifier)
+        {
+            continue;
+        }
+
+        warnings.push(Warning {
+            lint: "unused_binding",
+            span,
+            message: format!("`{}` is bound but never used", identifier),
+            note: None,
+        });
+    }
+}
+
+/// Terms after a loop which can't end: `[ 1 ] [ ... ] while`, a word which always calls
+/// itself, or a list which applies a copy of itself.
+pub fn unreachable_code(nodes: &[Node], warnings: &mut Vec<Warning>) {
+    let definitions = definitions(nodes);
+
+    each_sequence(nodes, &mut |sequence| {
+        let Some(end) = (0..sequence.len()).find(|&i| loops_forever(&sequence[..=i], &definitions))
+        else {
+            return;
+        };
+        let Some(rest) = sequence.get(end + 1..).filter(|x| !x.is_empty()) else {
+            return;
+        };
+
+        warnings.push(Warning {
+            lint: "unreachable_code",
+            span: join(rest[0].span, rest[rest.len() - 1].span),
+            message: "unreachable code after a loop which never ends".to_string(),
+            note: None,
+        });
+    });
+}
+
+/// Whether the last term of the sequence starts a loop which never ends.
+fn loops_forever(sequence: &[Node], definitions: &HashMap<&str, Option<&[Node]>>) -> bool {
+    match sequence {
+        [.., condition, _, last] if last.term == Term::While => {
+            matches!(condition.children.as_slice(), [x] if matches!(x.term, Term::Int(n) if n != 0))
+                && matches!(condition.term, Term::List { .. })
+        }
+        [.., list, dup, last] if last.term == Term::Apply && dup.term == Term::Dup => {
+            matches!(list.term, Term::List { .. })
+                && list
+                    .children
+                    .windows(2)
+                    .any(|x| x[0].term == Term::Dup && x[1].term == Term::Apply)
+        }
+        [.., put, last] if last.term == Term::Apply => match &put.term {
+            Term::Put { identifier } => definitions
+                .get(identifier.as_str())
+                .copied()
+                .flatten()
+                .is_some_and(|body| calls(body, identifier)),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+fn calls(body: &[Node], identifier: &str) -> bool {
+    body.windows(2).any(|x| {
+        matches!(&x[0].term, Term::Put { identifier: y } if y == identifier)
+            && x[1].term == Term::Apply
+    })
+}
+
+/// The bodies of the names which are bound exactly once, right after a list literal.
+fn definitions(nodes: &[Node]) -> HashMap<&str, Option<&[Node]>> {
+    let mut definitions = HashMap::new();
+
+    each_sequence(nodes, &mut |sequence| {
+        for (i, node) in sequence.iter().enumerate() {
+            let Term::Bind { identifier } = &node.term else {
+                continue;
+            };
+            let body = i
+                .checked_sub(1)
+                .map(|x| &sequence[x])
+                .filter(|x| matches!(x.term, Term::List { .. }))
+                .map(|x| x.children.as_slice());
+
+            definitions
+                .entry(identifier.as_str())
+                .and_modify(|x| *x = None)
+                .or_insert(body);
+        }
+    });
+
+    definitions
+}
+
+/// `[ ... ] drop`, a list left on the stack at the end of the program, or a bound list
+/// which is never applied: it's used only in its own body, or only to be dropped.
+pub fn unused_lists(nodes: &[Node], warnings: &mut Vec<Warning>) {
+    let mut warn = |span| {
+        warnings.push(Warning {
+            lint: "unused_list",
+            span,
+            message: "the list is never used".to_string(),
+            note: None,
+        })
+    };
+
+    each_sequence(nodes, &mut |sequence| {
+        for pair in sequence.windows(2) {
+            if matches!(pair[0].term, Term::List { .. }) && pair[1].term == Term::Drop {
+                warn(pair[0].span);
+            }
+        }
+    });
+    if let Some(last) = nodes.last().filter(|x| matches!(x.term, Term::List { .. })) {
+        warn(last.span);
+    }
+
+    let mut lists = Vec::new();
+    let mut uses: HashMap<&str, Vec<Span>> = HashMap::new();
+    each_sequence(nodes, &mut |sequence| {
+        for (i, node) in sequence.iter().enumerate() {
+            match &node.term {
+                Term::Bind { identifier } if i > 0 => {
+                    if let Term::List { .. } = sequence[i - 1].term {
+                        lists.push((identifier.as_str(), sequence[i - 1].span));
+                    }
+                }
+                Term::Put { identifier } => {
+                    let dropped = sequence.get(i + 1).is_some_and(|x| x.term == Term::Drop);
+                    let uses = uses.entry(identifier.as_str()).or_default();
+                    if !dropped {
+                        uses.push(node.span);
+                    }
+                }
+                _ => {}
+            }
+        }
+    });
+
+    let definitions = definitions(nodes);
+    for (identifier, list) in lists {
+        // a name which is never used is reported as an unused binding
+        let Some(uses) = uses.get(identifier) else {
+            continue;
+        };
+        let applied = uses
+            .iter()
+            .any(|x| x.start < list.start || x.end > list.end);
+        if !applied && definitions[identifier].is_some() {
+            warn(list);
+        }
+    }
+}
+
+pub fn noop_takes(nodes: &[Node], warnings: &mut Vec<Warning>) {
+    each_sequence(nodes, &mut |sequence| {
+        for pair in sequence.windows(2) {
+            if pair[0].term == Term::Int(0) && pair[1].term == Term::Take {
+                warnings.push(Warning {
+                    lint: "noop_take",
+                    span: join(pair[0].span, pair[1].span),
+                    message: "`0 take` does nothing".to_string(),
+                    note: None,
+                });
+            }
+        }
+    });
+}
+
+pub fn constant_conditions(nodes: &[Node], warnings: &mut Vec<Warning>) {
+    each_sequence(nodes, &mut |sequence| {
+        for pair in sequence.windows(2) {
+            if let (Term::Int(n), Term::If) = (&pair[0].term, &pair[1].term) {
+                warnings.push(Warning {
+                    lint: "constant_condition",
+                    span: pair[0].span,
+                    message: format!(
+                        "the condition of `?` is always {}",
+                        if *n != 0 { "true" } else { "false" }
+                    ),
+                    note: None,
+                });
+            }
+        }
+    });
+}
+
+/// A binding of a name which is already bound before it, in the same list or around it,
+/// and a binding which hides a prelude word. Lists side by side may bind the same names.
+pub fn shadowed_names(nodes: &[Node], prelude: bool, warnings: &mut Vec<Warning>) {
+    let words: HashSet<String> = if prelude {
+        parse(PRELUDE)
+            .expect("prelude must be a valid program")
+            .terms
+            .into_iter()
+            .filter_map(|x| match x {
+                Term::Bind { identifier } => Some(identifier),
+                _ => None,
+            })
+            .collect()
+    } else {
+        HashSet::new()
+    };
+
+    let mut shadowing = Shadowing {
+        words,
+        bound: Vec::new(),
+        reported: HashSet::new(),
+        warnings,
+    };
+    shadowing.scope(nodes);
+}
+
+struct Shadowing<'a, 'w> {
+    words: HashSet<String>,
+    /// The names bound in the scope, in order, with their bindings
+    bound: Vec<(&'a str, Span)>,
+    /// The prelude words reported as hidden
+    reported: HashSet<&'a str>,
+    warnings: &'w mut Vec<Warning>,
+}
+
+impl<'a> Shadowing<'a, '_> {
+    fn scope(&mut self, nodes: &'a [Node]) {
+        let outer = self.bound.len();
+
+        for node in nodes {
+            match &node.term {
+                Term::Bind { identifier } => {
+                    let first = self.bound.iter().find(|x| x.0 == identifier).map(|x| x.1);
+                    let warning = if let Some(first) = first {
+                        Some((
+                            format!("`{}` is already bound", identifier),
+                            Some((first, format!("`{}` is first bound here", identifier))),
+                        ))
+                    } else if self.words.contains(identifier) && self.reported.insert(identifier) {
+                        Some((
+                            format!("`{}` hides the prelude word with the same name", identifier),
+                            None,
+                        ))
+                    } else {
+                        None
+                    };
+                    if let Some((message, note)) = warning {
+                        self.warnings.push(Warning {
+                            lint: "shadowed_name",
+                            span: node.span,
+                            message,
+                            note,
+                        });
+                    }
+                    self.bound.push((identifier, node.span));
+                }
+                Term::List { .. } | Term::Macro { .. } => self.scope(&node.children),
+                _ => {}
+            }
+        }
+
+        self.bound.truncate(outer);
+    }
+}
+
+fn walk<'a>(nodes: &'a [Node], f: &mut impl FnMut(&'a Node)) {
+    for node in nodes {
+        f(node);
+        walk(&node.children, f);
+    }
+}
+
+/// Calls `f` with the program and the body of every list and macro in it.
+fn each_sequence<'a>(nodes: &'a [Node], f: &mut impl FnMut(&'a [Node])) {
+    f(nodes);
+    for node in nodes {
+        if !node.children.is_empty() {
+            each_sequence(&node.children, f);
+        }
+    }
+}
+
+fn join(first: Span, last: Span) -> Span {
+    Span {
+        start: first.start,
+        end: last.end,
+    }
+}
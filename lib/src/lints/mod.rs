@@ -0,0 +1,380 @@
+mod checks;
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+    common::Span,
+    diagnostics::{Diagnostic, Note, Severity},
+    err::CompilerError,
+    parser::parse_nodes,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+pub struct Lint {
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+pub const LINTS: &[Lint] = &[
+    Lint {
+        name: "unused_binding",
+        description: "a name is bound but never used",
+    },
+    Lint {
+        name: "unreachable_code",
+        description: "terms after a loop which never ends",
+    },
+    Lint {
+        name: "unused_list",
+        description: "a list literal which is dropped, left on the stack or never applied",
+    },
+    Lint {
+        name: "noop_take",
+        description: "`0 take`, which does nothing",
+    },
+    Lint {
+        name: "constant_condition",
+        description: "a number literal as the condition of `?`",
+    },
+    Lint {
+        name: "shadowed_name",
+        description: "a binding of a name which is already bound, or of a prelude word",
+    },
+];
+
+/// The group of all lints, e.g. `-D warnings`.
+const WARNINGS: &str = "warnings";
+
+/// The levels set on the command line. A level of a single lint takes precedence
+/// over the level of the `warnings` group; every lint warns by default.
+#[derive(Clone, Debug, Default)]
+pub struct LintLevels {
+    warnings: Option<Level>,
+    lints: HashMap<&'static str, Level>,
+}
+
+impl LintLevels {
+    pub fn set(&mut self, name: &str, level: Level) -> Result<()> {
+        if name == WARNINGS {
+            self.warnings = Some(level);
+            return Ok(());
+        }
+
+        let lint = find(name).ok_or_else(|| anyhow!("unknown lint `{}`", name))?;
+        self.lints.insert(lint.name, level);
+        Ok(())
+    }
+
+    pub fn level(&self, name: &str) -> Level {
+        self.lints
+            .get(name)
+            .or(self.warnings.as_ref())
+            .copied()
+            .unwrap_or(Level::Warn)
+    }
+}
+
+fn find(name: &str) -> Option<&'static Lint> {
+    LINTS.iter().find(|x| x.name == name)
+}
+
+/// A lint found in the source, before its level is applied.
+struct Warning {
+    lint: &'static str,
+    span: Span,
+    message: String,
+    /// A related place, e.g. the binding of a name which is bound again
+    note: Option<(Span, String)>,
+}
+
+/// Checks the source of a program file for suspicious code.
+///
+/// Denied lints are reported as errors. A `#!allow(lint, ...)` comment allows the lints
+/// on its line, or in the whole file if it is on a line of its own.
+pub fn lint<'s>(
+    source: &'s str,
+    levels: &LintLevels,
+    prelude: bool,
+) -> Result<Vec<Diagnostic>, CompilerError<'s>> {
+    let nodes = parse_nodes(source)?;
+    let allowed = Allowed::read(source);
+
+    let mut warnings = Vec::new();
+    checks::unused_bindings(&nodes, &mut warnings);
+    checks::unreachable_code(&nodes, &mut warnings);
+    checks::unused_lists(&nodes, &mut warnings);
+    checks::noop_takes(&nodes, &mut warnings);
+    checks::constant_conditions(&nodes, &mut warnings);
+    checks::shadowed_names(&nodes, prelude, &mut warnings);
+    warnings.sort_by_key(|x| x.span.start);
+
+    Ok(warnings
+        .into_iter()
+        .filter(|x| !allowed.allows(x.lint, line(source, x.span.start)))
+        .filter_map(|x| {
+            let severity = match levels.level(x.lint) {
+                Level::Allow => return None,
+                Level::Warn => Severity::Warning,
+                Level::Deny => Severity::Error,
+            };
+
+            let mut diagnostic = Diagnostic::error(x.message).at(source, x.span);
+            if let Some((span, note)) = x.note {
+                diagnostic = diagnostic.with_note(Note::new(note).at(source, span));
+            }
+            diagnostic.severity = severity;
+            diagnostic.code = Some(x.lint.to_string());
+            Some(diagnostic)
+        })
+        .collect())
+}
+
+fn line(source: &str, offset: usize) -> usize {
+    source[..offset].matches('\n').count()
+}
+
+/// The lints allowed by `#!allow(...)` comments.
+#[derive(Default)]
+struct Allowed {
+    file: HashSet<String>,
+    lines: HashMap<usize, HashSet<String>>,
+}
+
+impl Allowed {
+    fn read(source: &str) -> Allowed {
+        let mut allowed = Allowed::default();
+
+        for (i, line) in source.lines().enumerate() {
+            let Some((code, comment)) = split_comment(line) else {
+                continue;
+            };
+            let Some(names) = comment
+                .trim_end()
+                .strip_prefix("#!allow(")
+                .and_then(|x| x.strip_suffix(')'))
+            else {
+                continue;
+            };
+
+            let names = names.split(',').map(|x| x.trim().to_string());
+            if code.trim().is_empty() {
+                allowed.file.extend(names);
+            } else {
+                allowed.lines.entry(i).or_default().extend(names);
+            }
+        }
+
+        allowed
+    }
+
+    fn allows(&self, lint: &str, line: usize) -> bool {
+        let allows = |names: &HashSet<String>| names.contains(lint) || names.contains(WARNINGS);
+
+        allows(&self.file) || self.lines.get(&line).is_some_and(allows)
+    }
+}
+
+/// Splits the line at the comment, skipping `#` in the path of an `include`.
+fn split_comment(line: &str) -> Option<(&str, &str)> {
+    let mut in_string = false;
+
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return Some((&line[..i], &line[i..])),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lints(source: &str) -> Vec<(String, String)> {
+        lint(source, &LintLevels::default(), true)
+            .unwrap()
+            .into_iter()
+            .map(|x| {
+                let span = x.span.unwrap();
+                (
+                    x.code.unwrap(),
+                    source[span.byte_start..span.byte_end].to_string(),
+                )
+            })
+            .collect()
+    }
+
+    fn lint_names(source: &str) -> Vec<String> {
+        lints(source).into_iter().map(|x| x.0).collect()
+    }
+
+    #[test]
+    fn unused_binding() {
+        assert_eq!(
+            vec![
+                ("unused_binding".to_string(), ":y".to_string()),
+                ("shadowed_name".to_string(), ":x".to_string()),
+            ],
+            lints("1 :x 2 :y x . [ 3 :x ] !")
+        );
+        assert!(lints("1 :_unused [ x ] :f 2 :x f!").is_empty());
+    }
+
+    #[test]
+    fn unreachable_code() {
+        assert_eq!(
+            vec![("unreachable_code".to_string(), "2 .".to_string())],
+            lints("0 [ 1 ] [ 1 + ] while 2 .")
+        );
+        assert_eq!(
+            vec![("unreachable_code".to_string(), ". 1".to_string())],
+            lints("[ dup . 1 + f! ] :f 0 f! . 1")
+        );
+        assert_eq!(
+            vec![("unreachable_code".to_string(), "3 .".to_string())],
+            lints("[ 42 . dup ! ] dup ! 3 .")
+        );
+        assert!(lints("[ dup 10 < [ 1 + f! ] when ] :f 0 f! .").is_empty());
+        assert!(lints("0 [ dup 10 < ] [ 1 + ] while .").is_empty());
+    }
+
+    #[test]
+    fn unused_list() {
+        assert_eq!(
+            vec![
+                ("unused_list".to_string(), "[ 1 ]".to_string()),
+                ("unused_list".to_string(), "[ 2 ]".to_string()),
+            ],
+            lints("[ 1 ] drop 1 . [ 2 ]")
+        );
+        assert!(lints("[ 1 ] ! . [ 2 ] :f f! .").is_empty());
+
+        // bound, but never applied
+        assert_eq!(
+            vec![("unused_list".to_string(), "[ dup . 1 - f! ]".to_string())],
+            lints("[ dup . 1 - f! ] :f")
+        );
+        assert_eq!(
+            vec![("unused_list".to_string(), "[ 1 ]".to_string())],
+            lints("[ 1 ] :f f drop")
+        );
+        assert!(lints("[ dup 0 > [ 1 - f! ] when ] :f 3 f! .").is_empty());
+        assert!(lints("[ 1 ] :f [ 2 ] f compose ! . .").is_empty());
+    }
+
+    #[test]
+    fn noop_take() {
+        assert_eq!(
+            vec![("noop_take".to_string(), "0 take".to_string())],
+            lints("1 2 0 take 1 take . .")
+        );
+    }
+
+    #[test]
+    fn constant_condition() {
+        assert_eq!(
+            vec![("constant_condition".to_string(), "1".to_string())],
+            lints("2 3 1 ? . 2 3 4 5 < ? .")
+        );
+    }
+
+    #[test]
+    fn shadowed_name() {
+        assert_eq!(
+            vec![("shadowed_name".to_string(), ":abs".to_string())],
+            lints("[ dup * ] :abs -2 abs! .")
+        );
+        assert!(
+            lint("[ dup * ] :abs -2 abs! .", &LintLevels::default(), false)
+                .unwrap()
+                .is_empty()
+        );
+
+        assert_eq!(
+            vec![("shadowed_name".to_string(), ":x".to_string())],
+            lints("1 :x 2 :x x .")
+        );
+        let notes = &lint("1 :x\n2 :x x .", &LintLevels::default(), true).unwrap()[0].notes;
+        assert_eq!(1, notes.len());
+        assert_eq!("`x` is first bound here", notes[0].message);
+        assert_eq!(
+            Some((1, 3)),
+            notes[0]
+                .span
+                .as_ref()
+                .map(|x| (x.line_start, x.column_start))
+        );
+        assert_eq!(
+            vec![("shadowed_name".to_string(), ":n".to_string())],
+            lints("1 :n [ :n n . ] :p 2 p! n .")
+        );
+        // lists side by side have their own names
+        assert!(lints("[ :i i . ] :p [ :i i 1 + . ] :q 1 p! 2 q!").is_empty());
+        assert!(lint("1 :x 2 :x x .", &LintLevels::default(), false)
+            .unwrap()
+            .iter()
+            .any(|x| x.code.as_deref() == Some("shadowed_name")));
+    }
+
+    #[test]
+    fn levels() {
+        let source = "1 :x 2 3 0 take";
+        let severities = |levels: &LintLevels| -> Vec<_> {
+            lint(source, levels, true)
+                .unwrap()
+                .into_iter()
+                .map(|x| (x.code.unwrap(), x.severity))
+                .collect()
+        };
+
+        let mut levels = LintLevels::default();
+        levels.set("warnings", Level::Deny).unwrap();
+        levels.set("noop_take", Level::Allow).unwrap();
+        assert_eq!(
+            vec![("unused_binding".to_string(), Severity::Error)],
+            severities(&levels)
+        );
+
+        levels.set("warnings", Level::Allow).unwrap();
+        assert!(severities(&levels).is_empty());
+
+        assert!(levels.set("no_such_lint", Level::Warn).is_err());
+    }
+
+    #[test]
+    fn allow_comments() {
+        assert_eq!(
+            vec!["noop_take".to_string()],
+            lint_names("1 :x #!allow(unused_binding)\n2 3 0 take")
+        );
+        assert!(lint_names("#!allow(unused_binding, noop_take)\n1 :x\n2 3 0 take").is_empty());
+        assert!(lint_names("#!allow(warnings)\n1 :x\n2 3 0 take").is_empty());
+        assert_eq!(
+            vec!["unused_binding".to_string()],
+            lint_names("1 :x\n2 3 0 take #!allow(noop_take)")
+        );
+    }
+
+    #[test]
+    fn examples() {
+        let examples = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../examples");
+        for entry in std::fs::read_dir(examples).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|x| x == "plc") {
+                let source = std::fs::read_to_string(&path).unwrap();
+                assert_eq!(Vec::<(String, String)>::new(), lints(&source), "{:?}", path);
+            }
+        }
+    }
+}
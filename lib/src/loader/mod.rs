@@ -0,0 +1,831 @@
+mod prelude;
+
+use anyhow::{anyhow, Result};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    common::{Ast, Node, Span, Term},
+    diagnostics::{Diagnostic, Location, Note},
+    err::CompilerError,
+    macros::{const_eval, expand_macros, ConstEvalError, CONST_EVAL_FUEL},
+    parser::parse_nodes,
+};
+use prelude::{link_prelude, strip_prelude, PRELUDE_MODULE};
+
+pub use prelude::PRELUDE;
+
+/// The file name in the diagnostics about a program which is not backed by a file.
+const STDIN_FILE: &str = "<stdin>";
+
+/// Reads a program together with everything it `include`s.
+///
+/// Included files are modules: a binding `:name` defined in `math.plc` becomes `math::name`,
+/// both at the definition and at the module's own uses. Each file is included at most once;
+/// include cycles are reported as errors. The main program is linked with the prelude
+/// unless it is disabled.
+pub struct Loader {
+    include_dirs: Vec<PathBuf>,
+    prelude: bool,
+    including: Vec<PathBuf>,
+    included: HashSet<PathBuf>,
+    modules: HashMap<String, PathBuf>,
+    /// The text of every loaded file, to point the diagnostics at
+    sources: Vec<(PathBuf, String)>,
+    line_info: bool,
+    const_eval_fuel: u64,
+}
+
+pub fn load(path: &Path, include_dirs: &[PathBuf], prelude: bool) -> Result<Ast> {
+    Loader::new(include_dirs.to_vec(), prelude).load_file(path)
+}
+
+impl Loader {
+    pub fn new(include_dirs: Vec<PathBuf>, prelude: bool) -> Loader {
+        let mut modules = HashMap::new();
+        if prelude {
+            modules.insert(PRELUDE_MODULE.to_string(), PathBuf::from("<prelude>"));
+        }
+
+        Loader {
+            include_dirs,
+            prelude,
+            including: Vec::new(),
+            included: HashSet::new(),
+            modules,
+            sources: Vec::new(),
+            line_info: false,
+            const_eval_fuel: CONST_EVAL_FUEL,
+        }
+    }
+
+    /// Precedes every term with a [`Term::Line`] of its place in the source, for debug info.
+    pub fn with_line_info(mut self, line_info: bool) -> Loader {
+        self.line_info = line_info;
+        self
+    }
+
+    /// Limits the instructions each `const-eval` may run.
+    pub fn with_const_eval_fuel(mut self, fuel: u64) -> Loader {
+        self.const_eval_fuel = fuel;
+        self
+    }
+
+    /// Loads the main program file. Its bindings are not namespaced.
+    pub fn load_file(&mut self, path: &Path) -> Result<Ast> {
+        let path = canonicalize(path)?;
+        let terms = self.load(&path)?;
+
+        self.link(terms)
+    }
+
+    /// Loads a main program which is not backed by a file, e.g. read from stdin.
+    /// Includes are resolved relative to `dir`.
+    pub fn load_source(&mut self, source: &str, dir: &Path) -> Result<Ast> {
+        let terms = self
+            .parse(source, STDIN_FILE)
+            .map_err(|e| e.to_diagnostic().in_file(STDIN_FILE))?;
+        self.sources
+            .push((PathBuf::from(STDIN_FILE), source.to_string()));
+        let terms = self.resolve_includes(terms, dir)?;
+
+        self.link(terms)
+    }
+
+    /// Expands macros, links the prelude and evaluates `const-eval`s.
+    fn link(&self, terms: Vec<Term>) -> Result<Ast> {
+        let terms = expand_macros(terms).map_err(|e| match e.downcast_ref() {
+            Some(CompilerError::DuplicateMacro { name }) => {
+                self.locate_macro(name, Diagnostic::from_error(&e)).into()
+            }
+            _ => e,
+        })?;
+
+        let terms = if self.prelude {
+            link_prelude(terms)
+        } else {
+            terms
+        };
+        let terms = const_eval(terms, self.const_eval_fuel).map_err(|e| {
+            match e.downcast_ref::<ConstEvalError>() {
+                Some(ConstEvalError {
+                    line: Some((file, line)),
+                }) => self
+                    .locate_const_eval(file, *line, Diagnostic::from_error(&e))
+                    .into(),
+                _ => e,
+            }
+        })?;
+        let terms = if self.prelude {
+            strip_prelude(terms)
+        } else {
+            terms
+        };
+        let terms = if self.line_info {
+            terms
+        } else {
+            strip_lines(terms)
+        };
+        if let Some(identifier) = unbound_name(&terms) {
+            let diagnostic = CompilerError::UnboundName {
+                identifier: identifier.to_string(),
+            }
+            .to_diagnostic();
+            return Err(self.locate_put(identifier, diagnostic).into());
+        }
+
+        Ok(Ast::from_terms(terms))
+    }
+
+    /// Points the diagnostic at the first use of the name in the loaded files.
+    fn locate_put(&self, identifier: &str, diagnostic: Diagnostic) -> Diagnostic {
+        let puts = self.find_nodes(
+            identifier,
+            |term, name| matches!(term, Term::Put { identifier } if identifier == name),
+        );
+
+        match puts.first() {
+            Some((path, source, span)) => diagnostic
+                .in_file(path.display().to_string())
+                .at(source, *span),
+            None => diagnostic,
+        }
+    }
+
+    /// Points the diagnostic at the `const-eval` on the line of the file.
+    fn locate_const_eval(&self, file: &str, line: usize, diagnostic: Diagnostic) -> Diagnostic {
+        let Some((path, source)) = self
+            .sources
+            .iter()
+            .find(|(path, _)| path.display().to_string() == file)
+        else {
+            return diagnostic;
+        };
+        let Ok(nodes) = parse_nodes(source) else {
+            return diagnostic;
+        };
+
+        let mut spans = Vec::new();
+        collect_spans(&nodes, &|term| *term == Term::ConstEval, &mut spans);
+        match spans
+            .into_iter()
+            .find(|x| Location::new(source, *x).line_start == line)
+        {
+            Some(span) => diagnostic
+                .in_file(path.display().to_string())
+                .at(source, span),
+            None => diagnostic,
+        }
+    }
+
+    /// Points the diagnostic about a macro defined twice at the second definition,
+    /// with a note about the first one.
+    fn locate_macro(&self, identifier: &str, diagnostic: Diagnostic) -> Diagnostic {
+        let definitions = self.find_nodes(
+            identifier,
+            |term, name| matches!(term, Term::Macro { name: x, .. } if x == name),
+        );
+
+        match definitions.as_slice() {
+            [(first_path, first_source, first), (path, source, span), ..] => diagnostic
+                .in_file(path.display().to_string())
+                .at(source, *span)
+                .with_note(
+                    Note::new(format!("`{}` is first defined here", identifier))
+                        .in_file(first_path.display().to_string())
+                        .at(first_source, *first),
+                ),
+            _ => diagnostic,
+        }
+    }
+
+    /// The nodes of the name in the loaded files, in the order the files are loaded.
+    /// A module's own names are qualified by the module, so they are looked up unqualified.
+    fn find_nodes(
+        &self,
+        identifier: &str,
+        is_node: impl Fn(&Term, &str) -> bool,
+    ) -> Vec<(&Path, &str, Span)> {
+        let mut found = Vec::new();
+
+        for (path, source) in &self.sources {
+            let name = match self.modules.iter().find(|(_, x)| *x == path) {
+                Some((module, _)) => identifier
+                    .strip_prefix(module.as_str())
+                    .and_then(|x| x.strip_prefix("::"))
+                    .unwrap_or(identifier),
+                None => identifier,
+            };
+
+            let Ok(nodes) = parse_nodes(source) else {
+                continue;
+            };
+            let mut spans = Vec::new();
+            collect_spans(&nodes, &|term| is_node(term, name), &mut spans);
+            found.extend(
+                spans
+                    .into_iter()
+                    .map(|span| (path.as_path(), source.as_str(), span)),
+            );
+        }
+
+        found
+    }
+
+    fn load(&mut self, path: &Path) -> Result<Vec<Term>> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("can't read {}: {}", path.display(), e))?;
+        let file = path.display().to_string();
+        let terms = self
+            .parse(&source, &file)
+            .map_err(|e| e.to_diagnostic().in_file(file))?;
+
+        self.sources.push((path.to_path_buf(), source.clone()));
+        self.including.push(path.to_path_buf());
+        let dir = path.parent().unwrap_or(Path::new("/"));
+        let terms = self.resolve_includes(terms, dir);
+        self.including.pop();
+        self.included.insert(path.to_path_buf());
+
+        terms
+    }
+
+    fn load_module(&mut self, path: &Path) -> Result<Vec<Term>> {
+        if self.including.iter().any(|x| x == path) {
+            let cycle: Vec<_> = self
+                .including
+                .iter()
+                .map(PathBuf::as_path)
+                .skip_while(|x| *x != path)
+                .chain([path])
+                .collect();
+            let diagnostic = cycle.windows(2).fold(
+                CompilerError::IncludeCycle {
+                    file: path.display().to_string(),
+                }
+                .to_diagnostic(),
+                |diagnostic, x| diagnostic.with_note(self.include_note(x[0], x[1])),
+            );
+            return Err(diagnostic.into());
+        }
+
+        if self.included.contains(path) {
+            return Ok(Vec::new());
+        }
+
+        let module = module_name(path)?;
+        if let Some(other) = self.modules.insert(module.clone(), path.to_path_buf()) {
+            let diagnostic = CompilerError::DuplicateModule {
+                module: module.clone(),
+                file: path.display().to_string(),
+            }
+            .to_diagnostic()
+            .with_note(
+                Note::new(format!("`{}` is first defined here", module))
+                    .in_file(other.display().to_string()),
+            );
+            return Err(diagnostic.into());
+        }
+
+        let terms = self.load(path)?;
+
+        Ok(namespace(terms, &module))
+    }
+
+    /// A note about the `include` of `included` in `file`, pointing at it if it can be found.
+    fn include_note(&self, file: &Path, included: &Path) -> Note {
+        let note = Note::new(format!("includes {}", included.display()))
+            .in_file(file.display().to_string());
+        let Some((_, source)) = self.sources.iter().find(|(path, _)| path == file) else {
+            return note;
+        };
+        let Ok(nodes) = parse_nodes(source) else {
+            return note;
+        };
+
+        let dir = file.parent().unwrap_or(Path::new("/"));
+        let mut spans = Vec::new();
+        collect_spans(
+            &nodes,
+            &|term| matches!(term, Term::Include { path } if self.find(path, dir).is_ok_and(|x| x == included)),
+            &mut spans,
+        );
+        match spans.first() {
+            Some(span) => note.at(source, *span),
+            None => note,
+        }
+    }
+
+    fn resolve_includes(&mut self, terms: Vec<Term>, dir: &Path) -> Result<Vec<Term>> {
+        let mut resolved = Vec::with_capacity(terms.len());
+
+        for term in terms {
+            match term {
+                Term::Include { path } => {
+                    let path = self.find(&path, dir)?;
+                    resolved.extend(self.load_module(&path)?);
+                }
+                Term::List { terms } | Term::Macro { terms, .. } if contains_include(&terms) => {
+                    return Err(CompilerError::NotAtTopLevel {
+                        what: "`include`".to_string(),
+                    }
+                    .into());
+                }
+                term => resolved.push(term),
+            }
+        }
+
+        Ok(resolved)
+    }
+
+    /// The terms always have line info, to point the errors of `const-eval` at; it is stripped
+    /// after linking unless asked for.
+    fn parse<'s>(&self, source: &'s str, file: &str) -> Result<Vec<Term>, CompilerError<'s>> {
+        Ok(with_lines(source, file, parse_nodes(source)?))
+    }
+
+    /// Looks for the file relative to the including file first, then in the `-I` directories.
+    fn find(&self, path: &str, dir: &Path) -> Result<PathBuf> {
+        [dir]
+            .into_iter()
+            .chain(self.include_dirs.iter().map(PathBuf::as_path))
+            .map(|x| x.join(path))
+            .find(|x| x.is_file())
+            .ok_or_else(|| {
+                CompilerError::IncludedFileNotFound {
+                    path: path.to_string(),
+                }
+                .into()
+            })
+            .and_then(|x| canonicalize(&x))
+    }
+}
+
+fn canonicalize(path: &Path) -> Result<PathBuf> {
+    std::fs::canonicalize(path).map_err(|e| anyhow!("can't read {}: {}", path.display(), e))
+}
+
+fn module_name(path: &Path) -> Result<String> {
+    let name = path
+        .file_stem()
+        .and_then(|x| x.to_str())
+        .ok_or(anyhow!("path contains non-utf8 characters"))?;
+
+    let is_identifier = name.starts_with(|x: char| x.is_alphabetic() || x == '_')
+        && name.chars().all(|x| x.is_alphanumeric() || x == '_');
+    if !is_identifier {
+        return Err(CompilerError::InvalidModuleName {
+            path: path.display().to_string(),
+        }
+        .into());
+    }
+
+    Ok(name.to_string())
+}
+
+fn with_lines(source: &str, file: &str, nodes: Vec<Node>) -> Vec<Term> {
+    let mut terms = Vec::with_capacity(nodes.len() * 2);
+
+    for node in nodes {
+        terms.push(Term::Line {
+            file: file.to_string(),
+            line: Location::new(source, node.span).line_start,
+        });
+        terms.push(match node.term {
+            Term::List { .. } => Term::List {
+                terms: with_lines(source, file, node.children),
+            },
+            Term::Macro { name, .. } => Term::Macro {
+                name,
+                terms: with_lines(source, file, node.children),
+            },
+            term => term,
+        });
+    }
+
+    terms
+}
+
+fn strip_lines(terms: Vec<Term>) -> Vec<Term> {
+    terms
+        .into_iter()
+        .filter_map(|term| match term {
+            Term::Line { .. } => None,
+            Term::List { terms } => Some(Term::List {
+                terms: strip_lines(terms),
+            }),
+            term => Some(term),
+        })
+        .collect()
+}
+
+fn contains_include(terms: &[Term]) -> bool {
+    terms.iter().any(|term| match term {
+        Term::Include { .. } => true,
+        Term::List { terms } | Term::Macro { terms, .. } => contains_include(terms),
+        _ => false,
+    })
+}
+
+/// Qualifies every binding defined in the module with the module name.
+/// Names the module doesn't define are left as is and refer to the main program.
+fn namespace(terms: Vec<Term>, module: &str) -> Vec<Term> {
+    let mut defined = HashSet::new();
+    collect_bindings(&terms, &mut defined);
+
+    rename(terms, &|identifier| {
+        if defined.contains(identifier) {
+            format!("{module}::{identifier}")
+        } else {
+            identifier.to_string()
+        }
+    })
+}
+
+fn collect_bindings(terms: &[Term], defined: &mut HashSet<String>) {
+    for term in terms {
+        match term {
+            Term::Bind { identifier } if !identifier.contains("::") => {
+                defined.insert(identifier.clone());
+            }
+            Term::Macro { name, terms } => {
+                defined.insert(name.clone());
+                collect_bindings(terms, defined);
+            }
+            Term::List { terms } => collect_bindings(terms, defined),
+            _ => {}
+        }
+    }
+}
+
+fn collect_spans(nodes: &[Node], is_node: &impl Fn(&Term) -> bool, spans: &mut Vec<Span>) {
+    for node in nodes {
+        if is_node(&node.term) {
+            spans.push(node.span);
+        }
+        collect_spans(&node.children, is_node, spans);
+    }
+}
+
+/// A name which is put but never bound, there would be no cell to read it from.
+fn unbound_name(terms: &[Term]) -> Option<&str> {
+    fn collect<'a>(terms: &'a [Term], bound: &mut HashSet<&'a str>, puts: &mut Vec<&'a str>) {
+        for term in terms {
+            match term {
+                Term::Bind { identifier } => {
+                    bound.insert(identifier);
+                }
+                Term::Put { identifier } => puts.push(identifier),
+                Term::List { terms } => collect(terms, bound, puts),
+                _ => {}
+            }
+        }
+    }
+
+    let mut bound = HashSet::new();
+    let mut puts = Vec::new();
+    collect(terms, &mut bound, &mut puts);
+
+    puts.into_iter().find(|x| !bound.contains(x))
+}
+
+/// Renames the bound and put names, including the names of macros.
+pub(crate) fn rename(terms: Vec<Term>, f: &impl Fn(&str) -> String) -> Vec<Term> {
+    terms
+        .into_iter()
+        .map(|term| match term {
+            Term::Bind { identifier } => Term::Bind {
+                identifier: f(&identifier),
+            },
+            Term::Put { identifier } => Term::Put {
+                identifier: f(&identifier),
+            },
+            Term::List { terms } => Term::List {
+                terms: rename(terms, f),
+            },
+            Term::Macro { name, terms } => Term::Macro {
+                name: f(&name),
+                terms: rename(terms, f),
+            },
+            term => term,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use crate::common::{Ast, Term};
+
+    use super::*;
+
+    fn make_dir(files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join("plc")
+            .join(uuid::Uuid::new_v4().to_string());
+
+        for (name, source) in files {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, source).unwrap();
+        }
+
+        dir
+    }
+
+    fn put(identifier: &str) -> Term {
+        Term::Put {
+            identifier: identifier.to_string(),
+        }
+    }
+
+    fn bind(identifier: &str) -> Term {
+        Term::Bind {
+            identifier: identifier.to_string(),
+        }
+    }
+
+    #[test]
+    fn include_namespaces_module_bindings() {
+        let dir = make_dir(&[
+            ("main.plc", "include \"math.plc\" 5 math::sq! ."),
+            ("math.plc", "[ dup * ] :sq [ sq! sq! ] :quad"),
+        ]);
+        let exp = Ast {
+            terms: vec![
+                Term::List {
+                    terms: vec![Term::Dup, Term::Mul],
+                },
+                bind("math::sq"),
+                Term::List {
+                    terms: vec![put("math::sq"), Term::Apply, put("math::sq"), Term::Apply],
+                },
+                bind("math::quad"),
+                Term::Int(5),
+                put("math::sq"),
+                Term::Apply,
+                Term::Print,
+            ],
+        };
+        let act = load(&dir.join("main.plc"), &[], false);
+        assert!(act.is_ok());
+        assert_eq!(exp, act.unwrap());
+    }
+
+    #[test]
+    fn include_leaves_undefined_names_global() {
+        let dir = make_dir(&[
+            ("main.plc", "1 :x include \"lib/m.plc\""),
+            ("lib/m.plc", "x"),
+        ]);
+        let exp = Ast {
+            terms: vec![Term::Int(1), bind("x"), put("x")],
+        };
+        let act = load(&dir.join("main.plc"), &[], false);
+        assert!(act.is_ok());
+        assert_eq!(exp, act.unwrap());
+    }
+
+    #[test]
+    fn include_searches_include_dirs() {
+        let dir = make_dir(&[("src/main.plc", "include \"m.plc\""), ("deps/m.plc", "1")]);
+        let exp = Ast {
+            terms: vec![Term::Int(1)],
+        };
+        let act = load(&dir.join("src/main.plc"), &[dir.join("deps")], false);
+        assert!(act.is_ok());
+        assert_eq!(exp, act.unwrap());
+    }
+
+    #[test]
+    fn include_prefers_including_file_dir() {
+        let dir = make_dir(&[
+            ("main.plc", "include \"m.plc\""),
+            ("m.plc", "1"),
+            ("deps/m.plc", "2"),
+        ]);
+        let exp = Ast {
+            terms: vec![Term::Int(1)],
+        };
+        let act = load(&dir.join("main.plc"), &[dir.join("deps")], false);
+        assert!(act.is_ok());
+        assert_eq!(exp, act.unwrap());
+    }
+
+    #[test]
+    fn include_once() {
+        let dir = make_dir(&[
+            ("main.plc", "include \"a.plc\" include \"b.plc\""),
+            ("a.plc", "include \"c.plc\" 1"),
+            ("b.plc", "include \"c.plc\" 2"),
+            ("c.plc", "3"),
+        ]);
+        let exp = Ast {
+            terms: vec![Term::Int(3), Term::Int(1), Term::Int(2)],
+        };
+        let act = load(&dir.join("main.plc"), &[], false);
+        assert!(act.is_ok());
+        assert_eq!(exp, act.unwrap());
+    }
+
+    #[test]
+    fn include_cycle() {
+        let dir = make_dir(&[
+            ("main.plc", "include \"a.plc\""),
+            ("a.plc", "include \"b.plc\""),
+            ("b.plc", "1 .\ninclude \"a.plc\""),
+        ]);
+        let act = load(&dir.join("main.plc"), &[], false);
+        assert!(act.is_err());
+
+        let diagnostic = Diagnostic::from_error(&act.unwrap_err());
+        assert!(diagnostic.message.contains("include cycle"));
+        assert_eq!(Some("E0007"), diagnostic.code.as_deref());
+        let notes: Vec<_> = diagnostic.notes.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            vec![
+                format!(
+                    "{}:1:1: includes {}",
+                    dir.join("a.plc").display(),
+                    dir.join("b.plc").display()
+                ),
+                format!(
+                    "{}:2:1: includes {}",
+                    dir.join("b.plc").display(),
+                    dir.join("a.plc").display()
+                ),
+            ],
+            notes
+        );
+    }
+
+    #[test]
+    fn include_duplicate_module() {
+        let dir = make_dir(&[
+            ("main.plc", "include \"a/m.plc\" include \"b/m.plc\""),
+            ("a/m.plc", "1"),
+            ("b/m.plc", "2"),
+        ]);
+        let act = load(&dir.join("main.plc"), &[], false);
+        assert!(act.is_err());
+
+        let diagnostic = Diagnostic::from_error(&act.unwrap_err());
+        assert_eq!(Some("E0009"), diagnostic.code.as_deref());
+        assert_eq!(
+            vec![Note::new("`m` is first defined here")
+                .in_file(dir.join("a/m.plc").display().to_string())],
+            diagnostic.notes
+        );
+    }
+
+    #[test]
+    fn duplicate_macro_points_at_second_definition() {
+        let dir = make_dir(&[("main.plc", "macro f [ 1 ]\nmacro f [ 2 ]")]);
+        let act = load(&dir.join("main.plc"), &[], false);
+        assert!(act.is_err());
+
+        let diagnostic = Diagnostic::from_error(&act.unwrap_err());
+        assert_eq!(Some("E0012"), diagnostic.code.as_deref());
+        assert_eq!(
+            Some(dir.join("main.plc").display().to_string()),
+            diagnostic.file
+        );
+        assert_eq!(2, diagnostic.span.unwrap().line_start);
+        assert_eq!(
+            vec![Note::new("`f` is first defined here")
+                .in_file(dir.join("main.plc").display().to_string())
+                .at("macro f [ 1 ]", Span { start: 0, end: 13 })],
+            diagnostic.notes
+        );
+    }
+
+    #[test]
+    fn include_missing_file() {
+        let dir = make_dir(&[("main.plc", "include \"none.plc\"")]);
+        let act = load(&dir.join("main.plc"), &[], false);
+        assert!(act.is_err());
+    }
+
+    #[test]
+    fn include_inside_list() {
+        let dir = make_dir(&[("main.plc", "[ include \"m.plc\" ]"), ("m.plc", "1")]);
+        let act = load(&dir.join("main.plc"), &[], false);
+        assert!(act.is_err());
+    }
+
+    #[test]
+    fn unbound_name() {
+        let dir = make_dir(&[("main.plc", "[ x ] :f 1 :y y")]);
+        let act = load(&dir.join("main.plc"), &[], false);
+        assert!(act.is_err());
+
+        let diagnostic = Diagnostic::from_error(&act.unwrap_err());
+        assert_eq!("`x` is not bound", diagnostic.message);
+        assert!(diagnostic.file.unwrap().ends_with("main.plc"));
+        assert_eq!(
+            (2, 3),
+            diagnostic.span.map(|x| (x.byte_start, x.byte_end)).unwrap()
+        );
+    }
+
+    #[test]
+    fn unbound_name_in_module() {
+        let dir = make_dir(&[
+            ("main.plc", "include \"math.plc\" 1 math::sq!"),
+            ("math.plc", "[ dup * ] :sq\n[ sq! cube! ] :quad"),
+        ]);
+        let act = load(&dir.join("main.plc"), &[], false);
+
+        let diagnostic = Diagnostic::from_error(&act.unwrap_err());
+        assert_eq!("`cube` is not bound", diagnostic.message);
+        assert!(diagnostic.file.unwrap().ends_with("math.plc"));
+        let span = diagnostic.span.unwrap();
+        assert_eq!((2, 7), (span.line_start, span.column_start));
+    }
+
+    #[test]
+    fn parse_error_location() {
+        let act = Loader::new(Vec::new(), false).load_source("1 2\n+ ) .", Path::new("/"));
+
+        let diagnostic = Diagnostic::from_error(&act.unwrap_err());
+        assert_eq!("unexpected `)`", diagnostic.message);
+        assert_eq!(Some("<stdin>".to_string()), diagnostic.file);
+        let span = diagnostic.span.unwrap();
+        assert_eq!(
+            (2, 3, 6, 7),
+            (
+                span.line_start,
+                span.column_start,
+                span.byte_start,
+                span.byte_end
+            )
+        );
+    }
+
+    #[test]
+    fn line_info() {
+        fn line(line: usize) -> Term {
+            Term::Line {
+                file: STDIN_FILE.to_string(),
+                line,
+            }
+        }
+
+        let exp = Ast {
+            terms: vec![
+                line(1),
+                Term::Int(1),
+                line(2),
+                Term::List {
+                    terms: vec![line(2), Term::Int(2)],
+                },
+            ],
+        };
+        let act = Loader::new(vec![], false)
+            .with_line_info(true)
+            .load_source("1\n[ 2 ]", Path::new("/"));
+        assert_eq!(exp, act.unwrap());
+
+        let act = Loader::new(vec![], false)
+            .with_line_info(true)
+            .load_source("[ 1 2 + ]\nconst-eval .", Path::new("/"));
+        assert_eq!(
+            vec![line(1), Term::Int(3), line(2), Term::Print],
+            act.unwrap().terms
+        );
+    }
+
+    #[test]
+    fn const_eval_error_location() {
+        let dir = make_dir(&[
+            ("main.plc", "include \"m.plc\"\n1 ."),
+            ("m.plc", "[ 1 ] const-eval .\n[ 2 ] [ 1 0 / ] const-eval"),
+        ]);
+        let act = load(&dir.join("main.plc"), &[], false);
+        assert!(act.is_err());
+
+        let diagnostic = Diagnostic::from_error(&act.unwrap_err());
+        assert_eq!(Some("E0015"), diagnostic.code.as_deref());
+        assert_eq!(
+            Some(dir.join("m.plc").display().to_string()),
+            diagnostic.file
+        );
+        let span = diagnostic.span.unwrap();
+        assert_eq!((2, 17), (span.line_start, span.column_start));
+    }
+
+    #[test]
+    fn const_eval_fuel() {
+        let source = "[ 0 1000 [ 1 + ] times ] const-eval .";
+        let act = Loader::new(vec![], false).load_source(source, Path::new("/"));
+        assert!(act.is_ok());
+
+        let act = Loader::new(vec![], false)
+            .with_const_eval_fuel(100)
+            .load_source(source, Path::new("/"));
+        assert!(act.is_err());
+    }
+}
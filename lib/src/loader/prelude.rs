@@ -0,0 +1,153 @@
+use std::collections::HashSet;
+
+use crate::{common::Term, parser::parse};
+
+use super::{collect_bindings, namespace, rename};
+
+pub const PRELUDE: &str = include_str!("prelude.plc");
+pub const PRELUDE_MODULE: &str = "prelude";
+
+/// Prepends the prelude definitions to the program.
+///
+/// Unqualified names which the program doesn't bind itself are resolved to the prelude,
+/// so a user definition always shadows a prelude word with the same name.
+pub fn link_prelude(terms: Vec<Term>) -> Vec<Term> {
+    let definitions = definitions();
+
+    let mut bound = HashSet::new();
+    collect_bindings(&terms, &mut bound);
+    let terms = rename(terms, &|identifier| {
+        let qualified = format!("{PRELUDE_MODULE}::{identifier}");
+        if !bound.contains(identifier) && definitions.iter().any(|(x, _)| *x == qualified) {
+            qualified
+        } else {
+            identifier.to_string()
+        }
+    });
+
+    definitions
+        .into_iter()
+        .flat_map(|(identifier, body)| [body, Term::Bind { identifier }])
+        .chain(terms)
+        .collect()
+}
+
+/// Removes the prelude definitions which the program doesn't use.
+pub fn strip_prelude(terms: Vec<Term>) -> Vec<Term> {
+    let prefix = format!("{PRELUDE_MODULE}::");
+    let is_definition = |pair: &[Term]| matches!(pair, [Term::List { .. }, Term::Bind { identifier }] if identifier.starts_with(&prefix));
+
+    let split = terms
+        .chunks(2)
+        .take_while(|pair| is_definition(pair))
+        .count()
+        * 2;
+    let (definitions, program) = terms.split_at(split);
+
+    let mut used = HashSet::new();
+    let mut pending = Vec::new();
+    collect_puts(program, &mut pending);
+    while let Some(identifier) = pending.pop() {
+        if let Some(pair) = definitions
+            .chunks(2)
+            .find(|pair| matches!(&pair[1], Term::Bind { identifier: x } if *x == identifier))
+        {
+            if used.insert(identifier) {
+                collect_puts(&pair[..1], &mut pending);
+            }
+        }
+    }
+
+    definitions
+        .chunks(2)
+        .filter(|pair| matches!(&pair[1], Term::Bind { identifier } if used.contains(identifier)))
+        .flatten()
+        .chain(program)
+        .cloned()
+        .collect()
+}
+
+/// The prelude consists only of `[ ... ] :name` definitions.
+fn definitions() -> Vec<(String, Term)> {
+    let ast = parse(PRELUDE).expect("prelude must be a valid program");
+    let terms = namespace(ast.terms, PRELUDE_MODULE);
+
+    terms
+        .chunks(2)
+        .map(|definition| match definition {
+            [body @ Term::List { .. }, Term::Bind { identifier }] => {
+                (identifier.clone(), body.clone())
+            }
+            _ => panic!("prelude must consist of `[ ... ] :name` definitions"),
+        })
+        .collect()
+}
+
+fn collect_puts(terms: &[Term], puts: &mut Vec<String>) {
+    for term in terms {
+        match term {
+            Term::Put { identifier } => puts.push(identifier.clone()),
+            Term::List { terms } => collect_puts(terms, puts),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::Term;
+
+    use super::*;
+
+    fn put(identifier: &str) -> Term {
+        Term::Put {
+            identifier: identifier.to_string(),
+        }
+    }
+
+    #[test]
+    fn prelude_is_valid() {
+        assert!(!definitions().is_empty());
+    }
+
+    #[test]
+    fn unused_words_are_stripped() {
+        let act = strip_prelude(link_prelude(vec![Term::Int(1)]));
+        assert_eq!(vec![Term::Int(1)], act);
+    }
+
+    #[test]
+    fn used_words_are_linked_with_dependencies() {
+        let act = strip_prelude(link_prelude(vec![put("gcd"), Term::Apply]));
+        let bound: Vec<_> = act
+            .iter()
+            .filter_map(|term| match term {
+                Term::Bind { identifier } => Some(identifier.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                "prelude::neg",
+                "prelude::abs",
+                "prelude::mod",
+                "prelude::gcd"
+            ],
+            bound
+        );
+        assert_eq!(&[put("prelude::gcd"), Term::Apply], &act[act.len() - 2..]);
+    }
+
+    #[test]
+    fn user_definitions_shadow_prelude() {
+        let terms = vec![
+            Term::List { terms: vec![] },
+            Term::Bind {
+                identifier: "abs".to_string(),
+            },
+            put("abs"),
+        ];
+        let act = strip_prelude(link_prelude(terms.clone()));
+        assert_eq!(terms, act);
+    }
+}
@@ -0,0 +1,158 @@
+use anyhow::{Context, Result};
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{
+    common::Term,
+    err::CompilerError,
+    interpreter::{Interpreter, Value},
+};
+
+/// Limits the evaluation, so a non-terminating `const-eval` is reported instead of hanging.
+pub const CONST_EVAL_FUEL: u64 = 10_000_000;
+
+/// Replaces `[ ... ] const-eval` with the values the list leaves on the stack.
+///
+/// The list may use the lists bound once at the top level of the program, e.g. `[ ... ] :f`;
+/// other bindings and input/output aren't known at compile time.
+/// Each `const-eval` may run at most `fuel` instructions.
+pub fn const_eval(terms: Vec<Term>, fuel: u64) -> Result<Vec<Term>> {
+    let constants = constants(&terms);
+    evaluate(terms, &constants, fuel)
+}
+
+/// The context of an error in a `const-eval`, with the line of the `const-eval`
+/// if the terms have line info.
+#[derive(Debug)]
+pub struct ConstEvalError {
+    pub line: Option<(String, usize)>,
+}
+
+impl Display for ConstEvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "const-eval")
+    }
+}
+
+fn evaluate(terms: Vec<Term>, constants: &[(String, Value)], fuel: u64) -> Result<Vec<Term>> {
+    let mut evaluated = Vec::with_capacity(terms.len());
+
+    for term in terms {
+        match term {
+            Term::ConstEval => {
+                let mut line = None;
+                while let Some(Term::Line { file, line: n }) = evaluated.last() {
+                    line.get_or_insert((file.clone(), *n));
+                    evaluated.pop();
+                }
+                let context = || ConstEvalError { line: line.clone() };
+                let Some(Term::List { terms }) = evaluated.pop() else {
+                    return Err(CompilerError::ConstEvalWithoutList.into());
+                };
+
+                let mut interpreter = Interpreter::new().with_fuel(fuel);
+                for (identifier, value) in constants {
+                    interpreter.bind(identifier, value.clone());
+                }
+                interpreter.run(&terms).with_context(context)?;
+
+                for value in interpreter.stack() {
+                    evaluated.extend(value.to_terms().with_context(context)?);
+                }
+            }
+            Term::List { terms } => evaluated.push(Term::List {
+                terms: evaluate(terms, constants, fuel)?,
+            }),
+            term => evaluated.push(term),
+        }
+    }
+
+    Ok(evaluated)
+}
+
+fn constants(terms: &[Term]) -> Vec<(String, Value)> {
+    let mut binds = HashMap::new();
+    count_binds(terms, &mut binds);
+
+    let terms: Vec<_> = terms
+        .iter()
+        .filter(|x| !matches!(x, Term::Line { .. }))
+        .collect();
+
+    terms
+        .windows(2)
+        .filter_map(|pair| match pair {
+            [Term::List { terms }, Term::Bind { identifier }] if binds[identifier] == 1 => {
+                Some((identifier.clone(), Value::quotation(terms)))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn count_binds(terms: &[Term], binds: &mut HashMap<String, usize>) {
+    for term in terms {
+        match term {
+            Term::Bind { identifier } => *binds.entry(identifier.clone()).or_default() += 1,
+            Term::List { terms } => count_binds(terms, binds),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{common::Term, parser::parse};
+
+    use super::*;
+
+    fn eval(source: &str) -> Result<Vec<Term>> {
+        const_eval(parse(source).unwrap().terms, CONST_EVAL_FUEL)
+    }
+
+    #[test]
+    fn numbers() {
+        let exp = parse("1 120 .").unwrap().terms;
+        let act = eval("1 [ 1 2 3 4 5 * * * * ] const-eval .");
+        assert!(act.is_ok());
+        assert_eq!(exp, act.unwrap());
+    }
+
+    #[test]
+    fn table() {
+        let exp = vec![Term::Array {
+            values: vec![0, 1, 4, 9],
+        }];
+        let act = eval("[ 4 alloc :t 0 4 [ :i i i * t i @= ] each t ] const-eval");
+        assert!(act.is_ok());
+        assert_eq!(exp, act.unwrap());
+    }
+
+    #[test]
+    fn uses_top_level_definitions() {
+        let exp = parse("[ dup * ] :sq [ 81 ]").unwrap().terms;
+        let act = eval("[ dup * ] :sq [ [ 3 sq! sq! ] const-eval ]");
+        assert!(act.is_ok());
+        assert_eq!(exp, act.unwrap());
+    }
+
+    #[test]
+    fn quotations() {
+        let exp = parse("1 [ + ] curry").unwrap().terms;
+        let act = eval("[ 1 [ + ] curry ] const-eval");
+        assert!(act.is_ok());
+        assert_eq!(exp, act.unwrap());
+    }
+
+    #[test]
+    fn unknown_at_compile_time() {
+        assert!(eval("1 :x [ x ] const-eval").is_err());
+        assert!(eval("[ & ] const-eval").is_err());
+        assert!(eval("[ ] :f [ ] :f [ f ] const-eval").is_err());
+        assert!(eval("[ [ ] [ ] while ] const-eval").is_err());
+    }
+
+    #[test]
+    fn not_a_list() {
+        assert!(eval("1 const-eval").is_err());
+    }
+}
@@ -0,0 +1,165 @@
+mod const_eval;
+
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+
+use crate::{common::Term, err::CompilerError, loader::rename};
+
+pub use const_eval::{const_eval, ConstEvalError, CONST_EVAL_FUEL};
+
+/// Splices the bodies of `macro name [ ... ]` definitions into their use sites.
+///
+/// Bindings made inside a macro body are renamed at each use site to names which can't
+/// be written in a program, so they never clash with the program's own bindings.
+pub fn expand_macros(terms: Vec<Term>) -> Result<Vec<Term>> {
+    let mut macros = HashMap::new();
+    let mut program = Vec::with_capacity(terms.len());
+
+    for term in terms {
+        match term {
+            Term::Macro { name, terms } => {
+                if macros.insert(name.clone(), terms).is_some() {
+                    return Err(CompilerError::DuplicateMacro { name }.into());
+                }
+            }
+            term => program.push(term),
+        }
+    }
+
+    Expander {
+        macros,
+        expanding: Vec::new(),
+        expansions: 0,
+    }
+    .expand(program)
+}
+
+struct Expander {
+    macros: HashMap<String, Vec<Term>>,
+    expanding: Vec<String>,
+    expansions: usize,
+}
+
+impl Expander {
+    fn expand(&mut self, terms: Vec<Term>) -> Result<Vec<Term>> {
+        let mut expanded = Vec::with_capacity(terms.len());
+
+        for term in terms {
+            match term {
+                Term::Put { identifier } if self.macros.contains_key(&identifier) => {
+                    if self.expanding.contains(&identifier) {
+                        return Err(CompilerError::RecursiveMacro { name: identifier }.into());
+                    }
+
+                    self.expansions += 1;
+                    let body = hygienic(self.macros[&identifier].clone(), self.expansions);
+
+                    self.expanding.push(identifier);
+                    expanded.extend(self.expand(body)?);
+                    self.expanding.pop();
+                }
+                Term::List { terms } => expanded.push(Term::List {
+                    terms: self.expand(terms)?,
+                }),
+                Term::Macro { name, .. } => {
+                    return Err(CompilerError::NotAtTopLevel {
+                        what: format!("macro `{}`", name),
+                    }
+                    .into())
+                }
+                term => expanded.push(term),
+            }
+        }
+
+        Ok(expanded)
+    }
+}
+
+/// Renames the bindings made in a macro body to `name@N`.
+fn hygienic(terms: Vec<Term>, expansion: usize) -> Vec<Term> {
+    let mut bound = HashSet::new();
+    collect_binds(&terms, &mut bound);
+
+    rename(terms, &|identifier| {
+        if bound.contains(identifier) {
+            format!("{identifier}@{expansion}")
+        } else {
+            identifier.to_string()
+        }
+    })
+}
+
+fn collect_binds(terms: &[Term], bound: &mut HashSet<String>) {
+    for term in terms {
+        match term {
+            Term::Bind { identifier } => {
+                bound.insert(identifier.clone());
+            }
+            Term::List { terms } => collect_binds(terms, bound),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{common::Term, parser::parse};
+
+    use super::*;
+
+    fn expand(source: &str) -> Result<Vec<Term>> {
+        expand_macros(parse(source).unwrap().terms)
+    }
+
+    #[test]
+    fn macro_is_spliced() {
+        let exp = parse("2 dup * 3 [ dup * ]").unwrap().terms;
+        let act = expand("macro sq [ dup * ] 2 sq 3 [ sq ]");
+        assert!(act.is_ok());
+        assert_eq!(exp, act.unwrap());
+    }
+
+    #[test]
+    fn macro_bindings_are_hygienic() {
+        let x = |n: usize| format!("x@{n}");
+        let exp = vec![
+            Term::Int(1),
+            Term::Bind {
+                identifier: "x".to_string(),
+            },
+            Term::Bind { identifier: x(1) },
+            Term::Put { identifier: x(1) },
+            Term::Bind { identifier: x(2) },
+            Term::Put { identifier: x(2) },
+            Term::Put {
+                identifier: "x".to_string(),
+            },
+        ];
+        let act = expand("macro m [ :x x ] 1 :x m m x");
+        assert!(act.is_ok());
+        assert_eq!(exp, act.unwrap());
+    }
+
+    #[test]
+    fn nested_macros() {
+        let exp = parse("1 1 + 1 1 + +").unwrap().terms;
+        let act = expand("macro two [ 1 1 + ] macro four [ two two + ] four");
+        assert!(act.is_ok());
+        assert_eq!(exp, act.unwrap());
+    }
+
+    #[test]
+    fn recursive_macro() {
+        assert!(expand("macro m1 [ m2 ] macro m2 [ m1 ] m1").is_err());
+    }
+
+    #[test]
+    fn duplicate_macro() {
+        assert!(expand("macro a [ ] macro a [ ]").is_err());
+    }
+
+    #[test]
+    fn nested_macro_definition() {
+        assert!(expand("[ macro a [ ] ]").is_err());
+    }
+}
@@ -1,4 +1,6 @@
 mod terms;
+mod textmate;
+mod tokens;
 mod util;
 
 use anyhow::Result;
@@ -7,10 +9,14 @@ use nom::{
     error::{ContextError, ParseError, VerboseError},
     Finish, IResult, Parser,
 };
-use terms::terms;
+use terms::{nodes, terms, RawNode};
+pub use {
+    textmate::textmate_grammar,
+    tokens::{tokenize, Token, TokenKind},
+};
 
 use crate::{
-    common::{Ast, Term},
+    common::{Ast, Node, Span, Term},
     err::CompilerError,
 };
 
@@ -23,6 +29,56 @@ pub fn parse<'s>(source: &'s str) -> Result<Ast, CompilerError<'s>> {
     Ok(Ast::from_terms(tokens))
 }
 
+/// Parses the program keeping the position of every term.
+pub fn parse_nodes<'s>(source: &'s str) -> Result<Vec<Node>, CompilerError<'s>> {
+    let (_, nodes) = match all_consuming(nodes::<VerboseError<&'s str>>)
+        .parse(source)
+        .finish()
+    {
+        Ok(v) => v,
+        Err(e) => return Err(CompilerError::parser_error(source, e)),
+    };
+
+    Ok(nodes.into_iter().map(|x| to_node(source, x)).collect())
+}
+
+fn to_node(source: &str, node: RawNode) -> Node {
+    let start = node.text.as_ptr() as usize - source.as_ptr() as usize;
+
+    Node {
+        term: node.term,
+        span: Span {
+            start,
+            end: start + node.text.len(),
+        },
+        children: node
+            .children
+            .into_iter()
+            .map(|x| to_node(source, x))
+            .collect(),
+    }
+}
+
+/// Where the parsing really stopped, given the rest of the input after the last parsed term:
+/// a list that failed to parse as a whole is entered to find the term inside it that did.
+/// Also returns the innermost entered list, it is unclosed if the whole input was consumed.
+pub(crate) fn innermost_error(rest: &str) -> (&str, Option<&str>) {
+    let mut rest = rest;
+    let mut list = None;
+
+    while let Some(inner) = rest.strip_prefix('[') {
+        match nodes::<()>(inner) {
+            Ok((inner_rest, _)) if !inner_rest.starts_with(']') => {
+                list = Some(rest);
+                rest = inner_rest;
+            }
+            _ => break,
+        }
+    }
+
+    (rest, list)
+}
+
 fn axiom<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
     inp: &'s str,
 ) -> IResult<&'s str, Vec<Term>, E> {
@@ -530,4 +586,494 @@ mod tests {
         let act = act.unwrap();
         assert_eq!(exp, act);
     }
+
+    #[test]
+    fn try_scan() {
+        let source = "&?";
+        let exp = Ast {
+            terms: vec![Term::TryScan],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        let act = act.unwrap();
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn alloc() {
+        let source = "alloc";
+        let exp = Ast {
+            terms: vec![Term::Alloc],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        let act = act.unwrap();
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn load() {
+        let source = "@";
+        let exp = Ast {
+            terms: vec![Term::Load],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        let act = act.unwrap();
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn store() {
+        let source = "@=";
+        let exp = Ast {
+            terms: vec![Term::Store],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        let act = act.unwrap();
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn len() {
+        let source = "len";
+        let exp = Ast {
+            terms: vec![Term::Len],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        let act = act.unwrap();
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn array_access() {
+        let source = "3 alloc 1 @ 5 len";
+        let exp = Ast {
+            terms: vec![
+                Term::Int(3),
+                Term::Alloc,
+                Term::Int(1),
+                Term::Load,
+                Term::Int(5),
+                Term::Len,
+            ],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        let act = act.unwrap();
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn times() {
+        let source = "times";
+        let exp = Ast {
+            terms: vec![Term::Times],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        let act = act.unwrap();
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn _while() {
+        let source = "while";
+        let exp = Ast {
+            terms: vec![Term::While],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        let act = act.unwrap();
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn each() {
+        let source = "each";
+        let exp = Ast {
+            terms: vec![Term::Each],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        let act = act.unwrap();
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn times_loop() {
+        let source = "3 [ 1 . ] times";
+        let exp = Ast {
+            terms: vec![
+                Term::Int(3),
+                Term::List {
+                    terms: vec![Term::Int(1), Term::Print],
+                },
+                Term::Times,
+            ],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        let act = act.unwrap();
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn ifte() {
+        let source = "ifte";
+        let exp = Ast {
+            terms: vec![Term::Ifte],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        let act = act.unwrap();
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn when() {
+        let source = "when";
+        let exp = Ast {
+            terms: vec![Term::When],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        let act = act.unwrap();
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn curry() {
+        let source = "curry";
+        let exp = Ast {
+            terms: vec![Term::Curry],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        let act = act.unwrap();
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn compose() {
+        let source = "compose";
+        let exp = Ast {
+            terms: vec![Term::Compose],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        let act = act.unwrap();
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn swap() {
+        let source = "swap";
+        let exp = Ast {
+            terms: vec![Term::Swap],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        let act = act.unwrap();
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn over() {
+        let source = "over";
+        let exp = Ast {
+            terms: vec![Term::Over],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        let act = act.unwrap();
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn rot() {
+        let source = "rot";
+        let exp = Ast {
+            terms: vec![Term::Rot],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        let act = act.unwrap();
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn minus_rot() {
+        let source = "-rot";
+        let exp = Ast {
+            terms: vec![Term::MinusRot],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        let act = act.unwrap();
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn nip() {
+        let source = "nip";
+        let exp = Ast {
+            terms: vec![Term::Nip],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        let act = act.unwrap();
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn tuck() {
+        let source = "tuck";
+        let exp = Ast {
+            terms: vec![Term::Tuck],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        let act = act.unwrap();
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn pick() {
+        let source = "pick";
+        let exp = Ast {
+            terms: vec![Term::Pick],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        let act = act.unwrap();
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn two_dup() {
+        let source = "2dup";
+        let exp = Ast {
+            terms: vec![Term::TwoDup],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        let act = act.unwrap();
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn two_drop() {
+        let source = "2drop";
+        let exp = Ast {
+            terms: vec![Term::TwoDrop],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        let act = act.unwrap();
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn minus_rot_and_numbers() {
+        let source = "-1 - -rot 2 2drop";
+        let exp = Ast {
+            terms: vec![
+                Term::Int(-1),
+                Term::Sub,
+                Term::MinusRot,
+                Term::Int(2),
+                Term::TwoDrop,
+            ],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        let act = act.unwrap();
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn include() {
+        let source = "include \"lib/math.plc\" 1";
+        let exp = Ast {
+            terms: vec![
+                Term::Include {
+                    path: "lib/math.plc".to_string(),
+                },
+                Term::Int(1),
+            ],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        assert_eq!(exp, act.unwrap());
+    }
+
+    #[test]
+    fn include_is_reserved() {
+        assert!(parse(":include").is_err());
+        assert!(parse("include").is_err());
+    }
+
+    #[test]
+    fn qualified_put() {
+        let source = "math::fac includes";
+        let exp = Ast {
+            terms: vec![
+                Term::Put {
+                    identifier: "math::fac".to_string(),
+                },
+                Term::Put {
+                    identifier: "includes".to_string(),
+                },
+            ],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        assert_eq!(exp, act.unwrap());
+    }
+
+    #[test]
+    fn qualified_bind() {
+        let source = ":math::fac";
+        let act = parse(source);
+        assert!(act.is_err());
+    }
+
+    #[test]
+    fn macro_definition() {
+        let source = "macro sq [ dup * ] sq";
+        let exp = Ast {
+            terms: vec![
+                Term::Macro {
+                    name: "sq".to_string(),
+                    terms: vec![Term::Dup, Term::Mul],
+                },
+                Term::Put {
+                    identifier: "sq".to_string(),
+                },
+            ],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        assert_eq!(exp, act.unwrap());
+    }
+
+    #[test]
+    fn const_eval() {
+        let source = "[ 1 ] const-eval";
+        let exp = Ast {
+            terms: vec![
+                Term::List {
+                    terms: vec![Term::Int(1)],
+                },
+                Term::ConstEval,
+            ],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        assert_eq!(exp, act.unwrap());
+    }
+
+    #[test]
+    fn array_literal() {
+        let source = "{ 1 -2 3 } {}";
+        let exp = Ast {
+            terms: vec![
+                Term::Array {
+                    values: vec![1, -2, 3],
+                },
+                Term::Array { values: vec![] },
+            ],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        assert_eq!(exp, act.unwrap());
+    }
+
+    #[test]
+    fn int_bounds() {
+        let source = "2147483647 -2147483648 +1";
+        let exp = Ast {
+            terms: vec![Term::Int(i32::MAX), Term::Int(i32::MIN), Term::Int(1)],
+        };
+        let act = parse(source);
+        assert!(act.is_ok());
+        assert_eq!(exp, act.unwrap());
+    }
+
+    #[test]
+    fn int_out_of_range() {
+        assert!(parse("2147483648").is_err());
+        assert!(parse("-2147483649").is_err());
+        assert!(parse("[ 99999999999999999999999 ]").is_err());
+    }
+
+    #[test]
+    fn node_spans() {
+        let source = "1 [ dup # x\n * ] :sq\nmacro m [ . ]";
+        let nodes = parse_nodes(source).unwrap();
+        let texts: Vec<_> = nodes
+            .iter()
+            .map(|x| &source[x.span.start..x.span.end])
+            .collect();
+        assert_eq!(vec!["1", "[ dup # x\n * ]", ":sq", "macro m [ . ]"], texts);
+
+        let list: Vec<_> = nodes[1]
+            .children
+            .iter()
+            .map(|x| (x.span.start, x.span.end))
+            .collect();
+        assert_eq!(vec![(4, 7), (13, 14)], list);
+        assert_eq!(Term::Print, nodes[3].children[0].term);
+        assert_eq!(31, nodes[3].children[0].span.start);
+    }
+
+    #[test]
+    fn nodes_match_terms() {
+        let examples = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../examples");
+        for entry in std::fs::read_dir(examples).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|x| x != "plc") {
+                continue;
+            }
+            let source = std::fs::read_to_string(path).unwrap();
+            let terms: Vec<_> = parse_nodes(&source)
+                .unwrap()
+                .into_iter()
+                .map(|x| x.term)
+                .collect();
+            assert_eq!(parse(&source).unwrap().terms, terms);
+        }
+        assert!(parse_nodes("1 [").is_err());
+    }
+
+    #[test]
+    fn error_diagnostics() {
+        let diagnostic = |source| {
+            let diagnostic = parse(source).unwrap_err().to_diagnostic();
+            let span = diagnostic.span.unwrap();
+            (diagnostic.message, span.byte_start, span.byte_end)
+        };
+
+        assert_eq!(("unexpected `)`".to_string(), 2, 3), diagnostic("1 ) 2"));
+        assert_eq!(("unclosed `[`".to_string(), 0, 1), diagnostic("[ 1 [ 2 ]"));
+        assert_eq!(
+            ("unclosed `[`".to_string(), 10, 11),
+            diagnostic("[ 1 [ 2 ] [ 3")
+        );
+        assert_eq!(
+            ("unexpected `)`".to_string(), 8, 9),
+            diagnostic("[ 1 [ 2 ) ] ]")
+        );
+        assert_eq!(
+            (
+                "expected a number from -2147483648 to 2147483647".to_string(),
+                2,
+                13
+            ),
+            diagnostic("[ 99999999999 ]")
+        );
+    }
 }
@@ -1,10 +1,10 @@
 use nom::{
     branch::alt,
     bytes::complete::{tag, take_while, take_while_m_n},
-    character::complete::{char, one_of},
-    combinator::{all_consuming, not, peek, value, verify},
-    error::{ContextError, ParseError},
-    multi::{many0, many1, many_m_n},
+    character::complete::{char, digit1, one_of},
+    combinator::{all_consuming, consumed, not, opt, peek, recognize, value, verify},
+    error::{ContextError, ErrorKind, ParseError},
+    multi::{many0, many1},
     sequence::delimited,
     IResult, Parser,
 };
@@ -29,11 +29,87 @@ pub fn terms<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
     .parse(inp)
 }
 
-fn term<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+/// A term with the text it was parsed from; the terms of a list or a macro are nested.
+pub struct RawNode<'s> {
+    pub term: Term,
+    pub text: &'s str,
+    pub children: Vec<RawNode<'s>>,
+}
+
+/// The same as [`terms`], but keeps the text of every term.
+pub fn nodes<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Vec<RawNode<'s>>, E> {
+    delimited(
+        many0(separator),
+        many0(node.and(many0(separator))).map(|node_pairs| {
+            node_pairs
+                .into_iter()
+                .map(|node_pair| node_pair.0)
+                .collect()
+        }),
+        many0(separator),
+    )
+    .parse(inp)
+}
+
+fn node<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, RawNode<'s>, E> {
+    alt((
+        list_node,
+        macro_node,
+        consumed(term).map(|(text, term)| RawNode {
+            term,
+            text,
+            children: Vec::new(),
+        }),
+    ))
+    .parse(inp)
+}
+
+fn list_node<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, RawNode<'s>, E> {
+    consumed(delimited(tag("["), nodes, tag("]")))
+        .map(|(text, children)| RawNode {
+            term: Term::List {
+                terms: children.iter().map(|x| x.term.clone()).collect(),
+            },
+            text,
+            children,
+        })
+        .parse(inp)
+}
+
+fn macro_node<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, RawNode<'s>, E> {
+    consumed(
+        tag("macro")
+            .and(many1(separator))
+            .and(identifier)
+            .and(many0(separator))
+            .and(list_node),
+    )
+    .map(|(text, (((_, name), _), list))| RawNode {
+        term: Term::Macro {
+            name: name.to_string(),
+            terms: list.children.iter().map(|x| x.term.clone()).collect(),
+        },
+        text,
+        children: list.children,
+    })
+    .parse(inp)
+}
+
+pub fn term<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
     inp: &'s str,
 ) -> IResult<&'s str, Term, E> {
     alt((
+        symbolic_stack_keyword,
         int,
+        directive,
         put,
         alphabetic_keyword,
         bind,
@@ -43,23 +119,79 @@ fn term<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
         div,
         print,
         list,
+        array_literal,
+        comparison,
+        array_access,
+        apply,
+        _if,
+        try_scan,
+        scan,
+    ))
+    .parse(inp)
+}
+
+fn alphabetic_keyword<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    alt((
+        _bool,
+        and,
+        or,
+        _not,
+        stack_keyword,
+        control_keyword,
+        curry,
+        compose,
+        alloc,
+        len,
+    ))
+    .parse(inp)
+}
+
+fn stack_keyword<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    alt((take, dup, drop, swap, over, rot, nip, tuck, pick)).parse(inp)
+}
+
+/// Terms resolved by the loader before translation: `include`, `macro` and `const-eval`.
+fn directive<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    alt((include, macro_definition, const_eval)).parse(inp)
+}
+
+/// Stack keywords which start with a digit or a sign, so they must be tried before numbers.
+fn symbolic_stack_keyword<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    alt((two_dup, two_drop, minus_rot)).parse(inp)
+}
+
+fn control_keyword<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    alt((ifte, when, times, _while, each)).parse(inp)
+}
+
+fn comparison<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    alt((
         not_equals,
         equals,
         less_equals,
         less,
         greater_equals,
         greater,
-        apply,
-        _if,
-        scan,
     ))
     .parse(inp)
 }
 
-fn alphabetic_keyword<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+fn array_access<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
     inp: &'s str,
 ) -> IResult<&'s str, Term, E> {
-    alt((_bool, and, or, _not, take, dup, drop)).parse(inp)
+    alt((store, load)).parse(inp)
 }
 
 fn add<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
@@ -95,24 +227,22 @@ fn print<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
 fn int<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
     inp: &'s str,
 ) -> IResult<&'s str, Term, E> {
-    many_m_n(0, 1, one_of("-+"))
-        .and(many1(one_of("1234567890")))
-        .map(|(sign, digits)| {
-            let sign = if !sign.is_empty() && sign[0] == '-' {
-                -1
-            } else {
-                1
-            };
-            let uinteger = digits
-                .into_iter()
-                .collect::<String>()
-                .parse::<i32>()
-                .unwrap();
-            let integer = sign * uinteger;
+    number.map(Term::Int).parse(inp)
+}
 
-            Term::Int(integer)
-        })
-        .parse(inp)
+fn number<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, i32, E> {
+    let (rest, literal) = recognize(opt(one_of("-+")).and(digit1)).parse(inp)?;
+
+    match literal.parse::<i32>() {
+        Ok(number) => Ok((rest, number)),
+        Err(_) => Err(nom::Err::Failure(E::add_context(
+            inp,
+            "a number from -2147483648 to 2147483647",
+            E::from_error_kind(inp, ErrorKind::Digit),
+        ))),
+    }
 }
 
 fn dup<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
@@ -133,6 +263,60 @@ fn take<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
     value(Term::Take, tag("take")).parse(inp)
 }
 
+fn swap<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    value(Term::Swap, tag("swap")).parse(inp)
+}
+
+fn over<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    value(Term::Over, tag("over")).parse(inp)
+}
+
+fn rot<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    value(Term::Rot, tag("rot")).parse(inp)
+}
+
+fn minus_rot<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    value(Term::MinusRot, tag("-rot")).parse(inp)
+}
+
+fn nip<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    value(Term::Nip, tag("nip")).parse(inp)
+}
+
+fn tuck<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    value(Term::Tuck, tag("tuck")).parse(inp)
+}
+
+fn pick<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    value(Term::Pick, tag("pick")).parse(inp)
+}
+
+fn two_dup<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    value(Term::TwoDup, tag("2dup")).parse(inp)
+}
+
+fn two_drop<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    value(Term::TwoDrop, tag("2drop")).parse(inp)
+}
+
 fn list<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
     inp: &'s str,
 ) -> IResult<&'s str, Term, E> {
@@ -141,12 +325,57 @@ fn list<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
         .parse(inp)
 }
 
+/// { 1 2 3 }
+fn array_literal<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    delimited(
+        tag("{").and(many0(separator)),
+        many0(number.and(many0(separator))),
+        tag("}"),
+    )
+    .map(|numbers| Term::Array {
+        values: numbers.into_iter().map(|number| number.0).collect(),
+    })
+    .parse(inp)
+}
+
+fn times<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    value(Term::Times, tag("times")).parse(inp)
+}
+
+fn _while<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    value(Term::While, tag("while")).parse(inp)
+}
+
+fn each<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    value(Term::Each, tag("each")).parse(inp)
+}
+
 fn apply<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
     inp: &'s str,
 ) -> IResult<&'s str, Term, E> {
     value(Term::Apply, tag("!")).parse(inp)
 }
 
+fn curry<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    value(Term::Curry, tag("curry")).parse(inp)
+}
+
+fn compose<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    value(Term::Compose, tag("compose")).parse(inp)
+}
+
 fn and<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
     inp: &'s str,
 ) -> IResult<&'s str, Term, E> {
@@ -207,6 +436,18 @@ fn _if<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
     value(Term::If, tag("?")).parse(inp)
 }
 
+fn ifte<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    value(Term::Ifte, tag("ifte")).parse(inp)
+}
+
+fn when<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    value(Term::When, tag("when")).parse(inp)
+}
+
 fn _bool<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
     inp: &'s str,
 ) -> IResult<&'s str, Term, E> {
@@ -217,37 +458,90 @@ fn bind<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
     inp: &'s str,
 ) -> IResult<&'s str, Term, E> {
     char(':')
-        .and(put)
-        .map(|(_, term)| {
-            if let Term::Put { identifier } = term {
-                Term::Bind { identifier }
-            } else {
-                unreachable!()
-            }
+        .and(identifier)
+        .map(|(_, identifier)| Term::Bind {
+            identifier: identifier.to_string(),
         })
         .parse(inp)
 }
 
-/// первый символ: буква, _
-/// остальные символы: буква, цифра, _
+/// include "path/to/file.plc"
+fn include<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    tag("include")
+        .and(many1(separator))
+        .and(delimited(
+            char('"'),
+            take_while(|x: char| x != '"' && x != '\n'),
+            char('"'),
+        ))
+        .map(|(_, path): (_, &str)| Term::Include {
+            path: path.to_string(),
+        })
+        .parse(inp)
+}
+
+/// macro name [ ... ]
+fn macro_definition<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    tag("macro")
+        .and(many1(separator))
+        .and(identifier)
+        .and(many0(separator))
+        .and(list)
+        .map(|(((_, name), _), list)| match list {
+            Term::List { terms } => Term::Macro {
+                name: name.to_string(),
+                terms,
+            },
+            term => term,
+        })
+        .parse(inp)
+}
+
+fn const_eval<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    value(Term::ConstEval, tag("const-eval")).parse(inp)
+}
+
+/// Имя, возможно квалифицированное модулем: `name`, `module::name`
 fn put<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
     inp: &'s str,
 ) -> IResult<&'s str, Term, E> {
+    recognize(identifier.and(many0(tag("::").and(identifier))))
+        .map(|x: &str| Term::Put {
+            identifier: x.to_string(),
+        })
+        .parse(inp)
+}
+
+/// первый символ: буква, _
+/// остальные символы: буква, цифра, _
+fn identifier<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, &'s str, E> {
     verify(
         peek(take_while_m_n(1, 1, |x: char| {
             x.is_alphabetic() || x == '_'
         }))
         .and(take_while(|x: char| x.is_alphanumeric() || x == '_'))
         .map(|(_, id): (&str, &str)| id),
-        |x| {
-            not(all_consuming(alphabetic_keyword::<()>))
-                .parse(x)
-                .is_ok()
-        },
+        |x| not(all_consuming(reserved_word::<()>)).parse(x).is_ok(),
     )
-    .map(|x| Term::Put {
-        identifier: x.to_string(),
-    })
+    .parse(inp)
+}
+
+fn reserved_word<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, (), E> {
+    alt((
+        alphabetic_keyword.map(|_| ()),
+        tag("include").map(|_| ()),
+        tag("macro").map(|_| ()),
+    ))
     .parse(inp)
 }
 
@@ -256,3 +550,33 @@ fn scan<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
 ) -> IResult<&'s str, Term, E> {
     value(Term::Scan, tag("&")).parse(inp)
 }
+
+fn try_scan<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    value(Term::TryScan, tag("&?")).parse(inp)
+}
+
+fn alloc<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    value(Term::Alloc, tag("alloc")).parse(inp)
+}
+
+fn load<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    value(Term::Load, tag("@")).parse(inp)
+}
+
+fn store<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    value(Term::Store, tag("@=")).parse(inp)
+}
+
+fn len<'s, E: ParseError<&'s str> + ContextError<&'s str>>(
+    inp: &'s str,
+) -> IResult<&'s str, Term, E> {
+    value(Term::Len, tag("len")).parse(inp)
+}
@@ -0,0 +1,146 @@
+use serde_json::{json, Value};
+
+use super::tokens::TokenKind;
+use crate::common::KEYWORD_TERMS;
+
+const IDENTIFIER: &str = r"[\p{L}_][\p{L}\p{N}_]*";
+/// A keyword or a name can't be followed by a character of a name.
+const WORD_END: &str = r"(?![\p{L}\p{N}_])";
+
+/// The TextMate grammar of plc, built from the same keywords as the parser.
+///
+/// A checked-in copy in `editors/vscode/syntaxes` is compared with it by the tests.
+pub fn textmate_grammar() -> String {
+    let keywords = alternation(TokenKind::Keyword);
+    let operators = alternation(TokenKind::Operator);
+
+    let grammar = json!({
+        "name": "plc",
+        "scopeName": "source.plc",
+        "fileTypes": ["plc"],
+        "patterns": [
+            { "include": "#comment" },
+            { "include": "#include" },
+            { "include": "#macro" },
+            { "include": "#binding" },
+            { "include": "#keyword" },
+            { "include": "#number" },
+            { "include": "#operator" },
+            { "include": "#identifier" },
+            { "include": "#bracket" },
+        ],
+        "repository": {
+            "comment": {
+                "name": TokenKind::Comment.scope(),
+                "match": "#.*$",
+            },
+            "include": {
+                "match": format!(r#"(include){}\s+("[^"\n]*")"#, WORD_END),
+                "captures": captures(&[TokenKind::Keyword, TokenKind::String]),
+            },
+            "macro": {
+                "match": format!(r"(macro){}\s+({})", WORD_END, IDENTIFIER),
+                "captures": captures(&[TokenKind::Keyword, TokenKind::Binding]),
+            },
+            "binding": {
+                "name": TokenKind::Binding.scope(),
+                "match": format!(":{}", IDENTIFIER),
+            },
+            "keyword": {
+                "name": TokenKind::Keyword.scope(),
+                "match": format!("(?:{}){}", keywords, WORD_END),
+            },
+            "number": {
+                "name": TokenKind::Number.scope(),
+                "match": r"[-+]?\d+",
+            },
+            "operator": {
+                "name": TokenKind::Operator.scope(),
+                "match": operators,
+            },
+            "identifier": {
+                "name": TokenKind::Identifier.scope(),
+                "match": format!("{0}(?:::{0})*", IDENTIFIER),
+            },
+            "bracket": {
+                "name": TokenKind::Bracket.scope(),
+                "match": r"[\[\]{}]",
+            },
+        },
+    });
+
+    serde_json::to_string_pretty(&grammar).expect("grammar must be serializable") + "\n"
+}
+
+/// The keywords of a kind as a regex alternation, longer ones first.
+fn alternation(kind: TokenKind) -> String {
+    let mut keywords: Vec<_> = KEYWORD_TERMS
+        .iter()
+        .filter_map(|x| x.keyword())
+        .filter(|x| TokenKind::of_keyword(x) == kind)
+        .collect();
+    keywords.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+
+    keywords
+        .into_iter()
+        .map(escape)
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+fn escape(keyword: &str) -> String {
+    keyword
+        .chars()
+        .map(|x| match x {
+            '.' | '^' | '$' | '|' | '(' | ')' | '[' | ']' | '{' | '}' | '*' | '+' | '?' | '\\' => {
+                format!(r"\{}", x)
+            }
+            x => x.to_string(),
+        })
+        .collect()
+}
+
+fn captures(kinds: &[TokenKind]) -> Value {
+    kinds
+        .iter()
+        .enumerate()
+        .map(|(i, kind)| ((i + 1).to_string(), json!({ "name": kind.scope() })))
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::Path};
+
+    use super::*;
+
+    const UPDATE_ENV: &str = "UPDATE_SNAPSHOTS";
+
+    /// The grammar is rewritten with `UPDATE_SNAPSHOTS=1 cargo test -p lib grammar`.
+    #[test]
+    fn grammar_is_up_to_date() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../editors/vscode/syntaxes/plc.tmLanguage.json");
+        let grammar = textmate_grammar();
+
+        if env::var_os(UPDATE_ENV).is_some() {
+            fs::write(&path, &grammar).unwrap();
+        }
+        assert_eq!(
+            fs::read_to_string(&path).unwrap_or_default(),
+            grammar,
+            "the grammar is outdated, run `{}=1 cargo test -p lib grammar`",
+            UPDATE_ENV
+        );
+    }
+
+    #[test]
+    fn operators_are_escaped() {
+        assert_eq!(
+            r"!=|&\?|<=|==|>=|@=|!|&|\*|\+|-|\.|/|<|>|\?|@",
+            alternation(TokenKind::Operator)
+        );
+        assert!(alternation(TokenKind::Keyword).starts_with("const-eval|compose|"));
+    }
+}
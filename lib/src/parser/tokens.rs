@@ -0,0 +1,284 @@
+use serde::Serialize;
+
+use super::terms::term;
+use crate::common::{Span, Term};
+
+/// The class of a token, for syntax highlighting.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenKind {
+    Number,
+    /// A keyword written with symbols, e.g. `+`, `!` or `@=`
+    Operator,
+    /// A keyword written with letters, e.g. `dup`, `2dup`, `include` or `macro`
+    Keyword,
+    /// A name which is used, e.g. `x` or `module::name`
+    Identifier,
+    /// A name which is bound: `:x`, or the name of a macro
+    Binding,
+    Comment,
+    /// The path of an `include`, with the quotes
+    String,
+    /// `[`, `]`, `{` or `}`
+    Bracket,
+    /// Text which is not a term
+    Invalid,
+}
+
+impl TokenKind {
+    /// The TextMate scope of the token.
+    pub fn scope(&self) -> &'static str {
+        match self {
+            TokenKind::Number => "constant.numeric.integer.plc",
+            TokenKind::Operator => "keyword.operator.plc",
+            TokenKind::Keyword => "keyword.other.plc",
+            TokenKind::Identifier => "variable.other.plc",
+            TokenKind::Binding => "entity.name.function.plc",
+            TokenKind::Comment => "comment.line.number-sign.plc",
+            TokenKind::String => "string.quoted.double.plc",
+            TokenKind::Bracket => "punctuation.section.brackets.plc",
+            TokenKind::Invalid => "invalid.illegal.plc",
+        }
+    }
+
+    /// A keyword is an operator unless it has a letter or a digit in it.
+    pub fn of_keyword(keyword: &str) -> TokenKind {
+        if keyword.chars().any(char::is_alphanumeric) {
+            TokenKind::Keyword
+        } else {
+            TokenKind::Operator
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+/// Splits the source into tokens with the parser's own term rules. Unlike parsing it
+/// never fails: a program being edited is tokenized as far as possible, and the text
+/// which is not a term becomes an [`TokenKind::Invalid`] token.
+///
+/// Lists, arrays, includes and macros are split into their parts, the whitespace is skipped.
+pub fn tokenize(source: &str) -> Vec<Token> {
+    let mut lexer = Lexer {
+        source,
+        rest: source,
+        tokens: Vec::new(),
+    };
+    // the name after `macro` is bound
+    let mut macro_name = false;
+
+    while let Some(c) = lexer.rest.chars().next() {
+        if c.is_whitespace() {
+            lexer.skip(c.len_utf8());
+            continue;
+        }
+
+        let word_len = lexer
+            .rest
+            .find(|x: char| !x.is_alphanumeric() && x != '_')
+            .unwrap_or(lexer.rest.len());
+        let kind = match c {
+            '#' => lexer.push(line_len(lexer.rest), TokenKind::Comment),
+            '[' | ']' | '{' | '}' => lexer.push(1, TokenKind::Bracket),
+            '"' => match lexer.rest[1..].find(['"', '\n']) {
+                Some(end) if lexer.rest[1 + end..].starts_with('"') => {
+                    lexer.push(end + 2, TokenKind::String)
+                }
+                _ => lexer.push(line_len(lexer.rest), TokenKind::Invalid),
+            },
+            _ if matches!(&lexer.rest[..word_len], "macro" | "include") => {
+                lexer.push(word_len, TokenKind::Keyword)
+            }
+            _ => match term::<()>(lexer.rest) {
+                Ok((rest, term)) => {
+                    let len = lexer.rest.len() - rest.len();
+                    let kind = match term {
+                        Term::Int(_) => TokenKind::Number,
+                        Term::Put { .. } if macro_name => TokenKind::Binding,
+                        Term::Put { .. } => TokenKind::Identifier,
+                        Term::Bind { .. } => TokenKind::Binding,
+                        term => term
+                            .keyword()
+                            .map_or(TokenKind::Invalid, TokenKind::of_keyword),
+                    };
+                    lexer.push(len, kind)
+                }
+                Err(_) => {
+                    let len = lexer
+                        .rest
+                        .find(|x: char| x.is_whitespace() || "#[]{}".contains(x))
+                        .unwrap_or(lexer.rest.len())
+                        .max(c.len_utf8());
+                    lexer.push(len, TokenKind::Invalid)
+                }
+            },
+        };
+
+        if kind != TokenKind::Comment {
+            macro_name = kind == TokenKind::Keyword
+                && lexer
+                    .tokens
+                    .last()
+                    .is_some_and(|x| &source[x.span.start..x.span.end] == "macro");
+        }
+    }
+
+    lexer.tokens
+}
+
+struct Lexer<'s> {
+    source: &'s str,
+    rest: &'s str,
+    tokens: Vec<Token>,
+}
+
+impl Lexer<'_> {
+    fn push(&mut self, len: usize, kind: TokenKind) -> TokenKind {
+        let start = self.source.len() - self.rest.len();
+        self.tokens.push(Token {
+            kind,
+            span: Span {
+                start,
+                end: start + len,
+            },
+        });
+        self.rest = &self.rest[len..];
+
+        kind
+    }
+
+    fn skip(&mut self, len: usize) {
+        self.rest = &self.rest[len..];
+    }
+}
+
+fn line_len(text: &str) -> usize {
+    text.find(['\n', '\0']).unwrap_or(text.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{common::Node, parser::parse_nodes, KEYWORD_TERMS};
+
+    fn kinds(source: &str) -> Vec<(&str, TokenKind)> {
+        tokenize(source)
+            .into_iter()
+            .map(|x| (&source[x.span.start..x.span.end], x.kind))
+            .collect()
+    }
+
+    #[test]
+    fn classes() {
+        use TokenKind::*;
+
+        assert_eq!(
+            vec![
+                ("-1", Number),
+                (":x", Binding),
+                ("x", Identifier),
+                ("2dup", Keyword),
+                ("+", Operator),
+                ("f", Identifier),
+                ("!", Operator),
+                ("# sum", Comment),
+                ("[", Bracket),
+                ("m::y", Identifier),
+                ("-rot", Keyword),
+                ("]", Bracket),
+                ("{", Bracket),
+                ("1", Number),
+                ("}", Bracket),
+                ("@=", Operator),
+            ],
+            kinds("-1 :x x 2dup+ f! # sum\n[m::y -rot]{ 1 }@=")
+        );
+        assert_eq!(
+            vec![
+                ("include", Keyword),
+                ("\"a.plc\"", String),
+                ("macro", Keyword),
+                ("# c", Comment),
+                ("sq", Binding),
+                ("[", Bracket),
+                ("dup", Keyword),
+                ("]", Bracket),
+                ("sq", Identifier),
+            ],
+            kinds("include \"a.plc\" macro # c\n sq [dup] sq")
+        );
+    }
+
+    #[test]
+    fn invalid_text() {
+        use TokenKind::*;
+
+        assert_eq!(
+            vec![
+                ("1", Number),
+                ("[", Bracket),
+                ("$x", Invalid),
+                ("99999999999", Invalid),
+                ("\"a", Invalid),
+            ],
+            kinds("1 [ $x 99999999999 \"a\n")
+        );
+        assert_eq!(Vec::<(&str, TokenKind)>::new(), kinds(" \n\t"));
+    }
+
+    #[test]
+    fn keywords() {
+        for term in KEYWORD_TERMS {
+            let keyword = term.keyword().unwrap();
+            assert_eq!(
+                vec![(keyword, TokenKind::of_keyword(keyword))],
+                kinds(keyword)
+            );
+        }
+    }
+
+    /// Every term the parser finds in the examples is a token of the same span.
+    #[test]
+    fn examples_agree_with_parser() {
+        fn leaves(nodes: &[Node], spans: &mut Vec<Span>) {
+            for node in nodes {
+                match node.term {
+                    Term::List { .. } | Term::Macro { .. } => leaves(&node.children, spans),
+                    Term::Include { .. } | Term::Array { .. } => {}
+                    _ => spans.push(node.span),
+                }
+            }
+        }
+
+        let examples = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../examples");
+        for entry in std::fs::read_dir(examples).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|x| x != "plc") {
+                continue;
+            }
+
+            let source = std::fs::read_to_string(&path).unwrap();
+            let tokens = tokenize(&source);
+            assert!(
+                tokens.iter().all(|x| x.kind != TokenKind::Invalid),
+                "{:?}",
+                path
+            );
+
+            let mut spans = Vec::new();
+            leaves(&parse_nodes(&source).unwrap(), &mut spans);
+            for span in spans {
+                assert!(
+                    tokens.iter().any(|x| x.span == span),
+                    "{:?} {:?}",
+                    path,
+                    span
+                );
+            }
+        }
+    }
+}
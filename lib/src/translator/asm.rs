@@ -5,6 +5,7 @@ use x64asm::{
 
 pub struct Asm {
     pub rodata: Vec<Instruction>,
+    pub data: Vec<Instruction>,
     pub bss: Vec<Instruction>,
     pub text: Vec<Instruction>,
     pub text_tail: Vec<Instruction>,
@@ -14,6 +15,7 @@ impl Asm {
     pub fn empty() -> Asm {
         Asm {
             rodata: vec![],
+            data: vec![],
             bss: vec![],
             text: vec![],
             text_tail: vec![],
@@ -22,12 +24,14 @@ impl Asm {
 
     pub fn new(
         rodata: Vec<Instruction>,
+        data: Vec<Instruction>,
         bss: Vec<Instruction>,
         text: Vec<Instruction>,
         text_tail: Vec<Instruction>,
     ) -> Asm {
         Asm {
             rodata,
+            data,
             bss,
             text,
             text_tail,
@@ -35,46 +39,55 @@ impl Asm {
     }
 
     pub fn append(self, asm: Asm) -> Asm {
-        let mut data = self.rodata;
+        let mut rodata = self.rodata;
+        let mut data = self.data;
         let mut bss = self.bss;
         let mut text = self.text;
         let mut text_tail = self.text_tail;
 
-        data.extend(asm.rodata);
+        rodata.extend(asm.rodata);
+        data.extend(asm.data);
         bss.extend(asm.bss);
         text.extend(asm.text);
         text_tail.extend(asm.text_tail);
 
-        Self::new(data, bss, text, text_tail)
+        Self::new(rodata, data, bss, text, text_tail)
     }
 
     pub fn rodata(self, rodata: impl IntoIterator<Item = Instruction>) -> Asm {
         let mut old_rodata = self.rodata;
         old_rodata.extend(rodata);
-        Self::new(old_rodata, self.bss, self.text, self.text_tail)
+        Self::new(old_rodata, self.data, self.bss, self.text, self.text_tail)
+    }
+
+    pub fn data(self, data: impl IntoIterator<Item = Instruction>) -> Asm {
+        let mut old_data = self.data;
+        old_data.extend(data);
+        Self::new(self.rodata, old_data, self.bss, self.text, self.text_tail)
     }
 
     pub fn bss(self, bss: impl IntoIterator<Item = Instruction>) -> Asm {
         let mut old_bss = self.bss;
         old_bss.extend(bss);
-        Self::new(self.rodata, old_bss, self.text, self.text_tail)
+        Self::new(self.rodata, self.data, old_bss, self.text, self.text_tail)
     }
 
     pub fn text(self, text: impl IntoIterator<Item = Instruction>) -> Asm {
         let mut old_text = self.text;
         old_text.extend(text);
-        Self::new(self.rodata, self.bss, old_text, self.text_tail)
+        Self::new(self.rodata, self.data, self.bss, old_text, self.text_tail)
     }
 
     pub fn text_tail(self, text_tail: impl IntoIterator<Item = Instruction>) -> Asm {
         let mut old_text_tail = self.text_tail;
         old_text_tail.extend(text_tail);
-        Self::new(self.rodata, self.bss, self.text, old_text_tail)
+        Self::new(self.rodata, self.data, self.bss, self.text, old_text_tail)
     }
 
     pub fn into_assembly(self) -> String {
         self.rodata
             .into_iter()
+            .chain(self.data)
             .chain(self.bss)
             .chain(self.text)
             .chain(self.text_tail)
@@ -0,0 +1,74 @@
+use x64asm::{indirect_register, macros::*};
+
+use super::{asm::Asm, stdlib::STD_CALL_FN_LABEL, util::LabelGenerator, OP_SIZE_BYTES};
+
+/// `cond [then] [else] ifte`: executes only one of the branches depending on the condition.
+///
+/// If both branches are known at compile time they're inlined, otherwise the selected
+/// quotation is called.
+pub fn ifte(branches: Option<(Asm, Asm)>, label_generator: &mut LabelGenerator) -> Asm {
+    match branches {
+        Some((then_branch, else_branch)) => {
+            let else_label = label_generator.get_label();
+            let end_label = label_generator.get_label();
+
+            pop_condition()
+                .text([i!(Je, oplabel!(else_label))])
+                .append(then_branch)
+                .text([
+                    i!(Jmp, oplabel!(end_label)),
+                    i!(label!(else_label.as_str())),
+                ])
+                .append(else_branch)
+                .text([i!(label!(end_label.as_str()))])
+        }
+        None => Asm::empty().text([
+            // else
+            i!(Mov, reg!(Eax), indirect_register!(Ebx)),
+            // then
+            i!(Mov, reg!(Ecx), opexpr!(format!("[EBX+{OP_SIZE_BYTES}]"))),
+            // condition
+            i!(
+                Mov,
+                reg!(Edx),
+                opexpr!(format!("[EBX+{}]", OP_SIZE_BYTES * 2))
+            ),
+            i!(Add, reg!(Ebx), Op::Literal(OP_SIZE_BYTES * 3)),
+            i!(Cmp, reg!(Edx), Op::Literal(0)),
+            i!(Cmovne, reg!(Eax), reg!(Ecx)),
+            i!(Call, oplabel!(STD_CALL_FN_LABEL)),
+        ]),
+    }
+}
+
+/// `cond [then] when`: executes the branch only if the condition is true.
+pub fn when(branch: Option<Asm>, label_generator: &mut LabelGenerator) -> Asm {
+    let end_label = label_generator.get_label();
+
+    match branch {
+        Some(branch) => pop_condition()
+            .text([i!(Je, oplabel!(end_label))])
+            .append(branch)
+            .text([i!(label!(end_label.as_str()))]),
+        None => Asm::empty().text([
+            // then
+            i!(Mov, reg!(Eax), indirect_register!(Ebx)),
+            // condition
+            i!(Mov, reg!(Edx), opexpr!(format!("[EBX+{OP_SIZE_BYTES}]"))),
+            i!(Add, reg!(Ebx), Op::Literal(OP_SIZE_BYTES * 2)),
+            i!(Cmp, reg!(Edx), Op::Literal(0)),
+            i!(Je, oplabel!(end_label)),
+            i!(Call, oplabel!(STD_CALL_FN_LABEL)),
+            i!(label!(end_label.as_str())),
+        ]),
+    }
+}
+
+/// Pops the condition from the operand stack and compares it with zero.
+fn pop_condition() -> Asm {
+    Asm::empty().text([
+        i!(Mov, reg!(Eax), indirect_register!(Ebx)),
+        i!(Add, reg!(Ebx), Op::Literal(OP_SIZE_BYTES)),
+        i!(Cmp, reg!(Eax), Op::Literal(0)),
+    ])
+}
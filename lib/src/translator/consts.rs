@@ -6,4 +6,9 @@ pub const OP_STACK_BASE_LABEL: &str = "$op_stack_base";
 pub const OP_SIZE: Operand = Operand::Dword;
 pub const OP_SIZE_BYTES: i64 = 8;
 pub const OP_STACK_SIZE: i64 = OP_SIZE_BYTES * 1024;
+pub const HEAP_SIZE: i64 = OP_SIZE_BYTES * 1024 * 128;
+/// The cells of a closure made by `curry` or `compose` on the heap, without the header cell.
+pub const CLOSURE_CELLS: i64 = 3;
 pub const DWORD_ZERO_LABEL: &str = "$zero";
+/// Deeper literal `take`s are translated to a runtime loop.
+pub const MAX_STRAIGHT_LINE_TAKE_DEPTH: i32 = 16;
@@ -0,0 +1,132 @@
+use x64asm::{indirect_register, macros::*};
+
+use super::{asm::Asm, stdlib::STD_CALL_FN_LABEL, util::LabelGenerator, OP_SIZE_BYTES};
+
+/// `n [body] times`: executes the body `n` times.
+///
+/// The counter lives on the native stack, so the body is free to use the operand stack.
+/// If the body is known at compile time it's inlined, otherwise the quotation is taken
+/// from the top of the operand stack and called on each iteration.
+pub fn times(body: Option<Asm>, label_generator: &mut LabelGenerator) -> Asm {
+    let start_label = label_generator.get_label();
+    let end_label = label_generator.get_label();
+    let (setup, body, frame_size) = match body {
+        Some(body) => (Asm::empty(), body, OP_SIZE_BYTES),
+        None => (
+            pop_quotation(),
+            call_quotation(OP_SIZE_BYTES),
+            OP_SIZE_BYTES * 2,
+        ),
+    };
+
+    setup
+        .text([
+            // counter
+            i!(Mov, reg!(Ecx), indirect_register!(Ebx)),
+            i!(Add, reg!(Ebx), Op::Literal(OP_SIZE_BYTES)),
+            i!(Push, reg!(Rcx)),
+            i!(label!(start_label.as_str())),
+            i!(Cmp, opexpr!("dword [RSP]"), Op::Literal(0)),
+            i!(Jle, oplabel!(end_label)),
+            i!(Sub, opexpr!("dword [RSP]"), Op::Literal(1)),
+        ])
+        .append(body)
+        .text([
+            i!(Jmp, oplabel!(start_label)),
+            i!(label!(end_label.as_str())),
+            i!(Add, reg!(Rsp), Op::Literal(frame_size)),
+        ])
+}
+
+/// `from to [body] each`: executes the body for each number in `from..to`,
+/// the current number is put on the top of the operand stack before the body.
+pub fn each(body: Option<Asm>, label_generator: &mut LabelGenerator) -> Asm {
+    let start_label = label_generator.get_label();
+    let end_label = label_generator.get_label();
+    let (setup, body, frame_size) = match body {
+        Some(body) => (Asm::empty(), body, OP_SIZE_BYTES * 2),
+        None => (
+            pop_quotation(),
+            call_quotation(OP_SIZE_BYTES * 2),
+            OP_SIZE_BYTES * 3,
+        ),
+    };
+
+    setup
+        .text([
+            // upper bound
+            i!(Mov, reg!(Ecx), indirect_register!(Ebx)),
+            // counter
+            i!(Mov, reg!(Eax), opexpr!(format!("[EBX+{OP_SIZE_BYTES}]"))),
+            i!(Add, reg!(Ebx), Op::Literal(OP_SIZE_BYTES * 2)),
+            i!(Push, reg!(Rcx)),
+            i!(Push, reg!(Rax)),
+            i!(label!(start_label.as_str())),
+            i!(Mov, reg!(Eax), opexpr!("[RSP]")),
+            i!(Cmp, reg!(Eax), opexpr!(format!("[RSP+{OP_SIZE_BYTES}]"))),
+            i!(Jge, oplabel!(end_label)),
+            i!(Add, opexpr!("dword [RSP]"), Op::Literal(1)),
+            i!(Sub, reg!(Ebx), Op::Literal(OP_SIZE_BYTES)),
+            i!(Mov, indirect_register!(Ebx), reg!(Eax)),
+        ])
+        .append(body)
+        .text([
+            i!(Jmp, oplabel!(start_label)),
+            i!(label!(end_label.as_str())),
+            i!(Add, reg!(Rsp), Op::Literal(frame_size)),
+        ])
+}
+
+/// `[cond] [body] while`: executes the body while the condition leaves a non-zero value
+/// on the top of the operand stack.
+pub fn while_loop(cond_and_body: Option<(Asm, Asm)>, label_generator: &mut LabelGenerator) -> Asm {
+    let start_label = label_generator.get_label();
+    let end_label = label_generator.get_label();
+    let (setup, cond, body, frame_size) = match cond_and_body {
+        Some((cond, body)) => (Asm::empty(), cond, body, 0),
+        None => (
+            // the body is on the top, the condition is under it
+            pop_quotation().append(pop_quotation()),
+            call_quotation(0),
+            call_quotation(OP_SIZE_BYTES),
+            OP_SIZE_BYTES * 2,
+        ),
+    };
+
+    let end = Asm::empty().text([i!(label!(end_label.as_str()))]);
+    let end = if frame_size > 0 {
+        end.text([i!(Add, reg!(Rsp), Op::Literal(frame_size))])
+    } else {
+        end
+    };
+
+    setup
+        .text([i!(label!(start_label.as_str()))])
+        .append(cond)
+        .text([
+            i!(Mov, reg!(Eax), indirect_register!(Ebx)),
+            i!(Add, reg!(Ebx), Op::Literal(OP_SIZE_BYTES)),
+            i!(Cmp, reg!(Eax), Op::Literal(0)),
+            i!(Je, oplabel!(end_label)),
+        ])
+        .append(body)
+        .text([i!(Jmp, oplabel!(start_label))])
+        .append(end)
+}
+
+/// Moves a quotation from the top of the operand stack to the native stack.
+fn pop_quotation() -> Asm {
+    Asm::empty().text([
+        i!(Mov, reg!(Eax), indirect_register!(Ebx)),
+        i!(Add, reg!(Ebx), Op::Literal(OP_SIZE_BYTES)),
+        i!(Push, reg!(Rax)),
+    ])
+}
+
+/// Calls a quotation saved on the native stack at the offset.
+fn call_quotation(offset: i64) -> Asm {
+    Asm::empty().text([
+        i!(Mov, reg!(Eax), opexpr!(format!("[RSP+{offset}]"))),
+        i!(Call, oplabel!(STD_CALL_FN_LABEL)),
+    ])
+}
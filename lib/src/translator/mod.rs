@@ -1,32 +1,118 @@
 mod asm;
-mod consts;
+mod branches;
+pub(crate) mod consts;
+mod loops;
+mod options;
 mod stdlib;
 mod util;
 
 pub use {
     asm::Asm,
+    options::TranslationOptions,
     stdlib::{make_std_lib, STD_PRINT_FN_LABEL},
     util::LabelGenerator,
 };
 
-use crate::common::{Ast, Term};
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+
+use crate::{
+    common::{Ast, Term},
+    err::CompilerError,
+};
 use consts::*;
-use stdlib::{STD_EXIT_FN_LABEL, STD_SCAN_FN_LABEL};
-use x64asm::{indirect_register, macros::*};
+use stdlib::{
+    STD_ALLOC_FN_LABEL, STD_APPLY_FN_LABEL, STD_CALL_FN_LABEL, STD_COMPOSE_FN_LABEL,
+    STD_CURRY_FN_LABEL, STD_EXIT_FN_LABEL, STD_INDEX_ERROR_LABEL, STD_SCAN_FN_LABEL,
+    STD_STRICT_SCAN_FN_LABEL, STD_TRY_SCAN_FN_LABEL,
+};
+use util::binding_label;
+use x64asm::{indirect_register, macros::*, Instruction};
 
-pub fn translate(ast: &Ast) -> Asm {
+/// Translates a program loaded by the loader: its includes, macros and `const-eval`s
+/// must be resolved, e.g. a program straight from the parser isn't accepted.
+pub fn translate(ast: &Ast, options: &TranslationOptions) -> Result<Asm> {
     let mut label_generator = LabelGenerator::default();
     let asm = prelude();
 
-    let asm = ast.terms.iter().fold(asm, |asm, term| {
-        asm.append(translate_term(term, &mut label_generator))
-    });
+    let asm = asm.append(translate_terms(&ast.terms, options, &mut label_generator)?);
 
-    asm.append(epilogue())
+    Ok(compact_lines(
+        asm.append(bindings(&ast.terms)).append(epilogue()),
+    ))
+}
+
+/// Drops the `%line` directives which don't change the line of any instruction: those
+/// followed by another one, e.g. for a macro definition, and the repeated ones.
+fn compact_lines(asm: Asm) -> Asm {
+    let text_len = asm.text.len();
+    let code: Vec<_> = asm.text.into_iter().chain(asm.text_tail).collect();
+
+    let mut current = None;
+    let mut kept = Vec::with_capacity(code.len());
+    for (i, instruction) in code.iter().enumerate() {
+        let keep = match line_directive(instruction) {
+            Some(_) if code.get(i + 1).and_then(line_directive).is_some() => false,
+            Some(line) if current == Some(line) => false,
+            Some(line) => {
+                current = Some(line);
+                true
+            }
+            None => true,
+        };
+        kept.push(keep);
+    }
+
+    let (mut text, mut text_tail) = (Vec::new(), Vec::new());
+    for (i, instruction) in code.iter().enumerate().filter(|(i, _)| kept[*i]) {
+        if i < text_len {
+            text.push(instruction.clone());
+        } else {
+            text_tail.push(instruction.clone());
+        }
+    }
+
+    Asm::new(asm.rodata, asm.data, asm.bss, text, text_tail)
+}
+
+fn line_directive(instruction: &Instruction) -> Option<&str> {
+    match &instruction.mnemonic {
+        instruction::Mnemonic::Expression(x) if x == "%line" => match instruction.operands[..] {
+            [Op::Expression(ref line)] => Some(line),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Reserves a cell for every bound name; a name may be bound more than once.
+fn bindings(terms: &[Term]) -> Asm {
+    fn collect<'a>(terms: &'a [Term], names: &mut BTreeSet<&'a str>) {
+        for term in terms {
+            match term {
+                Term::Bind { identifier } => {
+                    names.insert(identifier);
+                }
+                Term::List { terms } => collect(terms, names),
+                _ => {}
+            }
+        }
+    }
+
+    let mut names = BTreeSet::new();
+    collect(terms, &mut names);
+
+    Asm::empty().bss(
+        names
+            .into_iter()
+            .map(|name| i!(label!(&binding_label(name)), opexpr!(format!("resq 1")))),
+    )
 }
 
 fn prelude() -> Asm {
     let rodata = vec![i!(section!(Rodata))];
+    let data = vec![i!(section!(Data))];
     let bss = vec![
         i!(section!(Bss)),
         i!(label!(OP_STACK_BASE_LABEL), opexpr!(format!("resd 1"))),
@@ -39,7 +125,15 @@ fn prelude() -> Asm {
     let text = vec![
         i!(Extern, oplabel!(STD_PRINT_FN_LABEL.to_string())),
         i!(Extern, oplabel!(STD_SCAN_FN_LABEL.to_string())),
+        i!(Extern, oplabel!(STD_STRICT_SCAN_FN_LABEL.to_string())),
+        i!(Extern, oplabel!(STD_TRY_SCAN_FN_LABEL.to_string())),
         i!(Extern, oplabel!(STD_EXIT_FN_LABEL.to_string())),
+        i!(Extern, oplabel!(STD_ALLOC_FN_LABEL.to_string())),
+        i!(Extern, oplabel!(STD_INDEX_ERROR_LABEL.to_string())),
+        i!(Extern, oplabel!(STD_APPLY_FN_LABEL.to_string())),
+        i!(Extern, oplabel!(STD_CALL_FN_LABEL.to_string())),
+        i!(Extern, oplabel!(STD_CURRY_FN_LABEL.to_string())),
+        i!(Extern, oplabel!(STD_COMPOSE_FN_LABEL.to_string())),
         i!(section!(Text)),
         i!(Global, oplabel!(START_LABEL)),
         i!(label!(START_LABEL)),
@@ -48,7 +142,7 @@ fn prelude() -> Asm {
         i!(Mov, opexpr!(format!("[{OP_STACK_BASE_LABEL}]")), reg!(Ebx)),
     ];
 
-    Asm::new(rodata, bss, text, vec![])
+    Asm::new(rodata, data, bss, text, vec![])
 }
 
 fn epilogue() -> Asm {
@@ -58,8 +152,82 @@ fn epilogue() -> Asm {
     ])
 }
 
-fn translate_term(term: &Term, label_generator: &mut LabelGenerator) -> Asm {
-    match term {
+/// Translates a sequence of terms, specialising the known combinations of them,
+/// e.g. a loop or a branch with list literals as bodies is translated with the bodies inlined.
+fn translate_terms(
+    terms: &[Term],
+    options: &TranslationOptions,
+    label_generator: &mut LabelGenerator,
+) -> Result<Asm> {
+    let mut asm = Asm::empty();
+    let mut rest = terms;
+
+    while let [term, ..] = rest {
+        if let Term::Line { .. } = term {
+            asm = asm.append(translate_term(term, options, label_generator)?);
+            rest = &rest[1..];
+            continue;
+        }
+
+        // the lines of the terms of a combination are skipped, it gets the line of its first term
+        let combination: Vec<_> = rest.iter().filter(|x| !is_line(x)).take(3).collect();
+        let (terms_asm, consumed) = match combination.as_slice() {
+            [Term::List { terms: cond }, Term::List { terms: body }, Term::While, ..] => {
+                let cond = translate_terms(cond, options, label_generator)?;
+                let body = translate_terms(body, options, label_generator)?;
+                (loops::while_loop(Some((cond, body)), label_generator), 3)
+            }
+            [Term::List { terms: then_branch }, Term::List { terms: else_branch }, Term::Ifte, ..] =>
+            {
+                let then_branch = translate_terms(then_branch, options, label_generator)?;
+                let else_branch = translate_terms(else_branch, options, label_generator)?;
+                (
+                    branches::ifte(Some((then_branch, else_branch)), label_generator),
+                    3,
+                )
+            }
+            [Term::List { terms: branch }, Term::When, ..] => {
+                let branch = translate_terms(branch, options, label_generator)?;
+                (branches::when(Some(branch), label_generator), 2)
+            }
+            [Term::Int(depth), Term::Take, ..]
+                if (0..=MAX_STRAIGHT_LINE_TAKE_DEPTH).contains(depth) =>
+            {
+                (literal_take(*depth), 2)
+            }
+            [Term::List { terms: body }, Term::Times, ..] => {
+                let body = translate_terms(body, options, label_generator)?;
+                (loops::times(Some(body), label_generator), 2)
+            }
+            [Term::List { terms: body }, Term::Each, ..] => {
+                let body = translate_terms(body, options, label_generator)?;
+                (loops::each(Some(body), label_generator), 2)
+            }
+            [term, ..] => (translate_term(term, options, label_generator)?, 1),
+            [] => unreachable!(),
+        };
+
+        asm = asm.append(terms_asm);
+        let mut skipped = 0;
+        for _ in 0..consumed {
+            skipped += rest[skipped..].iter().take_while(|x| is_line(x)).count() + 1;
+        }
+        rest = &rest[skipped..];
+    }
+
+    Ok(asm)
+}
+
+fn is_line(term: &Term) -> bool {
+    matches!(term, Term::Line { .. })
+}
+
+fn translate_term(
+    term: &Term,
+    options: &TranslationOptions,
+    label_generator: &mut LabelGenerator,
+) -> Result<Asm> {
+    let asm = match term {
         Term::Int(number) => Asm::empty().text([
             i!(Sub, reg!(Ebx), Op::Literal(OP_SIZE_BYTES)),
             i!(
@@ -85,14 +253,16 @@ fn translate_term(term: &Term, label_generator: &mut LabelGenerator) -> Asm {
             i!(Mul, opexpr!(format!("dword[EBX]"))),
             i!(Mov, indirect_register!(Ebx), reg!(Eax)),
         ]),
+        // signed division on sign-extended 64-bit operands
         Term::Div => Asm::empty().text([
-            i!(Mov, reg!(Edi), indirect_register!(Ebx)),
+            i!(Mov, reg!(Eax), indirect_register!(Ebx)),
             i!(Add, reg!(Ebx), Op::Literal(OP_SIZE_BYTES)),
-            i!(Xor, reg!(Rdx), reg!(Rdx)),
-            i!(Mov, reg!(Rax), indirect_register!(Ebx)),
+            i!(Cltq),
+            i!(Mov, reg!(Rdi), reg!(Rax)),
+            i!(Mov, reg!(Eax), indirect_register!(Ebx)),
             i!(Cltq),
             i!(Cqto),
-            i!(Div, reg!(Edi)),
+            i!(Idiv, reg!(Rdi)),
             i!(Mov, indirect_register!(Ebx), reg!(Eax)),
         ]),
         Term::Print => Asm::empty().text([i!(Call, oplabel!(STD_PRINT_FN_LABEL))]),
@@ -137,6 +307,79 @@ fn translate_term(term: &Term, label_generator: &mut LabelGenerator) -> Asm {
                 i!(label!(no_exch_label.as_str())),
             ])
         }
+        Term::Swap => Asm::empty().text([
+            i!(Mov, reg!(Rax), indirect_register!(Ebx)),
+            i!(Mov, reg!(Rcx), opexpr!(format!("[EBX+{OP_SIZE_BYTES}]"))),
+            i!(Mov, indirect_register!(Ebx), reg!(Rcx)),
+            i!(Mov, opexpr!(format!("[EBX+{OP_SIZE_BYTES}]")), reg!(Rax)),
+        ]),
+        Term::Over => Asm::empty().text([
+            i!(Mov, reg!(Rax), opexpr!(format!("[EBX+{OP_SIZE_BYTES}]"))),
+            i!(Sub, reg!(Ebx), Op::Literal(OP_SIZE_BYTES)),
+            i!(Mov, indirect_register!(Ebx), reg!(Rax)),
+        ]),
+        Term::Rot => Asm::empty().text([
+            i!(
+                Mov,
+                reg!(Rax),
+                opexpr!(format!("[EBX+{}]", OP_SIZE_BYTES * 2))
+            ),
+            i!(Mov, reg!(Rcx), opexpr!(format!("[EBX+{OP_SIZE_BYTES}]"))),
+            i!(Mov, reg!(Rdx), indirect_register!(Ebx)),
+            i!(
+                Mov,
+                opexpr!(format!("[EBX+{}]", OP_SIZE_BYTES * 2)),
+                reg!(Rcx)
+            ),
+            i!(Mov, opexpr!(format!("[EBX+{OP_SIZE_BYTES}]")), reg!(Rdx)),
+            i!(Mov, indirect_register!(Ebx), reg!(Rax)),
+        ]),
+        Term::MinusRot => Asm::empty().text([
+            i!(
+                Mov,
+                reg!(Rax),
+                opexpr!(format!("[EBX+{}]", OP_SIZE_BYTES * 2))
+            ),
+            i!(Mov, reg!(Rcx), opexpr!(format!("[EBX+{OP_SIZE_BYTES}]"))),
+            i!(Mov, reg!(Rdx), indirect_register!(Ebx)),
+            i!(
+                Mov,
+                opexpr!(format!("[EBX+{}]", OP_SIZE_BYTES * 2)),
+                reg!(Rdx)
+            ),
+            i!(Mov, opexpr!(format!("[EBX+{OP_SIZE_BYTES}]")), reg!(Rax)),
+            i!(Mov, indirect_register!(Ebx), reg!(Rcx)),
+        ]),
+        Term::Nip => Asm::empty().text([
+            i!(Mov, reg!(Rax), indirect_register!(Ebx)),
+            i!(Add, reg!(Ebx), Op::Literal(OP_SIZE_BYTES)),
+            i!(Mov, indirect_register!(Ebx), reg!(Rax)),
+        ]),
+        Term::Tuck => Asm::empty().text([
+            i!(Mov, reg!(Rax), indirect_register!(Ebx)),
+            i!(Mov, reg!(Rcx), opexpr!(format!("[EBX+{OP_SIZE_BYTES}]"))),
+            i!(Mov, opexpr!(format!("[EBX+{OP_SIZE_BYTES}]")), reg!(Rax)),
+            i!(Mov, indirect_register!(Ebx), reg!(Rcx)),
+            i!(Sub, reg!(Ebx), Op::Literal(OP_SIZE_BYTES)),
+            i!(Mov, indirect_register!(Ebx), reg!(Rax)),
+        ]),
+        Term::Pick => Asm::empty().text([
+            i!(Mov, reg!(Ecx), indirect_register!(Ebx)),
+            i!(
+                Mov,
+                reg!(Rax),
+                opexpr!(format!("[EBX+ECX*{OP_SIZE_BYTES}+{OP_SIZE_BYTES}]"))
+            ),
+            i!(Mov, indirect_register!(Ebx), reg!(Rax)),
+        ]),
+        Term::TwoDup => Asm::empty().text([
+            i!(Mov, reg!(Rax), opexpr!(format!("[EBX+{OP_SIZE_BYTES}]"))),
+            i!(Mov, reg!(Rcx), indirect_register!(Ebx)),
+            i!(Sub, reg!(Ebx), Op::Literal(OP_SIZE_BYTES * 2)),
+            i!(Mov, opexpr!(format!("[EBX+{OP_SIZE_BYTES}]")), reg!(Rax)),
+            i!(Mov, indirect_register!(Ebx), reg!(Rcx)),
+        ]),
+        Term::TwoDrop => Asm::empty().text([i!(Add, reg!(Ebx), Op::Literal(OP_SIZE_BYTES * 2))]),
         Term::List { terms } => {
             let label = label_generator.get_label();
 
@@ -149,20 +392,24 @@ fn translate_term(term: &Term, label_generator: &mut LabelGenerator) -> Asm {
                 ),
             ]);
 
-            let inner_asm = Asm::empty().text([i!(label!(label.as_str()))]);
-            let inner_asm = terms.iter().fold(inner_asm, |asm, term| {
-                asm.append(translate_term(term, label_generator))
-            });
-            let inner_asm = inner_asm.text([i!(Ret)]);
-            let inner_asm = Asm::new(inner_asm.rodata, inner_asm.bss, vec![], inner_asm.text_tail)
-                .text_tail(inner_asm.text);
+            let inner_asm = Asm::empty()
+                .text([i!(label!(label.as_str()))])
+                .append(translate_terms(terms, options, label_generator)?)
+                .text([i!(Ret)]);
+            let inner_asm = Asm::new(
+                inner_asm.rodata,
+                inner_asm.data,
+                inner_asm.bss,
+                vec![],
+                inner_asm.text_tail,
+            )
+            .text_tail(inner_asm.text);
 
             list_asm.append(inner_asm)
         }
-        Term::Apply => Asm::empty().text([
-            i!(Add, reg!(Ebx), Op::Literal(OP_SIZE_BYTES)),
-            i!(Call, opexpr!(format!("[EBX-{OP_SIZE_BYTES}]"))),
-        ]),
+        Term::Apply => Asm::empty().text([i!(Call, oplabel!(STD_APPLY_FN_LABEL))]),
+        Term::Curry => Asm::empty().text([i!(Call, oplabel!(STD_CURRY_FN_LABEL))]),
+        Term::Compose => Asm::empty().text([i!(Call, oplabel!(STD_COMPOSE_FN_LABEL))]),
         Term::Bool => Asm::empty().text([
             i!(Cmp, indirect_register!(Ebx), opexpr!("dword 0")),
             i!(Mov, reg!(Eax), Op::Literal(1)),
@@ -249,18 +496,193 @@ fn translate_term(term: &Term, label_generator: &mut LabelGenerator) -> Asm {
                 i!(label!(on_else.as_str())),
             ])
         }
-        Term::Bind { identifier: name } => Asm::empty()
-            .bss([i!(label!(name), opexpr!(format!("resq 1")))])
-            .text([
+        Term::Bind { identifier } => {
+            let name = binding_label(identifier);
+            Asm::empty().text([
                 i!(Mov, reg!(Rax), indirect_register!(Ebx)),
                 i!(Add, reg!(Ebx), Op::Literal(OP_SIZE_BYTES)),
                 i!(Mov, opexpr!(format!("[{name}]")), reg!(Rax)),
+            ])
+        }
+        Term::Put { identifier } => {
+            let name = binding_label(identifier);
+            Asm::empty().text([
+                i!(Mov, reg!(Rax), opexpr!(format!("[{name}]"))),
+                i!(Sub, reg!(Ebx), Op::Literal(OP_SIZE_BYTES)),
+                i!(Mov, indirect_register!(Ebx), reg!(Rax)),
+            ])
+        }
+        Term::Array { values } => {
+            let label = label_generator.get_label();
+            let cells = values
+                .iter()
+                .map(|value| (*value as u32).to_string())
+                .collect::<Vec<_>>();
+
+            let data = if cells.is_empty() {
+                vec![i!(label!(label.as_str()))]
+            } else {
+                vec![i!(
+                    label!(label.as_str()),
+                    opexpr!(format!("dq {}", cells.join(", ")))
+                )]
+            };
+
+            Asm::empty()
+                // the length header cell, as for arrays made by `alloc`
+                .data([i!(instruction::Mnemonic::Expression(format!(
+                    "dq {}",
+                    values.len()
+                )))])
+                .data(data)
+                .text([
+                    i!(Sub, reg!(Ebx), Op::Literal(OP_SIZE_BYTES)),
+                    i!(
+                        Mov,
+                        indirect_register!(Ebx),
+                        opexpr!(format!("dword {label}"))
+                    ),
+                ])
+        }
+        // the following instructions are attributed to the line by nasm's debug info
+        Term::Line { file, line } if options.debug_info => Asm::empty().text([i!(
+            instruction::Mnemonic::Expression("%line".to_string()),
+            opexpr!(format!("{}+0 {}", line, file))
+        )]),
+        Term::Line { .. } => Asm::empty(),
+        Term::Include { .. } => return Err(unresolved("include")),
+        Term::Macro { .. } => return Err(unresolved("macro")),
+        Term::ConstEval => return Err(unresolved("const-eval")),
+        Term::Scan => {
+            let scan_fn_label = if options.strict_scan {
+                STD_STRICT_SCAN_FN_LABEL
+            } else {
+                STD_SCAN_FN_LABEL
+            };
+            Asm::empty().text([i!(Call, oplabel!(scan_fn_label))])
+        }
+        Term::TryScan => Asm::empty().text([i!(Call, oplabel!(STD_TRY_SCAN_FN_LABEL))]),
+        Term::Ifte => branches::ifte(None, label_generator),
+        Term::When => branches::when(None, label_generator),
+        Term::Times => loops::times(None, label_generator),
+        Term::While => loops::while_loop(None, label_generator),
+        Term::Each => loops::each(None, label_generator),
+        Term::Alloc => Asm::empty().text([i!(Call, oplabel!(STD_ALLOC_FN_LABEL))]),
+        Term::Load => Asm::empty()
+            .text([
+                // index
+                i!(Mov, reg!(Ecx), indirect_register!(Ebx)),
+                i!(Add, reg!(Ebx), Op::Literal(OP_SIZE_BYTES)),
+                // array
+                i!(Mov, reg!(Eax), indirect_register!(Ebx)),
+            ])
+            .append(bounds_check(options))
+            .text([
+                i!(
+                    Mov,
+                    reg!(Rax),
+                    opexpr!(format!("[EAX+ECX*{OP_SIZE_BYTES}]"))
+                ),
+                i!(Mov, indirect_register!(Ebx), reg!(Rax)),
             ]),
-        Term::Put { identifier: name } => Asm::empty().text([
-            i!(Mov, reg!(Rax), opexpr!(format!("[{name}]"))),
-            i!(Sub, reg!(Ebx), Op::Literal(OP_SIZE_BYTES)),
-            i!(Mov, indirect_register!(Ebx), reg!(Rax)),
+        Term::Store => Asm::empty()
+            .text([
+                // index
+                i!(Mov, reg!(Ecx), indirect_register!(Ebx)),
+                // array
+                i!(Mov, reg!(Eax), opexpr!(format!("[EBX+{OP_SIZE_BYTES}]"))),
+                // value
+                i!(
+                    Mov,
+                    reg!(Rdx),
+                    opexpr!(format!("[EBX+{}]", OP_SIZE_BYTES * 2))
+                ),
+                i!(Add, reg!(Ebx), Op::Literal(OP_SIZE_BYTES * 3)),
+            ])
+            .append(bounds_check(options))
+            .text([i!(
+                Mov,
+                opexpr!(format!("[EAX+ECX*{OP_SIZE_BYTES}]")),
+                reg!(Rdx)
+            )]),
+        Term::Len => Asm::empty().text([
+            i!(Mov, reg!(Eax), indirect_register!(Ebx)),
+            i!(Mov, reg!(Eax), opexpr!(format!("[EAX-{OP_SIZE_BYTES}]"))),
+            i!(Mov, indirect_register!(Ebx), reg!(Eax)),
         ]),
-        Term::Scan => Asm::empty().text([i!(Call, oplabel!(STD_SCAN_FN_LABEL))]),
+    };
+
+    Ok(asm)
+}
+
+fn unresolved(keyword: &'static str) -> anyhow::Error {
+    CompilerError::UnresolvedTerm { keyword }.into()
+}
+
+/// `N take` with a literal N: moves the N-th element to the top without a runtime loop.
+fn literal_take(depth: i32) -> Asm {
+    if depth == 0 {
+        return Asm::empty();
+    }
+
+    let depth = depth as i64;
+    let shifts = (1..=depth).rev().flat_map(|i| {
+        [
+            i!(
+                Mov,
+                reg!(Rcx),
+                opexpr!(format!("[EBX+{}]", (i - 1) * OP_SIZE_BYTES))
+            ),
+            i!(
+                Mov,
+                opexpr!(format!("[EBX+{}]", i * OP_SIZE_BYTES)),
+                reg!(Rcx)
+            ),
+        ]
+    });
+
+    Asm::empty()
+        .text([i!(
+            Mov,
+            reg!(Rax),
+            opexpr!(format!("[EBX+{}]", depth * OP_SIZE_BYTES))
+        )])
+        .text(shifts)
+        .text([i!(Mov, indirect_register!(Ebx), reg!(Rax))])
+}
+
+/// Checks that the index in ECX is within the bounds of the array pointed by EAX.
+fn bounds_check(options: &TranslationOptions) -> Asm {
+    if !options.debug_checks {
+        return Asm::empty();
+    }
+
+    Asm::empty().text([
+        i!(Cmp, reg!(Ecx), opexpr!(format!("[EAX-{OP_SIZE_BYTES}]"))),
+        i!(Jae, oplabel!(STD_INDEX_ERROR_LABEL)),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn unresolved_terms_are_rejected() {
+        let options = TranslationOptions::default();
+        for source in ["include \"a.plc\"", "[ macro m [ 1 ] ]", "[ 1 ] const-eval"] {
+            let ast = parse(source).unwrap();
+            let act = translate(&ast, &options).err();
+            assert!(
+                matches!(
+                    act.as_ref().and_then(|e| e.downcast_ref()),
+                    Some(CompilerError::UnresolvedTerm { .. })
+                ),
+                "{}",
+                source
+            );
+        }
+        assert!(translate(&parse("1 [ 2 ] !").unwrap(), &options).is_ok());
     }
 }
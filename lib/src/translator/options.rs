@@ -0,0 +1,11 @@
+/// Options affecting the generated code.
+#[derive(Clone, Debug, Default)]
+pub struct TranslationOptions {
+    /// Abort the program when `&` can't read a number instead of pushing 0.
+    pub strict_scan: bool,
+    /// Insert runtime checks, e.g. array bounds checking.
+    pub debug_checks: bool,
+    /// Map the instructions to the lines of the program with nasm `%line` directives;
+    /// the loader must be asked for line info.
+    pub debug_info: bool,
+}
@@ -2,21 +2,61 @@ use x64asm::{
     i, indirect_register,
     instruction::Section::{Bss, Text},
     macros::*,
-    section,
+    section, Instruction,
 };
 
-use super::{asm::Asm, OP_SIZE_BYTES};
+use super::{asm::Asm, CLOSURE_CELLS, HEAP_SIZE, OP_SIZE_BYTES};
 
 pub const STD_PRINT_FN_LABEL: &str = "$std_print";
 pub const STD_SCAN_FN_LABEL: &str = "$std_scan";
+pub const STD_STRICT_SCAN_FN_LABEL: &str = "$std_strict_scan";
+pub const STD_TRY_SCAN_FN_LABEL: &str = "$std_try_scan";
 pub const STD_EXIT_FN_LABEL: &str = "$str_exit";
+pub const STD_ALLOC_FN_LABEL: &str = "$std_alloc";
+pub const STD_INDEX_ERROR_LABEL: &str = "$std_index_error";
+pub const STD_APPLY_FN_LABEL: &str = "$std_apply";
+pub const STD_CALL_FN_LABEL: &str = "$std_call";
+pub const STD_CURRY_FN_LABEL: &str = "$std_curry";
+pub const STD_COMPOSE_FN_LABEL: &str = "$std_compose";
 
 const OUTPUT_TEMPLATE_LABEL: &str = "$otemplate";
 const IO_TEMPLATE_STR: &str = "%d";
 const INPUT_TEMPLATE_LABEL: &str = "$itemplate";
+const SKIP_TEMPLATE_LABEL: &str = "$stemplate";
+const SKIP_TEMPLATE_STR: &str = "%*s";
+const SCANF_INTO_TOP_LABEL: &str = "$scanf_into_top";
+const SCANF_DONE_LABEL: &str = "$scanf_done";
+const SCAN_ERROR_LABEL: &str = "$scan_error";
+const SCAN_ERROR_STR: &str = "plc: failed to read an integer from stdin";
+const ALLOC_SIZE_ERROR_LABEL: &str = "$alloc_size_error";
+const ALLOC_SIZE_ERROR_STR: &str = "plc: invalid array size";
+const OUT_OF_MEMORY_ERROR_LABEL: &str = "$out_of_memory_error";
+const OUT_OF_MEMORY_ERROR_STR: &str = "plc: out of memory";
+const INDEX_ERROR_LABEL: &str = "$index_error";
+const INDEX_ERROR_STR: &str = "plc: array index out of bounds";
+const APPLY_ERROR_LABEL: &str = "$apply_error";
+const APPLY_ERROR_STR: &str = "plc: applied value is not a quotation";
+const ABORT_LABEL: &str = "$abort";
+const ABORT_EXIT_CODE: i64 = 1;
+const STDERR_FD: i64 = 2;
+
+const HEAP_LABEL: &str = "$heap";
+const HEAP_USED_LABEL: &str = "$heap_used";
+
+/// Closures are heap objects of 3 cells: a kind and two operands.
+/// They are told apart from arrays by the header, which holds a length for arrays.
+const CLOSURE_HEADER: i64 = -1;
+/// Puts the first operand on the stack and applies the second one.
+const CURRY_KIND: i64 = 0;
+/// Applies the first operand and then the second one.
+const COMPOSE_KIND: i64 = 1;
+const MAKE_CLOSURE_LABEL: &str = "$make_closure";
+const CALL_CODE_LABEL: &str = "$call_code";
+const CALL_COMPOSITION_LABEL: &str = "$call_composition";
 
 const LIBC_PRINTF_LABEL: &str = "printf";
 const LIBC_SCANF_LABEL: &str = "scanf";
+const LIBC_DPRINTF_LABEL: &str = "dprintf";
 const LIBC_EXIT_LABEL: &str = "exit";
 
 pub fn make_std_lib() -> Asm {
@@ -35,15 +75,39 @@ pub fn make_std_lib() -> Asm {
             opstring!(IO_TEMPLATE_STR.to_string()),
             Op::Literal(0)
         ),
+        i!(
+            label!(SKIP_TEMPLATE_LABEL),
+            dd!(Db),
+            opstring!(SKIP_TEMPLATE_STR.to_string()),
+            Op::Literal(0)
+        ),
+        error_message(SCAN_ERROR_LABEL, SCAN_ERROR_STR),
+        error_message(ALLOC_SIZE_ERROR_LABEL, ALLOC_SIZE_ERROR_STR),
+        error_message(OUT_OF_MEMORY_ERROR_LABEL, OUT_OF_MEMORY_ERROR_STR),
+        error_message(INDEX_ERROR_LABEL, INDEX_ERROR_STR),
+        error_message(APPLY_ERROR_LABEL, APPLY_ERROR_STR),
+    ];
+    let bss = vec![
+        i!(section!(Bss)),
+        i!(label!(HEAP_USED_LABEL), opexpr!(format!("resq 1"))),
+        i!(label!(HEAP_LABEL), opexpr!(format!("resb {HEAP_SIZE}"))),
     ];
-    let bss = vec![i!(section!(Bss))];
     let text = vec![
         // definitions
         i!(Global, oplabel!(STD_PRINT_FN_LABEL.to_string())),
         i!(Global, oplabel!(STD_SCAN_FN_LABEL.to_string())),
+        i!(Global, oplabel!(STD_STRICT_SCAN_FN_LABEL.to_string())),
+        i!(Global, oplabel!(STD_TRY_SCAN_FN_LABEL.to_string())),
         i!(Global, oplabel!(STD_EXIT_FN_LABEL.to_string())),
+        i!(Global, oplabel!(STD_ALLOC_FN_LABEL.to_string())),
+        i!(Global, oplabel!(STD_INDEX_ERROR_LABEL.to_string())),
+        i!(Global, oplabel!(STD_APPLY_FN_LABEL.to_string())),
+        i!(Global, oplabel!(STD_CALL_FN_LABEL.to_string())),
+        i!(Global, oplabel!(STD_CURRY_FN_LABEL.to_string())),
+        i!(Global, oplabel!(STD_COMPOSE_FN_LABEL.to_string())),
         i!(Extern, oplabel!(LIBC_PRINTF_LABEL.to_string())),
         i!(Extern, oplabel!(LIBC_SCANF_LABEL.to_string())),
+        i!(Extern, oplabel!(LIBC_DPRINTF_LABEL.to_string())),
         i!(Extern, oplabel!(LIBC_EXIT_LABEL.to_string())),
         i!(section!(Text)),
         // print
@@ -59,24 +123,186 @@ pub fn make_std_lib() -> Asm {
         i!(Mov, reg!(Rsp), reg!(Rbp)),
         i!(Pop, reg!(Rbp)),
         i!(Ret),
-        // scan
+        // scan: pushes the read number, or 0 if stdin is exhausted or malformed
         i!(label!(STD_SCAN_FN_LABEL)),
+        i!(Call, oplabel!(SCANF_INTO_TOP_LABEL)),
+        i!(Ret),
+        // strict scan: aborts the program if a number can't be read
+        i!(label!(STD_STRICT_SCAN_FN_LABEL)),
+        i!(Call, oplabel!(SCANF_INTO_TOP_LABEL)),
+        i!(Cmp, reg!(Eax), Op::Literal(1)),
+        i!(Mov, reg!(Rsi), oplabel!(SCAN_ERROR_LABEL.to_string())),
+        i!(Jne, oplabel!(ABORT_LABEL)),
+        i!(Ret),
+        // try scan: pushes the read number (0 on failure) and then the success flag
+        i!(label!(STD_TRY_SCAN_FN_LABEL)),
+        i!(Call, oplabel!(SCANF_INTO_TOP_LABEL)),
+        i!(Xor, reg!(Ecx), reg!(Ecx)),
+        i!(Cmp, reg!(Eax), Op::Literal(1)),
+        i!(Sete, reg!(Cl)),
+        i!(Sub, reg!(Ebx), Op::Literal(OP_SIZE_BYTES)),
+        i!(Mov, indirect_register!(Ebx), reg!(Ecx)),
+        i!(Ret),
+        // reserves a zeroed cell on the top of the stack and scans into it,
+        // the scanf return value is left in eax; a malformed word is skipped,
+        // so the next scan doesn't fail on it again
+        i!(label!(SCANF_INTO_TOP_LABEL)),
         i!(Push, reg!(Rbp)),
         i!(Mov, reg!(Rbp), reg!(Rsp)),
         i!(And, reg!(Rsp), Op::Literal(-16)),
-        i!(Mov, reg!(Rdi), oplabel!(INPUT_TEMPLATE_LABEL.to_string())),
         i!(Sub, reg!(Ebx), Op::Literal(OP_SIZE_BYTES)),
+        i!(Mov, indirect_register!(Ebx), opexpr!("dword 0")),
+        i!(Mov, reg!(Rdi), oplabel!(INPUT_TEMPLATE_LABEL.to_string())),
         i!(Xor, reg!(Rsi), reg!(Rsi)),
         i!(Mov, reg!(Esi), reg!(Ebx)),
+        i!(Xor, reg!(Rax), reg!(Rax)),
         i!(Call, oplabel!(LIBC_SCANF_LABEL)),
+        i!(Cmp, reg!(Eax), Op::Literal(0)),
+        i!(Jne, oplabel!(SCANF_DONE_LABEL)),
+        i!(Mov, reg!(Rdi), oplabel!(SKIP_TEMPLATE_LABEL.to_string())),
+        i!(Xor, reg!(Rax), reg!(Rax)),
+        i!(Call, oplabel!(LIBC_SCANF_LABEL)),
+        i!(Xor, reg!(Eax), reg!(Eax)),
+        i!(label!(SCANF_DONE_LABEL)),
         i!(Mov, reg!(Rsp), reg!(Rbp)),
         i!(Pop, reg!(Rbp)),
         i!(Ret),
+        // alloc: replaces N on the top of the stack with an array of N zeroed cells,
+        // the length of the array is stored in the cell right before the first element
+        i!(label!(STD_ALLOC_FN_LABEL)),
+        i!(Mov, reg!(Ecx), indirect_register!(Ebx)),
+        i!(Mov, reg!(Rsi), oplabel!(ALLOC_SIZE_ERROR_LABEL.to_string())),
+        i!(Cmp, reg!(Ecx), Op::Literal(0)),
+        i!(Jl, oplabel!(ABORT_LABEL)),
+        i!(
+            Mov,
+            reg!(Rsi),
+            oplabel!(OUT_OF_MEMORY_ERROR_LABEL.to_string())
+        ),
+        // a size which can't fit the heap is rejected before the size in bytes overflows
+        i!(Cmp, reg!(Ecx), Op::Literal(HEAP_SIZE / OP_SIZE_BYTES)),
+        i!(Ja, oplabel!(ABORT_LABEL)),
+        i!(Mov, reg!(Eax), reg!(Ecx)),
+        i!(Add, reg!(Eax), Op::Literal(1)),
+        i!(Shl, reg!(Eax), Op::Literal(3)),
+        i!(Mov, reg!(Edx), opexpr!(format!("[{HEAP_USED_LABEL}]"))),
+        i!(Add, reg!(Eax), reg!(Edx)),
+        i!(Cmp, reg!(Eax), Op::Literal(HEAP_SIZE)),
+        i!(Ja, oplabel!(ABORT_LABEL)),
+        i!(Mov, opexpr!(format!("[{HEAP_USED_LABEL}]")), reg!(Eax)),
+        i!(Mov, reg!(Eax), oplabel!(HEAP_LABEL.to_string())),
+        i!(Add, reg!(Eax), reg!(Edx)),
+        i!(Mov, indirect_register!(Eax), reg!(Ecx)),
+        i!(Add, reg!(Eax), Op::Literal(OP_SIZE_BYTES)),
+        i!(Mov, indirect_register!(Ebx), reg!(Eax)),
+        i!(Ret),
+        // apply: applies a quotation from the top of the stack
+        i!(label!(STD_APPLY_FN_LABEL)),
+        i!(Mov, reg!(Eax), indirect_register!(Ebx)),
+        i!(Add, reg!(Ebx), Op::Literal(OP_SIZE_BYTES)),
+        // call: applies a quotation from eax, it's either a code address or a closure
+        i!(label!(STD_CALL_FN_LABEL)),
+        i!(Cmp, reg!(Eax), oplabel!(HEAP_LABEL.to_string())),
+        i!(Jb, oplabel!(CALL_CODE_LABEL)),
+        i!(Cmp, reg!(Eax), opexpr!(format!("{HEAP_LABEL}+{HEAP_SIZE}"))),
+        i!(Jae, oplabel!(CALL_CODE_LABEL)),
+        i!(Mov, reg!(Rsi), oplabel!(APPLY_ERROR_LABEL.to_string())),
+        i!(
+            Cmp,
+            opexpr!(format!("dword [EAX-{OP_SIZE_BYTES}]")),
+            Op::Literal(CLOSURE_HEADER)
+        ),
+        i!(Jne, oplabel!(ABORT_LABEL)),
+        i!(Cmp, opexpr!("dword [EAX]"), Op::Literal(CURRY_KIND)),
+        i!(Jne, oplabel!(CALL_COMPOSITION_LABEL)),
+        // curried value
+        i!(Mov, reg!(Rcx), opexpr!(format!("[EAX+{OP_SIZE_BYTES}]"))),
+        i!(Sub, reg!(Ebx), Op::Literal(OP_SIZE_BYTES)),
+        i!(Mov, indirect_register!(Ebx), reg!(Rcx)),
+        i!(
+            Mov,
+            reg!(Eax),
+            opexpr!(format!("[EAX+{}]", OP_SIZE_BYTES * 2))
+        ),
+        i!(Jmp, oplabel!(STD_CALL_FN_LABEL)),
+        i!(label!(CALL_COMPOSITION_LABEL)),
+        i!(Push, reg!(Rax)),
+        i!(Mov, reg!(Eax), opexpr!(format!("[EAX+{OP_SIZE_BYTES}]"))),
+        i!(Call, oplabel!(STD_CALL_FN_LABEL)),
+        i!(Pop, reg!(Rax)),
+        i!(
+            Mov,
+            reg!(Eax),
+            opexpr!(format!("[EAX+{}]", OP_SIZE_BYTES * 2))
+        ),
+        i!(Jmp, oplabel!(STD_CALL_FN_LABEL)),
+        i!(label!(CALL_CODE_LABEL)),
+        i!(Jmp, reg!(Rax)),
+        // curry: makes a closure which puts a value on the stack and applies a quotation
+        i!(label!(STD_CURRY_FN_LABEL)),
+        i!(Mov, reg!(Edx), Op::Literal(CURRY_KIND)),
+        i!(Jmp, oplabel!(MAKE_CLOSURE_LABEL)),
+        // compose: makes a closure which applies two quotations one after another
+        i!(label!(STD_COMPOSE_FN_LABEL)),
+        i!(Mov, reg!(Edx), Op::Literal(COMPOSE_KIND)),
+        i!(Jmp, oplabel!(MAKE_CLOSURE_LABEL)),
+        // replaces two operands on the top of the stack with a closure of the kind from edx
+        i!(label!(MAKE_CLOSURE_LABEL)),
+        i!(Push, reg!(Rdx)),
+        i!(Sub, reg!(Ebx), Op::Literal(OP_SIZE_BYTES)),
+        i!(
+            Mov,
+            indirect_register!(Ebx),
+            opexpr!(format!("dword {CLOSURE_CELLS}"))
+        ),
+        i!(Call, oplabel!(STD_ALLOC_FN_LABEL)),
+        i!(Pop, reg!(Rdx)),
+        i!(Mov, reg!(Eax), indirect_register!(Ebx)),
+        i!(Add, reg!(Ebx), Op::Literal(OP_SIZE_BYTES)),
+        i!(
+            Mov,
+            opexpr!(format!("dword [EAX-{OP_SIZE_BYTES}]")),
+            Op::Literal(CLOSURE_HEADER)
+        ),
+        i!(Mov, indirect_register!(Eax), reg!(Edx)),
+        i!(Mov, reg!(Rcx), opexpr!(format!("[EBX+{OP_SIZE_BYTES}]"))),
+        i!(Mov, opexpr!(format!("[EAX+{OP_SIZE_BYTES}]")), reg!(Rcx)),
+        i!(Mov, reg!(Rcx), indirect_register!(Ebx)),
+        i!(
+            Mov,
+            opexpr!(format!("[EAX+{}]", OP_SIZE_BYTES * 2)),
+            reg!(Rcx)
+        ),
+        i!(Add, reg!(Ebx), Op::Literal(OP_SIZE_BYTES)),
+        i!(Mov, indirect_register!(Ebx), reg!(Rax)),
+        i!(Ret),
+        // index error: jumped to by the bounds checks
+        i!(label!(STD_INDEX_ERROR_LABEL)),
+        i!(Mov, reg!(Rsi), oplabel!(INDEX_ERROR_LABEL.to_string())),
+        i!(Jmp, oplabel!(ABORT_LABEL)),
+        // abort: prints the message pointed by rsi to stderr and exits with an error
+        i!(label!(ABORT_LABEL)),
+        i!(And, reg!(Rsp), Op::Literal(-16)),
+        i!(Mov, reg!(Rdi), Op::Literal(STDERR_FD)),
+        i!(Xor, reg!(Rax), reg!(Rax)),
+        i!(Call, oplabel!(LIBC_DPRINTF_LABEL.to_string())),
+        i!(Mov, reg!(Rdi), Op::Literal(ABORT_EXIT_CODE)),
+        i!(Call, oplabel!(LIBC_EXIT_LABEL.to_string())),
         // exit
         i!(label!(STD_EXIT_FN_LABEL)),
         i!(And, reg!(Rsp), Op::Literal(-16)),
         i!(Call, oplabel!(LIBC_EXIT_LABEL.to_string())),
     ];
 
-    Asm::new(rodata, bss, text, vec![])
+    Asm::new(rodata, vec![], bss, text, vec![])
+}
+
+fn error_message(label: &str, message: &str) -> Instruction {
+    i!(
+        label!(label),
+        dd!(Db),
+        opstring!(message.to_string()),
+        Op::Literal(10),
+        Op::Literal(0)
+    )
 }
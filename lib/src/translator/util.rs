@@ -12,3 +12,20 @@ impl LabelGenerator {
         format!("$lb{}", self.count)
     }
 }
+
+/// Bindings get a `$bind_` prefix so they can't clash with nasm keywords or our own labels;
+/// module separators `::` become `$` and non-ascii characters, which nasm doesn't accept
+/// in labels, become `$$<code>$`.
+pub fn binding_label(identifier: &str) -> String {
+    let mut label = "$bind_".to_string();
+
+    for c in identifier.replace("::", "$").chars() {
+        if c.is_ascii() {
+            label.push(c);
+        } else {
+            label.push_str(&format!("$${:x}$", c as u32));
+        }
+    }
+
+    label
+}
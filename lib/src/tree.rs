@@ -0,0 +1,84 @@
+use std::fmt::Write;
+
+use crate::common::{Ast, Term};
+
+impl Ast {
+    /// Draws the terms as a tree, one term per line; lists and macros are nested.
+    ///
+    /// ```text
+    /// program
+    /// ├── [ ]
+    /// │   ├── dup
+    /// │   └── *
+    /// └── :sq
+    /// ```
+    pub fn to_tree(&self) -> String {
+        let mut tree = String::from("program\n");
+        draw(&mut tree, &self.terms, "");
+        tree
+    }
+}
+
+fn draw(tree: &mut String, terms: &[Term], indent: &str) {
+    for (i, term) in terms.iter().enumerate() {
+        let last = i + 1 == terms.len();
+        let (branch, nested_indent) = if last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        let _ = writeln!(tree, "{}{}{}", indent, branch, label(term));
+
+        if let Term::List { terms } | Term::Macro { terms, .. } = term {
+            draw(tree, terms, &format!("{}{}", indent, nested_indent));
+        }
+    }
+}
+
+fn label(term: &Term) -> String {
+    match term {
+        Term::Int(x) => x.to_string(),
+        Term::List { .. } => "[ ]".to_string(),
+        Term::Array { values } => {
+            let values: Vec<_> = values.iter().map(|x| x.to_string()).collect();
+            format!("{{ {} }}", values.join(" "))
+        }
+        Term::Bind { identifier } => format!(":{}", identifier),
+        Term::Put { identifier } => identifier.clone(),
+        Term::Include { path } => format!("include \"{}\"", path),
+        Term::Macro { name, .. } => format!("macro {}", name),
+        term => term.keyword().unwrap_or_default().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse;
+
+    #[test]
+    fn tree() {
+        let ast = parse("1 [ dup [ 2 ] ! ] :f { 1 2 } x macro m [ . ]").unwrap();
+        assert_eq!(
+            ast.to_tree(),
+            "\
+program
+├── 1
+├── [ ]
+│   ├── dup
+│   ├── [ ]
+│   │   └── 2
+│   └── !
+├── :f
+├── { 1 2 }
+├── x
+└── macro m
+    └── .
+"
+        );
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(parse("").unwrap().to_tree(), "program\n");
+    }
+}
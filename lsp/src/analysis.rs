@@ -0,0 +1,531 @@
+use std::{collections::BTreeMap, path::Path};
+
+use lib::TokenKind;
+use lib::{Node, Span, Term};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, DiagnosticSeverity, Hover, HoverContents, MarkupContent,
+    MarkupKind, NumberOrString, Range, SemanticToken, SemanticTokenModifier, SemanticTokenType,
+    SemanticTokensLegend,
+};
+
+use crate::document::{position, range};
+
+/// The file name the loader gives to a program which is not read from a file.
+const SOURCE_FILE: &str = "<stdin>";
+
+/// Limits each `const-eval`, as the diagnostics are computed on every change of the document.
+const CONST_EVAL_FUEL: u64 = 100_000;
+
+/// The errors of the program, or its lints if it compiles.
+///
+/// Errors in the included files are shown at the start of the document.
+pub fn diagnostics(text: &str, path: Option<&Path>) -> Vec<lsp_types::Diagnostic> {
+    let dir = path.and_then(Path::parent).unwrap_or(Path::new("."));
+    let mut loader = lib::Loader::new(Vec::new(), true).with_const_eval_fuel(CONST_EVAL_FUEL);
+
+    let diagnostics = match loader.load_source(text, dir) {
+        Ok(_) => lib::lint(text, &lib::LintLevels::default(), true).unwrap_or_default(),
+        Err(e) => vec![lib::Diagnostic::from_error(&e)],
+    };
+
+    diagnostics
+        .into_iter()
+        .map(|x| diagnostic(text, x))
+        .collect()
+}
+
+fn diagnostic(text: &str, diagnostic: lib::Diagnostic) -> lsp_types::Diagnostic {
+    let mut message = diagnostic.message;
+    let range = match (&diagnostic.file, &diagnostic.span) {
+        (Some(file), _) if file != SOURCE_FILE => {
+            message = format!("{}: {}", file, message);
+            Range::default()
+        }
+        (_, Some(span)) => range(
+            text,
+            Span {
+                start: span.byte_start,
+                end: span.byte_end,
+            },
+        ),
+        (_, None) => Range::default(),
+    };
+    for note in diagnostic.notes {
+        message += &format!("\nnote: {}", note);
+    }
+
+    lsp_types::Diagnostic {
+        range,
+        severity: Some(match diagnostic.severity {
+            lib::Severity::Error => DiagnosticSeverity::ERROR,
+            lib::Severity::Warning => DiagnosticSeverity::WARNING,
+        }),
+        code: diagnostic.code.map(NumberOrString::String),
+        source: Some("plc".to_string()),
+        message,
+        ..lsp_types::Diagnostic::default()
+    }
+}
+
+/// The stack effect of the term under the cursor and the stack depth around it.
+pub fn hover(text: &str, offset: usize) -> Option<Hover> {
+    let nodes = lib::parse_nodes(text).ok()?;
+    let node = node_at(&nodes, offset)?;
+    let prelude = Prelude::new();
+
+    let depth = lib::stack_depths(&nodes, &prelude.nodes)
+        .into_iter()
+        .find(|x| x.span == node.span)?;
+    let nested = parent_list(&nodes, node.span).is_some();
+
+    let label = match &node.term {
+        Term::List { .. } => "[ ]",
+        Term::Macro { .. } => "macro",
+        _ => &text[node.span.start..node.span.end],
+    };
+    let effect = match &node.term {
+        Term::Put { identifier } if !is_bound(&nodes, identifier) => prelude
+            .comment(identifier)
+            .unwrap_or(node.term.stack_effect().notation),
+        term => term.stack_effect().notation,
+    };
+    let show = |depth: Option<i32>| depth.map_or("?".to_string(), |x| x.to_string());
+
+    let mut value = format!(
+        "```plc\n{} {}\n```\nstack depth: {} → {}",
+        label,
+        effect,
+        show(depth.before),
+        show(depth.after)
+    );
+    if nested {
+        value += ", counted from the start of the list";
+    }
+
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value,
+        }),
+        range: Some(range(text, node.span)),
+    })
+}
+
+/// Where the name under the cursor is bound: the closest binding before the use,
+/// otherwise the first one after it, e.g. for a recursive word.
+pub fn definition(text: &str, offset: usize) -> Option<Span> {
+    let nodes = lib::parse_nodes(text).ok()?;
+    let Term::Put { identifier } = &node_at(&nodes, offset)?.term else {
+        return None;
+    };
+
+    let mut bindings = Vec::new();
+    walk(&nodes, &mut |node| match &node.term {
+        Term::Bind { identifier: x } | Term::Macro { name: x, .. } if x == identifier => {
+            bindings.push(node.span)
+        }
+        _ => {}
+    });
+
+    bindings
+        .iter()
+        .rev()
+        .find(|x| x.start < offset)
+        .or(bindings.first())
+        .copied()
+}
+
+/// The edits which rename the name under the cursor, at its bindings and uses.
+pub fn rename(text: &str, offset: usize, new_name: &str) -> Result<Vec<(Span, String)>, String> {
+    let nodes = lib::parse_nodes(text).map_err(|e| e.to_string())?;
+    let identifier = match node_at(&nodes, offset).map(|x| &x.term) {
+        Some(
+            Term::Put { identifier }
+            | Term::Bind { identifier }
+            | Term::Macro {
+                name: identifier, ..
+            },
+        ) => identifier,
+        _ => return Err("there is no name under the cursor".to_string()),
+    };
+
+    let put = lib::parse(new_name).ok().map(|x| x.terms);
+    if put
+        != Some(vec![Term::Put {
+            identifier: new_name.to_string(),
+        }])
+    {
+        return Err(format!("`{}` is not a valid name", new_name));
+    }
+
+    let mut edits = Vec::new();
+    walk(&nodes, &mut |node| match &node.term {
+        Term::Put { identifier: x } if x == identifier => edits.push(node.span),
+        Term::Bind { identifier: x } if x == identifier => edits.push(Span {
+            start: node.span.start + 1,
+            end: node.span.end,
+        }),
+        Term::Macro { name, .. } if name == identifier => {
+            // the name may be preceded by comments
+            let tokens = lib::tokenize(&text[node.span.start..node.span.end]);
+            if let Some(token) = tokens.iter().find(|x| x.kind == TokenKind::Binding) {
+                edits.push(Span {
+                    start: node.span.start + token.span.start,
+                    end: node.span.start + token.span.end,
+                });
+            }
+        }
+        _ => {}
+    });
+
+    Ok(edits
+        .into_iter()
+        .map(|x| (x, new_name.to_string()))
+        .collect())
+}
+
+/// The keywords, the names bound in the document and the prelude words.
+pub fn completion(text: &str) -> Vec<CompletionItem> {
+    let mut items: Vec<_> = lib::KEYWORD_TERMS
+        .iter()
+        .filter_map(|x| Some((x.keyword()?, x.stack_effect().notation)))
+        .chain([("include", "\"path\""), ("macro", "name [ ... ]")])
+        .map(|(label, detail)| CompletionItem {
+            label: label.to_string(),
+            kind: Some(CompletionItemKind::KEYWORD),
+            detail: Some(detail.to_string()),
+            ..CompletionItem::default()
+        })
+        .collect();
+
+    let mut names = BTreeMap::new();
+    match lib::parse_nodes(text) {
+        Ok(nodes) => walk(&nodes, &mut |node| match &node.term {
+            Term::Bind { identifier } => {
+                names.insert(identifier.clone(), CompletionItemKind::VARIABLE);
+            }
+            Term::Macro { name, .. } => {
+                names.insert(name.clone(), CompletionItemKind::FUNCTION);
+            }
+            _ => {}
+        }),
+        // a document being edited often doesn't parse, its bindings are still useful
+        Err(_) => {
+            for word in text.split_whitespace() {
+                if let Some(name) = word.strip_prefix(':').filter(|x| !x.is_empty()) {
+                    names.insert(name.to_string(), CompletionItemKind::VARIABLE);
+                }
+            }
+        }
+    }
+    let prelude = Prelude::new();
+    // a name bound in the document shadows the prelude word
+    let words: Vec<_> = prelude
+        .words()
+        .filter(|(name, _)| !names.contains_key(*name))
+        .collect();
+
+    items.extend(names.into_iter().map(|(name, kind)| CompletionItem {
+        label: name,
+        kind: Some(kind),
+        ..CompletionItem::default()
+    }));
+    items.extend(words.into_iter().map(|(name, comment)| CompletionItem {
+        label: name.to_string(),
+        kind: Some(CompletionItemKind::FUNCTION),
+        detail: comment.map(str::to_string),
+        ..CompletionItem::default()
+    }));
+
+    items
+}
+
+/// The semantic token types, in the order of their indices in [`semantic_tokens`].
+const TOKEN_TYPES: [SemanticTokenType; 6] = [
+    SemanticTokenType::NUMBER,
+    SemanticTokenType::OPERATOR,
+    SemanticTokenType::KEYWORD,
+    SemanticTokenType::VARIABLE,
+    SemanticTokenType::COMMENT,
+    SemanticTokenType::STRING,
+];
+
+pub fn semantic_tokens_legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: TOKEN_TYPES.to_vec(),
+        token_modifiers: vec![SemanticTokenModifier::DECLARATION],
+    }
+}
+
+/// The tokens of [`lib::tokenize`], a binding is a variable with the declaration modifier.
+/// Brackets and invalid text are left to the TextMate grammar.
+pub fn semantic_tokens(text: &str) -> Vec<SemanticToken> {
+    let mut tokens = Vec::new();
+    let mut previous = lsp_types::Position::default();
+
+    for token in lib::tokenize(text) {
+        let (token_type, modifiers) = match token.kind {
+            TokenKind::Number => (0, 0),
+            TokenKind::Operator => (1, 0),
+            TokenKind::Keyword => (2, 0),
+            TokenKind::Identifier => (3, 0),
+            TokenKind::Binding => (3, 1),
+            TokenKind::Comment => (4, 0),
+            TokenKind::String => (5, 0),
+            TokenKind::Bracket | TokenKind::Invalid => continue,
+        };
+
+        // tokens don't span lines, so the length is the difference of the columns
+        let start = position(text, token.span.start);
+        let end = position(text, token.span.end);
+        tokens.push(SemanticToken {
+            delta_line: start.line - previous.line,
+            delta_start: if start.line == previous.line {
+                start.character - previous.character
+            } else {
+                start.character
+            },
+            length: end.character - start.character,
+            token_type,
+            token_modifiers_bitset: modifiers,
+        });
+        previous = start;
+    }
+
+    tokens
+}
+
+/// The definitions of the prelude words and their stack effects from the comments.
+struct Prelude {
+    nodes: Vec<Node>,
+}
+
+impl Prelude {
+    fn new() -> Prelude {
+        Prelude {
+            nodes: lib::parse_nodes(lib::PRELUDE).expect("prelude must be a valid program"),
+        }
+    }
+
+    fn words(&self) -> impl Iterator<Item = (&str, Option<&'static str>)> {
+        self.nodes.iter().filter_map(|node| match &node.term {
+            Term::Bind { identifier } => Some((identifier.as_str(), comment(node.span.end))),
+            _ => None,
+        })
+    }
+
+    fn comment(&self, name: &str) -> Option<&'static str> {
+        self.words().find(|x| x.0 == name).and_then(|x| x.1)
+    }
+}
+
+/// The comment at the end of the prelude line, e.g. `( a -- |a| )`.
+fn comment(offset: usize) -> Option<&'static str> {
+    let rest = &lib::PRELUDE[offset..];
+    let line = &rest[..rest.find('\n').unwrap_or(rest.len())];
+
+    line.split_once('#').map(|x| x.1.trim())
+}
+
+/// The innermost node under the cursor.
+fn node_at(nodes: &[Node], offset: usize) -> Option<&Node> {
+    let node = nodes
+        .iter()
+        .find(|x| x.span.start <= offset && offset < x.span.end)?;
+
+    node_at(&node.children, offset).or(Some(node))
+}
+
+fn parent_list(nodes: &[Node], span: Span) -> Option<&Node> {
+    let node = nodes
+        .iter()
+        .find(|x| x.span.start <= span.start && span.end <= x.span.end && x.span != span)?;
+
+    parent_list(&node.children, span).or(Some(node))
+}
+
+fn is_bound(nodes: &[Node], identifier: &str) -> bool {
+    let mut bound = false;
+    walk(nodes, &mut |node| match &node.term {
+        Term::Bind { identifier: x } | Term::Macro { name: x, .. } => bound |= x == identifier,
+        _ => {}
+    });
+    bound
+}
+
+fn walk<'a>(nodes: &'a [Node], f: &mut impl FnMut(&'a Node)) {
+    for node in nodes {
+        f(node);
+        walk(&node.children, f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::Position;
+
+    use super::*;
+
+    fn hover_text(text: &str, offset: usize) -> String {
+        match hover(text, offset).unwrap().contents {
+            HoverContents::Markup(markup) => markup.value,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn diagnostics_of_errors_and_lints() {
+        let errors = diagnostics("1 :x\n y .", None);
+        assert_eq!(1, errors.len());
+        assert_eq!("`y` is not bound", errors[0].message);
+        assert_eq!(
+            Range::new(Position::new(1, 1), Position::new(1, 2)),
+            errors[0].range
+        );
+        assert_eq!(Some(DiagnosticSeverity::ERROR), errors[0].severity);
+        assert_eq!(
+            Some(NumberOrString::String("E0002".to_string())),
+            errors[0].code
+        );
+
+        let lints = diagnostics("1 :x 2 .", None);
+        assert_eq!(1, lints.len());
+        assert_eq!(Some(DiagnosticSeverity::WARNING), lints[0].severity);
+        assert_eq!(
+            Some(NumberOrString::String("unused_binding".to_string())),
+            lints[0].code
+        );
+
+        assert!(diagnostics("1 2 + .", None).is_empty());
+    }
+
+    #[test]
+    fn hover_shows_effect_and_depth() {
+        let text = "1 2 swap [ dup * ] ! -3 abs!";
+        assert_eq!(
+            "```plc\nswap ( a b -- b a )\n```\nstack depth: 2 → 2",
+            hover_text(text, text.find("swap").unwrap() + 1)
+        );
+        assert_eq!(
+            "```plc\ndup ( a -- a a )\n```\nstack depth: 0 → 1, counted from the start of the list",
+            hover_text(text, text.find("dup").unwrap())
+        );
+        assert_eq!(
+            "```plc\n! ( .. [q] -- .. )\n```\nstack depth: 3 → 2",
+            hover_text(text, text.find('!').unwrap())
+        );
+        assert_eq!(
+            "```plc\nabs ( a -- |a| )\n```\nstack depth: 3 → 4",
+            hover_text(text, text.find("abs").unwrap())
+        );
+        assert!(hover("1 [", 0).is_none());
+        assert!(hover("1 2", 1).is_none());
+    }
+
+    #[test]
+    fn definition_of_names() {
+        let text = "[ dup * ] :sq 1 :x x sq! 2 :x x [ x f! ] :f";
+        let span = |offset| definition(text, offset).map(|x: Span| &text[x.start..x.end]);
+
+        assert_eq!(Some(":sq"), span(text.find("sq!").unwrap()));
+        let uses: Vec<_> = text.match_indices(" x ").map(|x| x.0 + 1).collect();
+        assert_eq!(Some(":x"), span(uses[0]));
+        assert_eq!(
+            Some(text.rfind(":x").unwrap()),
+            definition(text, uses[1]).map(|x| x.start)
+        );
+        assert_eq!(Some(":f"), span(text.find("f!").unwrap()));
+        assert_eq!(None, span(0));
+    }
+
+    #[test]
+    fn rename_bindings_and_uses() {
+        fn renamed(text: &str, offset: usize, new_name: &str) -> String {
+            rename(text, offset, new_name).unwrap().iter().rev().fold(
+                text.to_string(),
+                |mut text, (span, name)| {
+                    text.replace_range(span.start..span.end, name);
+                    text
+                },
+            )
+        }
+
+        let text = "1 :x x . [ x ] ! macro m [ x ] m";
+        assert_eq!(
+            "1 :y y . [ y ] ! macro m [ y ] m",
+            renamed(text, text.find(":x").unwrap(), "y")
+        );
+        assert_eq!(
+            "1 :x x . [ x ] ! macro sq [ x ] sq",
+            renamed(text, text.rfind('m').unwrap(), "sq")
+        );
+
+        assert!(rename(text, text.find(":x").unwrap(), "dup").is_err());
+        assert!(rename(text, text.find(":x").unwrap(), "a b").is_err());
+        assert!(rename(text, 0, "y").is_err());
+
+        let text = "macro # m\n m [ dup * ] 3 m";
+        assert_eq!(
+            "macro # m\n sq [ dup * ] 3 sq",
+            renamed(text, text.rfind('m').unwrap(), "sq")
+        );
+    }
+
+    #[test]
+    fn semantic_tokens_are_relative() {
+        let tokens: Vec<_> = semantic_tokens("1 :x # 😀\n  x y [ . ]")
+            .into_iter()
+            .map(|x| {
+                (
+                    x.delta_line,
+                    x.delta_start,
+                    x.length,
+                    x.token_type,
+                    x.token_modifiers_bitset,
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            vec![
+                (0, 0, 1, 0, 0),
+                (0, 2, 2, 3, 1),
+                (0, 3, 4, 4, 0),
+                (1, 2, 1, 3, 0),
+                (0, 2, 1, 3, 0),
+                (0, 4, 1, 1, 0),
+            ],
+            tokens
+        );
+        assert_eq!(
+            TOKEN_TYPES.len(),
+            semantic_tokens_legend().token_types.len()
+        );
+    }
+
+    #[test]
+    fn completion_items() {
+        let labels: Vec<_> = completion("[ 1 ] :one macro two [ 2 ]")
+            .into_iter()
+            .map(|x| x.label)
+            .collect();
+        for label in [
+            "dup",
+            "@=",
+            "const-eval",
+            "include",
+            "one",
+            "two",
+            "abs",
+            "loop",
+        ] {
+            assert!(labels.contains(&label.to_string()), "{}", label);
+        }
+
+        let labels: Vec<_> = completion("1 :x [ x")
+            .into_iter()
+            .map(|x| x.label)
+            .collect();
+        assert!(labels.contains(&"x".to_string()));
+    }
+}
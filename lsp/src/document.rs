@@ -0,0 +1,55 @@
+use lsp_types::{Position, Range};
+
+/// The position of a byte offset; LSP counts the characters of a line in UTF-16 code units.
+pub fn position(text: &str, offset: usize) -> Position {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |x| x + 1);
+
+    Position {
+        line: before.matches('\n').count() as u32,
+        character: before[line_start..].encode_utf16().count() as u32,
+    }
+}
+
+/// The byte offset of a position, clamped to the line and to the text.
+pub fn offset(text: &str, position: Position) -> usize {
+    let line_start = text
+        .split_inclusive('\n')
+        .take(position.line as usize)
+        .map(str::len)
+        .sum::<usize>();
+    let line = text[line_start..].split('\n').next().unwrap_or_default();
+
+    let mut units = 0;
+    for (i, c) in line.char_indices() {
+        if units >= position.character as usize {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+
+    line_start + line.len()
+}
+
+pub fn range(text: &str, span: lib::Span) -> Range {
+    Range {
+        start: position(text, span.start),
+        end: position(text, span.end),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions() {
+        let text = "1 2\n+ ж 😀 x .";
+        let x = text.find('x').unwrap();
+        assert_eq!(Position::new(1, 7), position(text, x));
+        assert_eq!(x, offset(text, Position::new(1, 7)));
+        assert_eq!(Position::new(0, 0), position(text, 0));
+        assert_eq!(3, offset(text, Position::new(0, 100)));
+        assert_eq!(text.len(), offset(text, Position::new(5, 0)));
+    }
+}
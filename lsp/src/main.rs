@@ -0,0 +1,368 @@
+mod analysis;
+mod document;
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+        PublishDiagnostics,
+    },
+    request::{
+        Completion, GotoDefinition, HoverRequest, Rename, Request as _, SemanticTokensFullRequest,
+    },
+    CompletionParams, CompletionResponse, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, GotoDefinitionParams, GotoDefinitionResponse, HoverParams,
+    HoverProviderCapability, Location, OneOf, PublishDiagnosticsParams, RenameParams,
+    SemanticTokens, SemanticTokensFullOptions, SemanticTokensOptions, SemanticTokensParams,
+    SemanticTokensResult, SemanticTokensServerCapabilities, ServerCapabilities,
+    TextDocumentSyncCapability, TextDocumentSyncKind, TextEdit, Url, WorkspaceEdit,
+};
+use serde::de::DeserializeOwned;
+
+fn main() -> Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    serve(&connection)?;
+    io_threads.join()?;
+
+    Ok(())
+}
+
+fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        rename_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(lsp_types::CompletionOptions::default()),
+        semantic_tokens_provider: Some(SemanticTokensServerCapabilities::SemanticTokensOptions(
+            SemanticTokensOptions {
+                legend: analysis::semantic_tokens_legend(),
+                full: Some(SemanticTokensFullOptions::Bool(true)),
+                ..SemanticTokensOptions::default()
+            },
+        )),
+        ..ServerCapabilities::default()
+    }
+}
+
+/// Answers the client until it shuts the server down.
+fn serve(connection: &Connection) -> Result<()> {
+    connection.initialize(serde_json::to_value(capabilities())?)?;
+
+    let mut server = Server {
+        connection,
+        documents: HashMap::new(),
+    };
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+                server.request(request)?;
+            }
+            Message::Notification(notification) => server.notification(notification)?,
+            Message::Response(_) => {}
+        }
+    }
+
+    Ok(())
+}
+
+struct Server<'c> {
+    connection: &'c Connection,
+    /// The texts of the open documents, which may differ from the files on disk
+    documents: HashMap<Url, String>,
+}
+
+impl Server<'_> {
+    fn notification(&mut self, notification: Notification) -> Result<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let Some(params) = params::<DidOpenTextDocumentParams>(notification) else {
+                    return Ok(());
+                };
+                self.update(params.text_document.uri, params.text_document.text)
+            }
+            DidChangeTextDocument::METHOD => {
+                let Some(mut params) = params::<DidChangeTextDocumentParams>(notification) else {
+                    return Ok(());
+                };
+                // the whole text is sent on every change
+                match params.content_changes.pop() {
+                    Some(change) => self.update(params.text_document.uri, change.text),
+                    None => Ok(()),
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let Some(params) = params::<DidCloseTextDocumentParams>(notification) else {
+                    return Ok(());
+                };
+                self.documents.remove(&params.text_document.uri);
+                self.publish(params.text_document.uri, Vec::new())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn update(&mut self, uri: Url, text: String) -> Result<()> {
+        let path = uri.to_file_path().ok();
+        let diagnostics = analysis::diagnostics(&text, path.as_deref());
+        self.documents.insert(uri.clone(), text);
+
+        self.publish(uri, diagnostics)
+    }
+
+    fn publish(&self, uri: Url, diagnostics: Vec<lsp_types::Diagnostic>) -> Result<()> {
+        let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+        self.send(Notification::new(PublishDiagnostics::METHOD.to_string(), params).into())
+    }
+
+    fn request(&self, request: Request) -> Result<()> {
+        let id = request.id.clone();
+        let response = self.respond(request).unwrap_or_else(|e| {
+            Response::new_err(id, ErrorCode::InvalidParams as i32, e.to_string())
+        });
+
+        self.send(response.into())
+    }
+
+    /// The response to the request, or the error of its malformed parameters.
+    fn respond(&self, request: Request) -> serde_json::Result<Response> {
+        let id = request.id;
+        let response = match request.method.as_str() {
+            HoverRequest::METHOD => {
+                let params: HoverParams = serde_json::from_value(request.params)?;
+                let position = params.text_document_position_params;
+                let hover = self
+                    .offset(&position.text_document.uri, position.position)
+                    .and_then(|(text, offset)| analysis::hover(text, offset));
+                Response::new_ok(id, hover)
+            }
+            GotoDefinition::METHOD => {
+                let params: GotoDefinitionParams = serde_json::from_value(request.params)?;
+                let position = params.text_document_position_params;
+                let uri = position.text_document.uri;
+                let location = self
+                    .offset(&uri, position.position)
+                    .and_then(|(text, offset)| {
+                        let span = analysis::definition(text, offset)?;
+                        Some(GotoDefinitionResponse::Scalar(Location::new(
+                            uri.clone(),
+                            document::range(text, span),
+                        )))
+                    });
+                Response::new_ok(id, location)
+            }
+            Rename::METHOD => {
+                let params: RenameParams = serde_json::from_value(request.params)?;
+                let position = params.text_document_position;
+                let uri = position.text_document.uri;
+                match self.offset(&uri, position.position) {
+                    Some((text, offset)) => {
+                        match analysis::rename(text, offset, &params.new_name) {
+                            Ok(edits) => {
+                                let edits = edits
+                                    .into_iter()
+                                    .map(|(span, name)| {
+                                        TextEdit::new(document::range(text, span), name)
+                                    })
+                                    .collect();
+                                Response::new_ok(
+                                    id,
+                                    WorkspaceEdit::new(HashMap::from([(uri, edits)])),
+                                )
+                            }
+                            Err(message) => {
+                                Response::new_err(id, ErrorCode::InvalidParams as i32, message)
+                            }
+                        }
+                    }
+                    None => Response::new_ok(id, serde_json::Value::Null),
+                }
+            }
+            Completion::METHOD => {
+                let params: CompletionParams = serde_json::from_value(request.params)?;
+                let uri = params.text_document_position.text_document.uri;
+                let items = self
+                    .documents
+                    .get(&uri)
+                    .map(|text| CompletionResponse::Array(analysis::completion(text)));
+                Response::new_ok(id, items)
+            }
+            SemanticTokensFullRequest::METHOD => {
+                let params: SemanticTokensParams = serde_json::from_value(request.params)?;
+                let tokens = self.documents.get(&params.text_document.uri).map(|text| {
+                    SemanticTokensResult::Tokens(SemanticTokens {
+                        result_id: None,
+                        data: analysis::semantic_tokens(text),
+                    })
+                });
+                Response::new_ok(id, tokens)
+            }
+            _ => Response::new_err(
+                id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unknown method `{}`", request.method),
+            ),
+        };
+
+        Ok(response)
+    }
+
+    fn offset(&self, uri: &Url, position: lsp_types::Position) -> Option<(&str, usize)> {
+        let text = self.documents.get(uri)?;
+        Some((text, document::offset(text, position)))
+    }
+
+    fn send(&self, message: Message) -> Result<()> {
+        self.connection.sender.send(message)?;
+        Ok(())
+    }
+}
+
+/// The parameters of the notification. Malformed ones are logged and the notification is skipped,
+/// as there is no response to report them in.
+fn params<P: DeserializeOwned>(notification: Notification) -> Option<P> {
+    match serde_json::from_value(notification.params) {
+        Ok(params) => Some(params),
+        Err(e) => {
+            eprintln!("invalid parameters of `{}`: {}", notification.method, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use lsp_server::RequestId;
+    use lsp_types::{notification::Initialized, request::Initialize};
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn request(id: i32, method: &str, params: Value) -> Message {
+        Request::new(RequestId::from(id), method.to_string(), params).into()
+    }
+
+    fn notification(method: &str, params: Value) -> Message {
+        Notification::new(method.to_string(), params).into()
+    }
+
+    fn response(client: &Connection) -> Response {
+        match client.receiver.recv().unwrap() {
+            Message::Response(response) => response,
+            message => panic!("expected a response, got {:?}", message),
+        }
+    }
+
+    #[test]
+    fn session() {
+        let (server, client) = Connection::memory();
+        let server = thread::spawn(move || serve(&server).unwrap());
+        let uri = "file:///tmp/main.plc";
+
+        client
+            .sender
+            .send(request(
+                1,
+                Initialize::METHOD,
+                json!({ "capabilities": {} }),
+            ))
+            .unwrap();
+        let initialized = response(&client).result.unwrap();
+        assert_eq!(json!(true), initialized["capabilities"]["hoverProvider"]);
+        client
+            .sender
+            .send(notification(Initialized::METHOD, json!({})))
+            .unwrap();
+
+        client
+            .sender
+            .send(notification(
+                DidOpenTextDocument::METHOD,
+                json!({ "textDocument": {
+                    "uri": uri, "languageId": "plc", "version": 1, "text": "1 :x\ny ."
+                }}),
+            ))
+            .unwrap();
+        let Message::Notification(published) = client.receiver.recv().unwrap() else {
+            panic!("expected diagnostics");
+        };
+        assert_eq!(PublishDiagnostics::METHOD, published.method);
+        assert_eq!(
+            json!("`y` is not bound"),
+            published.params["diagnostics"][0]["message"]
+        );
+
+        // malformed parameters are reported, and the server goes on
+        client
+            .sender
+            .send(notification(DidChangeTextDocument::METHOD, json!({})))
+            .unwrap();
+        client
+            .sender
+            .send(request(6, HoverRequest::METHOD, json!({})))
+            .unwrap();
+        assert_eq!(
+            ErrorCode::InvalidParams as i32,
+            response(&client).error.unwrap().code
+        );
+
+        client
+            .sender
+            .send(request(
+                2,
+                HoverRequest::METHOD,
+                json!({ "textDocument": { "uri": uri }, "position": { "line": 0, "character": 0 } }),
+            ))
+            .unwrap();
+        let hover = response(&client).result.unwrap();
+        assert!(hover["contents"]["value"]
+            .as_str()
+            .unwrap()
+            .contains("stack depth: 0 → 1"));
+
+        client
+            .sender
+            .send(request(
+                5,
+                SemanticTokensFullRequest::METHOD,
+                json!({ "textDocument": { "uri": uri } }),
+            ))
+            .unwrap();
+        let tokens = response(&client).result.unwrap();
+        // `1`, `:x`, `y` and `.`, five numbers each
+        assert_eq!(20, tokens["data"].as_array().unwrap().len());
+
+        client
+            .sender
+            .send(request(
+                3,
+                Rename::METHOD,
+                json!({
+                    "textDocument": { "uri": uri },
+                    "position": { "line": 0, "character": 2 },
+                    "newName": "dup"
+                }),
+            ))
+            .unwrap();
+        assert!(response(&client).error.is_some());
+
+        client
+            .sender
+            .send(request(4, "shutdown", Value::Null))
+            .unwrap();
+        response(&client);
+        client
+            .sender
+            .send(notification("exit", Value::Null))
+            .unwrap();
+        server.join().unwrap();
+    }
+}